@@ -5,11 +5,11 @@ use kira::{
 	instance::InstanceSettings,
 	manager::{AudioManager, AudioManagerSettings},
 	parameter::ParameterSettings,
-	sound::{Sound, SoundSettings},
+	sound::{SampleFormat, Sound, SoundSettings},
 	Frame,
 };
 
-fn create_test_sound(num_samples: usize) -> Sound {
+fn create_test_sound(num_samples: usize, sample_format: SampleFormat) -> Sound {
 	const SAMPLE_RATE: u32 = 48000;
 	let mut sine_samples = vec![];
 	let mut phase = 0.0;
@@ -23,6 +23,7 @@ fn create_test_sound(num_samples: usize) -> Sound {
 		SoundSettings {
 			cooldown: None,
 			default_loop_start: Some(0.0),
+			sample_format,
 			..Default::default()
 		},
 	)
@@ -40,7 +41,30 @@ fn instances_benchmark(c: &mut Criterion) {
 				..Default::default()
 			});
 		// add a test sound
-		let mut sound_handle = audio_manager.add_sound(create_test_sound(48000)).unwrap();
+		let mut sound_handle = audio_manager.add_sound(create_test_sound(48000, SampleFormat::F32)).unwrap();
+		backend.process();
+		// start a bunch of instances
+		for _ in 0..NUM_INSTANCES {
+			sound_handle.play(Default::default()).unwrap();
+		}
+		backend.process();
+		b.iter(|| backend.process());
+		drop(backend);
+		drop(audio_manager);
+	});
+
+	benchmark_group.bench_function("with adpcm samples", |b| {
+		const NUM_INSTANCES: usize = 100_000;
+		let (mut audio_manager, mut backend) =
+			AudioManager::new_without_audio_thread(AudioManagerSettings {
+				num_instances: NUM_INSTANCES,
+				num_commands: NUM_INSTANCES,
+				..Default::default()
+			});
+		// add a test sound
+		let mut sound_handle = audio_manager
+			.add_sound(create_test_sound(48000, SampleFormat::Adpcm))
+			.unwrap();
 		backend.process();
 		// start a bunch of instances
 		for _ in 0..NUM_INSTANCES {
@@ -70,7 +94,7 @@ fn instances_benchmark(c: &mut Criterion) {
 			.add_parameter(ParameterSettings::new().value(0.5))
 			.unwrap();
 		// add a test sound
-		let mut sound_handle = audio_manager.add_sound(create_test_sound(48000)).unwrap();
+		let mut sound_handle = audio_manager.add_sound(create_test_sound(48000, SampleFormat::F32)).unwrap();
 		backend.process();
 		// start a bunch of instances
 		for _ in 0..NUM_INSTANCES {
@@ -1,7 +1,7 @@
 use basedrop::Owned;
 
 use crate::{
-	sound::{handle::SoundHandle, Sound, SoundId},
+	sound::{handle::SoundHandle, InterpolationMode, Sound, SoundId},
 	static_container::index_map::StaticIndexMap,
 	util::inverse_lerp,
 	util::lerp,
@@ -73,6 +73,7 @@ impl SoundClip {
 		&self,
 		position: f64,
 		sounds: &StaticIndexMap<SoundId, Owned<Sound>>,
+		interpolation_mode: Option<InterpolationMode>,
 	) -> Frame {
 		if let Some(sound) = sounds.get(&self.sound_id) {
 			let relative_time =
@@ -80,11 +81,14 @@ impl SoundClip {
 			if relative_time < 0.0 || relative_time > 1.0 {
 				Frame::from_mono(0.0)
 			} else {
-				sound.get_frame_at_position(lerp(
-					self.sound_time_range.0,
-					self.sound_time_range.1,
-					relative_time,
-				))
+				sound.get_frame_at_position_with_interpolation(
+					lerp(
+						self.sound_time_range.0,
+						self.sound_time_range.1,
+						relative_time,
+					),
+					interpolation_mode.unwrap_or_else(|| sound.interpolation_mode()),
+				)
 			}
 		} else {
 			Frame::from_mono(0.0)
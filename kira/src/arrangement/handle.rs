@@ -1,13 +1,15 @@
 //! An interface for controlling arrangements.
 
+use std::sync::{Arc, Mutex, PoisonError};
+
 use crate::{
 	command::{
 		producer::{CommandError, CommandProducer},
 		InstanceCommand,
 	},
 	instance::{
-		handle::InstanceHandle, Instance, InstanceId, InstanceSettings, PauseInstanceSettings,
-		ResumeInstanceSettings, StopInstanceSettings,
+		handle::InstanceHandle, Instance, InstanceId, InstanceSettings, InstanceSlots,
+		PauseInstanceSettings, ResumeInstanceSettings, StopInstanceSettings,
 	},
 	mixer::TrackIndex,
 };
@@ -23,10 +25,15 @@ pub struct ArrangementHandle {
 	semantic_duration: Option<f64>,
 	default_loop_start: Option<f64>,
 	command_producer: CommandProducer,
+	instance_slots: Arc<Mutex<InstanceSlots>>,
 }
 
 impl ArrangementHandle {
-	pub(crate) fn new(arrangement: &Arrangement, command_producer: CommandProducer) -> Self {
+	pub(crate) fn new(
+		arrangement: &Arrangement,
+		command_producer: CommandProducer,
+		instance_slots: Arc<Mutex<InstanceSlots>>,
+	) -> Self {
 		Self {
 			id: arrangement.id(),
 			duration: arrangement.duration(),
@@ -34,6 +41,7 @@ impl ArrangementHandle {
 			semantic_duration: arrangement.semantic_duration(),
 			default_loop_start: arrangement.default_loop_start(),
 			command_producer,
+			instance_slots,
 		}
 	}
 
@@ -66,14 +74,28 @@ impl ArrangementHandle {
 		self.default_loop_start
 	}
 
-	/// Plays the arrangement.
-	pub fn play(&mut self, settings: InstanceSettings) -> Result<InstanceHandle, CommandError> {
-		let id = settings.id.unwrap_or(InstanceId::new());
+	fn create_instance(
+		&self,
+		settings: InstanceSettings,
+	) -> (InstanceId, Instance, InstanceHandle) {
+		let slot = self
+			.instance_slots
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.claim(settings.id);
+		let id = slot.id();
 		let instance = Instance::new(
 			self.id.into(),
 			self.duration,
 			None,
-			settings.into_internal(self.duration, self.default_loop_start, self.default_track),
+			settings.into_internal(
+				self.duration,
+				self.default_loop_start,
+				None,
+				self.default_track,
+			),
+			None,
+			slot,
 		);
 		let handle = InstanceHandle::new(
 			id,
@@ -81,11 +103,31 @@ impl ArrangementHandle {
 			instance.public_position(),
 			self.command_producer.clone(),
 		);
+		(id, instance, handle)
+	}
+
+	/// Plays the arrangement.
+	pub fn play(&mut self, settings: InstanceSettings) -> Result<InstanceHandle, CommandError> {
+		let (id, instance, handle) = self.create_instance(settings);
 		self.command_producer
 			.push(InstanceCommand::Play(id, instance).into())?;
 		Ok(handle)
 	}
 
+	/// Plays the arrangement when the audio clock reaches the given time.
+	///
+	/// See [`AudioManager::clock_time`](crate::manager::AudioManager::clock_time).
+	pub fn play_at(
+		&mut self,
+		settings: InstanceSettings,
+		clock_time: u64,
+	) -> Result<InstanceHandle, CommandError> {
+		let (id, instance, handle) = self.create_instance(settings);
+		self.command_producer
+			.push_scheduled(InstanceCommand::Play(id, instance), clock_time)?;
+		Ok(handle)
+	}
+
 	/// Pauses all instances of this arrangement.
 	pub fn pause(&mut self, settings: PauseInstanceSettings) -> Result<(), CommandError> {
 		self.command_producer
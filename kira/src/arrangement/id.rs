@@ -1,6 +1,6 @@
 use std::hash::Hash;
 
-use uuid::Uuid;
+use crate::id_counter::{IdCounter, IdTooLarge};
 
 use super::ArrangementHandle;
 
@@ -12,15 +12,24 @@ use super::ArrangementHandle;
 	serde(transparent)
 )]
 pub struct ArrangementId {
-	uuid: Uuid,
+	index: u64,
 }
 
+static NEXT_ARRANGEMENT_ID: IdCounter = IdCounter::new();
+
 impl ArrangementId {
 	pub(crate) fn new() -> Self {
 		Self {
-			uuid: Uuid::new_v4(),
+			index: NEXT_ARRANGEMENT_ID.next(),
 		}
 	}
+
+	/// Makes sure [`ArrangementId::new`] never returns this ID,
+	/// since it was chosen by the user.
+	pub(crate) fn claim(self) -> Result<Self, IdTooLarge> {
+		NEXT_ARRANGEMENT_ID.claim(self.index)?;
+		Ok(self)
+	}
 }
 
 impl From<&ArrangementHandle> for ArrangementId {
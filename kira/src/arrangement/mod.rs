@@ -138,7 +138,7 @@ pub use settings::{ArrangementSettings, LoopArrangementSettings};
 use crate::{
 	group::{groups::Groups, GroupId, GroupSet},
 	mixer::TrackIndex,
-	sound::{handle::SoundHandle, Sound, SoundId},
+	sound::{handle::SoundHandle, InterpolationMode, Sound, SoundId},
 	static_container::index_map::StaticIndexMap,
 	Frame,
 };
@@ -165,7 +165,7 @@ impl Arrangement {
 	/// Creates a new, empty arrangement.
 	pub fn new(settings: ArrangementSettings) -> Self {
 		Self {
-			id: settings.id.unwrap_or(ArrangementId::new()),
+			id: settings.id.unwrap_or_else(ArrangementId::new),
 			clips: vec![],
 			duration: 0.0,
 			default_track: settings.default_track,
@@ -279,15 +279,24 @@ impl Arrangement {
 		self.default_loop_start
 	}
 
+	/// Returns the IDs of the sounds used by the arrangement's clips.
+	pub(crate) fn sound_ids(&self) -> impl Iterator<Item = SoundId> + '_ {
+		self.clips.iter().map(|clip| clip.sound_id)
+	}
+
 	/// Gets the frame at the given position of the arrangement.
+	///
+	/// If `interpolation_mode` is `None`, each clip's sound
+	/// uses its own interpolation mode.
 	pub(crate) fn get_frame_at_position(
 		&self,
 		position: f64,
 		sounds: &StaticIndexMap<SoundId, Owned<Sound>>,
+		interpolation_mode: Option<InterpolationMode>,
 	) -> Frame {
 		let mut frame = Frame::from_mono(0.0);
 		for clip in &self.clips {
-			frame += clip.get_frame_at_position(position, sounds);
+			frame += clip.get_frame_at_position(position, sounds, interpolation_mode);
 		}
 		frame
 	}
@@ -318,4 +327,9 @@ impl Arrangement {
 	pub(crate) fn is_in_group(&self, id: GroupId, all_groups: &Groups) -> bool {
 		self.groups.has_ancestor(id, all_groups)
 	}
+
+	/// Returns if this arrangement is in an unpausable group.
+	pub(crate) fn is_unpausable(&self, all_groups: &Groups) -> bool {
+		self.groups.is_unpausable(all_groups)
+	}
 }
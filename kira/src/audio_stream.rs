@@ -10,7 +10,7 @@
 
 use std::fmt::Debug;
 
-use uuid::Uuid;
+use crate::static_container::slot_map::{SlotIndex, SlotKey};
 
 use crate::Frame;
 
@@ -24,6 +24,20 @@ pub trait AudioStream: Debug + Send + 'static {
 	///
 	/// `dt` represents how many seconds have elapsed since the last request.
 	fn next(&mut self, dt: f64) -> Frame;
+
+	/// Fills a block of frames with the next samples.
+	///
+	/// `dt` represents how many seconds elapse between each frame.
+	///
+	/// The audio thread always calls this function rather than
+	/// [`next`](AudioStream::next). By default, it calls `next` for each
+	/// frame, so streams only need to implement this if they can produce
+	/// a whole block more efficiently.
+	fn next_block(&mut self, dt: f64, frames: &mut [Frame]) {
+		for frame in frames {
+			*frame = self.next(dt);
+		}
+	}
 }
 
 /// A unique identifier for an [`AudioStream`](crate::audio_stream::AudioStream).
@@ -34,13 +48,15 @@ pub trait AudioStream: Debug + Send + 'static {
 	serde(transparent)
 )]
 pub struct AudioStreamId {
-	uuid: Uuid,
+	index: SlotIndex,
 }
 
-impl AudioStreamId {
-	pub(crate) fn new() -> Self {
-		Self {
-			uuid: Uuid::new_v4(),
-		}
+impl SlotKey for AudioStreamId {
+	fn from_slot_index(index: SlotIndex) -> Self {
+		Self { index }
+	}
+
+	fn slot_index(&self) -> SlotIndex {
+		self.index
 	}
 }
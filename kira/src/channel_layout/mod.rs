@@ -0,0 +1,297 @@
+//! Describes the speaker setups Kira can output audio to.
+//!
+//! Internally, Kira processes audio in stereo. When the output
+//! [`ChannelLayout`] has more than two channels, stereo audio
+//! is sent to the front left and right speakers, and mixer
+//! tracks and instances can be placed anywhere around the
+//! listener by giving them a surround angle (see
+//! [`SubTrackSettings::surround_angle`](crate::mixer::SubTrackSettings::surround_angle)
+//! and [`InstanceSettings::surround_angle`](crate::instance::InstanceSettings::surround_angle)).
+//!
+//! Effects always process stereo audio, so positioned tracks
+//! and instances are mixed into the output after the main
+//! track's effects.
+//!
+//! If the output device has fewer channels than the layout,
+//! the audio is downmixed to fit.
+
+#[cfg(test)]
+mod tests;
+
+use std::f64::consts::FRAC_PI_2;
+
+use crate::Frame;
+
+/// The largest number of channels any [`ChannelLayout`] has.
+pub(crate) const MAX_CHANNELS: usize = 8;
+
+// the gain used when folding the center and surround channels
+// into the front channels (about -3dB)
+const DOWNMIX_GAIN: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// An arrangement of output speakers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub enum ChannelLayout {
+	/// A single speaker.
+	Mono,
+	/// Left and right speakers.
+	Stereo,
+	/// Front left, front right, back left, and back right speakers.
+	Quad,
+	/// Front left, front right, center, LFE (subwoofer),
+	/// surround left, and surround right speakers.
+	Surround51,
+	/// Front left, front right, center, LFE (subwoofer),
+	/// back left, back right, side left, and side right speakers.
+	Surround71,
+}
+
+// `#[default]` on enum variants needs Rust 1.62, which is newer
+// than the crate's minimum supported version
+#[allow(clippy::derivable_impls)]
+impl Default for ChannelLayout {
+	fn default() -> Self {
+		Self::Stereo
+	}
+}
+
+impl ChannelLayout {
+	/// Returns the number of channels in the layout.
+	pub fn num_channels(self) -> usize {
+		self.speaker_angles().len()
+	}
+
+	/// Returns the angle of each speaker in the layout (in degrees),
+	/// in channel order.
+	///
+	/// 0 degrees is directly in front of the listener, and positive
+	/// angles are to the right. The LFE channel has no position,
+	/// so its angle is `None`.
+	pub fn speaker_angles(self) -> &'static [Option<f64>] {
+		match self {
+			ChannelLayout::Mono => &[Some(0.0)],
+			ChannelLayout::Stereo => &[Some(-30.0), Some(30.0)],
+			ChannelLayout::Quad => &[Some(-45.0), Some(45.0), Some(-135.0), Some(135.0)],
+			ChannelLayout::Surround51 => &[
+				Some(-30.0),
+				Some(30.0),
+				Some(0.0),
+				None,
+				Some(-110.0),
+				Some(110.0),
+			],
+			ChannelLayout::Surround71 => &[
+				Some(-30.0),
+				Some(30.0),
+				Some(0.0),
+				None,
+				Some(-150.0),
+				Some(150.0),
+				Some(-90.0),
+				Some(90.0),
+			],
+		}
+	}
+
+	/// Adds a stereo frame to the front speakers of a multichannel
+	/// frame.
+	pub(crate) fn upmix(self, input: Frame, out: &mut [f32]) {
+		match self {
+			ChannelLayout::Mono => out[0] += (input.left + input.right) / 2.0,
+			_ => {
+				out[0] += input.left;
+				out[1] += input.right;
+			}
+		}
+	}
+
+	/// Mixes a stereo frame down to mono and adds it to a multichannel
+	/// frame, placed at the given angle (in degrees).
+	///
+	/// The sound is panned between the two speakers closest to the
+	/// angle using constant power panning.
+	pub(crate) fn pan(self, input: Frame, angle: f64, out: &mut [f32]) {
+		let mono = (input.left + input.right) / 2.0;
+		let angle = wrap_angle(angle);
+		if let ChannelLayout::Mono = self {
+			out[0] += mono;
+			return;
+		}
+		if let ChannelLayout::Stereo = self {
+			// there are no rear speakers, so sounds behind the listener
+			// are mirrored to the front
+			let angle = if angle > 90.0 {
+				180.0 - angle
+			} else if angle < -90.0 {
+				-180.0 - angle
+			} else {
+				angle
+			};
+			let x = (angle + 90.0) / 180.0;
+			out[0] += mono * (x * FRAC_PI_2).cos() as f32;
+			out[1] += mono * (x * FRAC_PI_2).sin() as f32;
+			return;
+		}
+		// find the speakers on either side of the angle
+		let speakers = self.speaker_angles();
+		let mut left = None;
+		let mut right = None;
+		for (channel, speaker_angle) in speakers.iter().enumerate() {
+			let speaker_angle = match speaker_angle {
+				Some(speaker_angle) => *speaker_angle,
+				None => continue,
+			};
+			// how far clockwise (to the right) the sound is from the speaker
+			let distance = (angle - speaker_angle).rem_euclid(360.0);
+			match left {
+				Some((_, closest)) if closest <= distance => {}
+				_ => left = Some((channel, distance)),
+			}
+			let distance = (speaker_angle - angle).rem_euclid(360.0);
+			match right {
+				Some((_, closest)) if closest <= distance => {}
+				_ => right = Some((channel, distance)),
+			}
+		}
+		let ((left, left_distance), (right, right_distance)) = match (left, right) {
+			(Some(left), Some(right)) => (left, right),
+			_ => return,
+		};
+		if left == right {
+			out[left] += mono;
+			return;
+		}
+		let x = left_distance / (left_distance + right_distance);
+		out[left] += mono * (x * FRAC_PI_2).cos() as f32;
+		out[right] += mono * (x * FRAC_PI_2).sin() as f32;
+	}
+
+	/// Mixes a multichannel frame down to a stereo frame.
+	pub(crate) fn downmix_to_stereo(self, input: &[f32]) -> Frame {
+		match self {
+			ChannelLayout::Mono => Frame::from_mono(input[0]),
+			ChannelLayout::Stereo => Frame::new(input[0], input[1]),
+			ChannelLayout::Quad => Frame::new(
+				input[0] + input[2] * DOWNMIX_GAIN,
+				input[1] + input[3] * DOWNMIX_GAIN,
+			),
+			ChannelLayout::Surround51 => {
+				let center = input[2] * DOWNMIX_GAIN;
+				Frame::new(
+					input[0] + center + input[4] * DOWNMIX_GAIN,
+					input[1] + center + input[5] * DOWNMIX_GAIN,
+				)
+			}
+			ChannelLayout::Surround71 => {
+				let center = input[2] * DOWNMIX_GAIN;
+				Frame::new(
+					input[0] + center + (input[4] + input[6]) * DOWNMIX_GAIN,
+					input[1] + center + (input[5] + input[7]) * DOWNMIX_GAIN,
+				)
+			}
+		}
+	}
+
+	/// Returns the layout with the given number of channels, or if
+	/// there isn't one, the largest layout with fewer channels.
+	fn for_num_channels(num_channels: usize) -> Self {
+		match num_channels {
+			0 | 1 => ChannelLayout::Mono,
+			2 | 3 => ChannelLayout::Stereo,
+			4 | 5 => ChannelLayout::Quad,
+			6 | 7 => ChannelLayout::Surround51,
+			_ => ChannelLayout::Surround71,
+		}
+	}
+
+	/// Copies a multichannel frame in this layout to an output
+	/// frame with a different number of channels.
+	///
+	/// Mono and stereo outputs receive a downmix. Other outputs
+	/// are treated as the layout with that many channels (or the
+	/// largest layout that fits, leaving any extra channels silent),
+	/// and each input channel is sent to the output speaker closest
+	/// to it. A channel that's exactly between two speakers (like the
+	/// center channel on a quad output) is split evenly between them,
+	/// and the LFE channel is dropped if the output doesn't have one.
+	pub(crate) fn remap(self, input: &[f32], out: &mut [f32]) {
+		if out.len() == input.len() {
+			out.copy_from_slice(input);
+			return;
+		}
+		match out.len() {
+			1 => {
+				let frame = self.downmix_to_stereo(input);
+				out[0] = (frame.left + frame.right) / 2.0;
+			}
+			2 => {
+				let frame = self.downmix_to_stereo(input);
+				out[0] = frame.left;
+				out[1] = frame.right;
+			}
+			_ => {
+				out.iter_mut().for_each(|sample| *sample = 0.0);
+				let out_layout = Self::for_num_channels(out.len());
+				for (sample, angle) in input.iter().zip(self.speaker_angles()) {
+					out_layout.add_to_nearest_speaker(*sample, *angle, out);
+				}
+			}
+		}
+	}
+
+	/// Adds a sample from a speaker at the given angle (or from
+	/// the LFE channel, if the angle is `None`) to the closest
+	/// speaker in this layout.
+	fn add_to_nearest_speaker(self, sample: f32, angle: Option<f64>, out: &mut [f32]) {
+		let speakers = self.speaker_angles();
+		let angle = match angle {
+			Some(angle) => angle,
+			None => {
+				if let Some(lfe) = speakers.iter().position(Option::is_none) {
+					out[lfe] += sample;
+				}
+				return;
+			}
+		};
+		let mut nearest: Option<(usize, f64)> = None;
+		// another speaker that's just as close as the nearest one
+		let mut tied = None;
+		for (channel, speaker_angle) in speakers.iter().enumerate() {
+			let speaker_angle = match speaker_angle {
+				Some(speaker_angle) => *speaker_angle,
+				None => continue,
+			};
+			let distance = wrap_angle(angle - speaker_angle).abs();
+			match nearest {
+				Some((_, closest)) if distance > closest => {}
+				Some((_, closest)) if distance == closest => tied = Some(channel),
+				_ => {
+					nearest = Some((channel, distance));
+					tied = None;
+				}
+			}
+		}
+		match (nearest, tied) {
+			(Some((nearest, _)), Some(tied)) => {
+				out[nearest] += sample * DOWNMIX_GAIN;
+				out[tied] += sample * DOWNMIX_GAIN;
+			}
+			(Some((nearest, _)), None) => out[nearest] += sample,
+			_ => {}
+		}
+	}
+}
+
+/// Wraps an angle (in degrees) to the range -180 to 180.
+fn wrap_angle(angle: f64) -> f64 {
+	let angle = angle.rem_euclid(360.0);
+	if angle > 180.0 {
+		angle - 360.0
+	} else {
+		angle
+	}
+}
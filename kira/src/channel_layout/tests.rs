@@ -0,0 +1,65 @@
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use super::ChannelLayout;
+
+/// Remaps a frame with a single channel playing to an output
+/// with the given number of channels.
+fn remap_channel(layout: ChannelLayout, channel: usize, num_out_channels: usize) -> Vec<f32> {
+	let mut input = vec![0.0; layout.num_channels()];
+	input[channel] = 1.0;
+	let mut out = vec![0.0; num_out_channels];
+	layout.remap(&input, &mut out);
+	out
+}
+
+#[test]
+fn remaps_quad_to_surround_51() {
+	let layout = ChannelLayout::Quad;
+	// front speakers stay in front
+	assert_eq!(remap_channel(layout, 0, 6), [1.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+	assert_eq!(remap_channel(layout, 1, 6), [0.0, 1.0, 0.0, 0.0, 0.0, 0.0]);
+	// back speakers go to the surround speakers, not the
+	// center and LFE channels
+	assert_eq!(remap_channel(layout, 2, 6), [0.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+	assert_eq!(remap_channel(layout, 3, 6), [0.0, 0.0, 0.0, 0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn remaps_surround_51_to_quad() {
+	let layout = ChannelLayout::Surround51;
+	assert_eq!(remap_channel(layout, 0, 4), [1.0, 0.0, 0.0, 0.0]);
+	assert_eq!(remap_channel(layout, 1, 4), [0.0, 1.0, 0.0, 0.0]);
+	// the center channel is split between the front speakers
+	assert_eq!(
+		remap_channel(layout, 2, 4),
+		[FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.0, 0.0]
+	);
+	// quad has no LFE channel
+	assert_eq!(remap_channel(layout, 3, 4), [0.0; 4]);
+	assert_eq!(remap_channel(layout, 4, 4), [0.0, 0.0, 1.0, 0.0]);
+	assert_eq!(remap_channel(layout, 5, 4), [0.0, 0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn remaps_surround_71_to_surround_51() {
+	let layout = ChannelLayout::Surround71;
+	for channel in 0..4 {
+		let mut expected = [0.0; 6];
+		expected[channel] = 1.0;
+		assert_eq!(remap_channel(layout, channel, 6), expected);
+	}
+	// both the back and side speakers go to the surround speakers
+	assert_eq!(remap_channel(layout, 4, 6), [0.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+	assert_eq!(remap_channel(layout, 5, 6), [0.0, 0.0, 0.0, 0.0, 0.0, 1.0]);
+	assert_eq!(remap_channel(layout, 6, 6), [0.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+	assert_eq!(remap_channel(layout, 7, 6), [0.0, 0.0, 0.0, 0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn leaves_extra_output_channels_silent() {
+	// a 5 channel output is treated as quad
+	assert_eq!(
+		remap_channel(ChannelLayout::Surround51, 4, 5),
+		[0.0, 0.0, 1.0, 0.0, 0.0]
+	);
+}
@@ -5,17 +5,20 @@ use basedrop::Owned;
 use crate::{
 	arrangement::{Arrangement, ArrangementId},
 	audio_stream::{AudioStream, AudioStreamId},
-	group::{Group, GroupId},
+	group::{groups::Groups, Group, GroupId},
 	instance::{
 		Instance, InstanceId, PauseInstanceSettings, ResumeInstanceSettings, StopInstanceSettings,
 	},
-	metronome::{Metronome, MetronomeId},
+	manager::backend::{
+		instances::Instances, mixer::Mixer, sequences::Sequences, streams::Streams,
+	},
+	metronome::{Metronome, MetronomeId, Metronomes},
 	mixer::{
 		effect::{Effect, EffectId, EffectSettings},
 		SendTrackId, SubTrackId, Track, TrackIndex,
 	},
-	parameter::{tween::Tween, ParameterId},
-	playable::PlayableId,
+	parameter::{tween::Tween, ParameterId, Parameters},
+	playable::{PlayableId, Playables},
 	sequence::{SequenceInstance, SequenceInstanceId},
 	sound::{Sound, SoundId},
 	tempo::Tempo,
@@ -30,12 +33,13 @@ pub(crate) enum ResourceCommand {
 	RemoveArrangement(ArrangementId),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub(crate) enum InstanceCommand {
 	Play(InstanceId, Instance),
 	SetInstanceVolume(InstanceId, Value<f64>),
 	SetInstancePlaybackRate(InstanceId, Value<f64>),
 	SetInstancePanning(InstanceId, Value<f64>),
+	SetInstanceSurroundAngle(InstanceId, Option<Value<f64>>),
 	SeekInstance(InstanceId, f64),
 	SeekInstanceTo(InstanceId, f64),
 	PauseInstance(InstanceId, PauseInstanceSettings),
@@ -76,9 +80,17 @@ pub(crate) enum SequenceCommand {
 pub(crate) enum MixerCommand {
 	AddTrack(Owned<Track>),
 	SetTrackVolume(TrackIndex, Value<f64>),
+	SetTrackSurroundAngle(TrackIndex, Option<Value<f64>>),
 	RemoveSubTrack(SubTrackId),
 	RemoveSendTrack(SendTrackId),
-	AddEffect(TrackIndex, EffectId, Owned<Box<dyn Effect>>, EffectSettings),
+	// the last field is the sample rate the effect was initialized with
+	AddEffect(
+		TrackIndex,
+		EffectId,
+		Owned<Box<dyn Effect>>,
+		EffectSettings,
+		u32,
+	),
 	SetEffectEnabled(TrackIndex, EffectId, bool),
 	SetEffectMix(TrackIndex, EffectId, Value<f64>),
 	RemoveEffect(TrackIndex, EffectId),
@@ -102,7 +114,33 @@ pub(crate) enum StreamCommand {
 	RemoveStream(AudioStreamId),
 }
 
+/// A command that can be scheduled to run at a specific time
+/// on the audio clock.
+#[derive(Debug)]
+pub(crate) enum ScheduledCommand {
+	Instance(InstanceCommand),
+	Parameter(ParameterCommand),
+}
+
+/// Larger storage for one of the audio thread's resource pools.
+///
+/// Each variant holds an empty collection with a greater capacity
+/// than the current one, which the existing resources are moved into.
+pub(crate) enum GrowPoolCommand {
+	Playables(Owned<Playables>),
+	Instances(Owned<Instances>),
+	Sequences(Owned<Sequences>),
+	Mixer(Owned<Mixer>),
+	Parameters(Owned<Parameters>),
+	Groups(Owned<Groups>),
+	Streams(Owned<Streams>),
+	Metronomes(Owned<Metronomes>),
+}
+
 pub(crate) enum Command {
+	Batch(Owned<Vec<Command>>),
+	GrowPool(GrowPoolCommand),
+	Scheduled(u64, ScheduledCommand),
 	Resource(ResourceCommand),
 	Instance(InstanceCommand),
 	Metronome(MetronomeCommand),
@@ -111,6 +149,9 @@ pub(crate) enum Command {
 	Parameter(ParameterCommand),
 	Group(GroupCommand),
 	Stream(StreamCommand),
+	SetPaused(bool),
+	Suspend(Option<Tween>),
+	Resume(Option<Tween>),
 }
 
 impl From<ResourceCommand> for Command {
@@ -119,6 +160,12 @@ impl From<ResourceCommand> for Command {
 	}
 }
 
+impl From<GrowPoolCommand> for Command {
+	fn from(command: GrowPoolCommand) -> Self {
+		Self::GrowPool(command)
+	}
+}
+
 impl From<InstanceCommand> for Command {
 	fn from(command: InstanceCommand) -> Self {
 		Self::Instance(command)
@@ -160,3 +207,24 @@ impl From<StreamCommand> for Command {
 		Self::Stream(command)
 	}
 }
+
+impl From<ScheduledCommand> for Command {
+	fn from(command: ScheduledCommand) -> Self {
+		match command {
+			ScheduledCommand::Instance(command) => Self::Instance(command),
+			ScheduledCommand::Parameter(command) => Self::Parameter(command),
+		}
+	}
+}
+
+impl From<InstanceCommand> for ScheduledCommand {
+	fn from(command: InstanceCommand) -> Self {
+		Self::Instance(command)
+	}
+}
+
+impl From<ParameterCommand> for ScheduledCommand {
+	fn from(command: ParameterCommand) -> Self {
+		Self::Parameter(command)
+	}
+}
@@ -1,9 +1,16 @@
-use std::sync::{Arc, Mutex};
+use std::{
+	collections::VecDeque,
+	sync::{Arc, Mutex},
+	thread::{self, ThreadId},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Duration, Instant};
 
 use ringbuf::Producer;
 use thiserror::Error;
 
-use super::Command;
+use super::{Command, ScheduledCommand};
 
 /// Something that can go wrong when sending a command to the
 /// audio thread.
@@ -15,26 +22,254 @@ pub enum CommandError {
 	/// A thread panicked while using the command producer.
 	#[error("The command producer cannot be used because a thread panicked while borrowing it.")]
 	MutexPoisoned,
+	/// A batch was started while another batch was still
+	/// being recorded.
+	#[error("Cannot start a batch while another batch is being recorded")]
+	BatchInProgress,
+}
+
+/// What to do when a command is sent while the command queue is full.
+///
+/// The command queue holds commands sent from the audio manager and
+/// handles until the audio thread receives them, which happens once
+/// per audio buffer. Sending a lot of commands at once (for example,
+/// while loading a level) can fill it up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub enum CommandQueuePolicy {
+	/// Discard the command and return a
+	/// [`CommandError::CommandQueueFull`].
+	Fail,
+	/// Wait up to `timeout` seconds for the audio thread to make
+	/// room for the command, then give up and return a
+	/// [`CommandError::CommandQueueFull`].
+	///
+	/// The thread sending the command is blocked while it waits.
+	/// Blocking isn't possible on wasm, so this behaves like
+	/// [`Fail`](CommandQueuePolicy::Fail) there.
+	Block {
+		/// The longest time to wait (in seconds).
+		timeout: f64,
+	},
+	/// Hold the command on the sending side and send it once the
+	/// audio thread has made room, keeping commands in the order
+	/// they were sent.
+	///
+	/// Held commands are sent whenever another command is sent and
+	/// when [`AudioManager::free_unused_resources`](crate::manager::AudioManager::free_unused_resources)
+	/// is called. If [`AudioManagerSettings::resource_collection_interval`](crate::manager::AudioManagerSettings::resource_collection_interval)
+	/// is set, they're also sent periodically on the background
+	/// thread that frees resources. Sending commands never fails,
+	/// but held commands take effect later than usual, and the
+	/// number of held commands isn't limited.
+	Spill,
+}
+
+// written out by hand because deriving `Default` for an enum
+// needs Rust 1.62
+#[allow(clippy::derivable_impls)]
+impl Default for CommandQueuePolicy {
+	fn default() -> Self {
+		Self::Fail
+	}
+}
+
+struct CommandQueue {
+	producer: Producer<Command>,
+	policy: CommandQueuePolicy,
+	// commands that didn't fit in the queue when using
+	// CommandQueuePolicy::Spill, oldest first
+	spilled: VecDeque<Command>,
+	// commands collected by the thread that's currently
+	// recording a batch, if any
+	batch: Option<(ThreadId, Vec<Command>)>,
+}
+
+impl CommandQueue {
+	fn flush_spilled(&mut self) {
+		while let Some(command) = self.spilled.pop_front() {
+			if let Err(command) = self.producer.push(command) {
+				self.spilled.push_front(command);
+				return;
+			}
+		}
+	}
 }
 
 #[derive(Clone)]
 pub(crate) struct CommandProducer {
-	producer: Arc<Mutex<Producer<Command>>>,
+	queue: Arc<Mutex<CommandQueue>>,
 }
 
 impl CommandProducer {
-	pub fn new(producer: Producer<Command>) -> Self {
+	pub fn new(producer: Producer<Command>, policy: CommandQueuePolicy) -> Self {
 		Self {
-			producer: Arc::new(Mutex::new(producer)),
+			queue: Arc::new(Mutex::new(CommandQueue {
+				producer,
+				policy,
+				spilled: VecDeque::new(),
+				batch: None,
+			})),
 		}
 	}
 
 	pub fn push(&mut self, command: Command) -> Result<(), CommandError> {
-		self.producer
+		let mut queue = self.queue.lock().map_err(|_| CommandError::MutexPoisoned)?;
+		if let Some((thread_id, batch)) = &mut queue.batch {
+			if *thread_id == thread::current().id() {
+				batch.push(command);
+				return Ok(());
+			}
+		}
+		queue.flush_spilled();
+		if !queue.spilled.is_empty() {
+			// the new command has to wait behind the ones
+			// that are already waiting
+			queue.spilled.push_back(command);
+			return Ok(());
+		}
+		let command = match queue.producer.push(command) {
+			Ok(()) => return Ok(()),
+			Err(command) => command,
+		};
+		match queue.policy {
+			CommandQueuePolicy::Fail => Err(CommandError::CommandQueueFull),
+			CommandQueuePolicy::Block { timeout } => {
+				// let other threads use the queue while we wait
+				drop(queue);
+				self.push_blocking(command, timeout)
+			}
+			CommandQueuePolicy::Spill => {
+				queue.spilled.push_back(command);
+				Ok(())
+			}
+		}
+	}
+
+	#[cfg(not(target_arch = "wasm32"))]
+	fn push_blocking(&mut self, mut command: Command, timeout: f64) -> Result<(), CommandError> {
+		const RETRY_INTERVAL: Duration = Duration::from_micros(500);
+		let start_time = Instant::now();
+		loop {
+			std::thread::sleep(RETRY_INTERVAL);
+			let mut queue = self.queue.lock().map_err(|_| CommandError::MutexPoisoned)?;
+			command = match queue.producer.push(command) {
+				Ok(()) => return Ok(()),
+				Err(command) => command,
+			};
+			if start_time.elapsed().as_secs_f64() >= timeout {
+				return Err(CommandError::CommandQueueFull);
+			}
+		}
+	}
+
+	#[cfg(target_arch = "wasm32")]
+	fn push_blocking(&mut self, _command: Command, _timeout: f64) -> Result<(), CommandError> {
+		Err(CommandError::CommandQueueFull)
+	}
+
+	/// Sends as many held commands to the audio thread as
+	/// there's room for.
+	pub fn flush_spilled(&mut self) -> Result<(), CommandError> {
+		let mut queue = self.queue.lock().map_err(|_| CommandError::MutexPoisoned)?;
+		queue.flush_spilled();
+		Ok(())
+	}
+
+	/// Sends a command that the audio thread will run when its
+	/// clock reaches `time`.
+	pub fn push_scheduled(
+		&mut self,
+		command: impl Into<ScheduledCommand>,
+		time: u64,
+	) -> Result<(), CommandError> {
+		self.push(Command::Scheduled(time, command.into()))
+	}
+
+	/// Starts collecting the commands sent from the current
+	/// thread into a batch instead of sending them to the
+	/// audio thread.
+	///
+	/// Only one batch can be recorded at a time.
+	pub fn start_batch(&mut self) -> Result<Batch, CommandError> {
+		let mut queue = self.queue.lock().map_err(|_| CommandError::MutexPoisoned)?;
+		if queue.batch.is_some() {
+			return Err(CommandError::BatchInProgress);
+		}
+		queue.batch = Some((thread::current().id(), vec![]));
+		Ok(Batch {
+			queue: self.queue.clone(),
+			finished: false,
+		})
+	}
+
+	/// Returns the number of commands waiting to be received
+	/// by the audio thread.
+	pub fn len(&self) -> usize {
+		self.queue
+			.lock()
+			.map(|queue| queue.producer.len())
+			.unwrap_or(0)
+	}
+
+	/// Returns the number of commands being held until there's
+	/// room for them in the command queue.
+	pub fn num_spilled(&self) -> usize {
+		self.queue
+			.lock()
+			.map(|queue| queue.spilled.len())
+			.unwrap_or(0)
+	}
+
+	/// Returns the maximum number of commands that can be
+	/// waiting to be received by the audio thread.
+	pub fn capacity(&self) -> usize {
+		self.queue
 			.lock()
-			.map_err(|_| CommandError::MutexPoisoned)?
-			.push(command)
-			.map_err(|_| CommandError::CommandQueueFull)
+			.map(|queue| queue.producer.capacity())
+			.unwrap_or(0)
+	}
+}
+
+/// A batch of commands being recorded by a [`CommandProducer`].
+///
+/// If the batch is dropped without being finished (for example,
+/// because the code recording it panicked), the commands are
+/// discarded and later commands are sent as usual.
+pub(crate) struct Batch {
+	queue: Arc<Mutex<CommandQueue>>,
+	finished: bool,
+}
+
+impl Batch {
+	/// Stops collecting commands into the batch and returns
+	/// the commands that were collected.
+	pub fn finish(mut self) -> Result<Vec<Command>, CommandError> {
+		self.finished = true;
+		let mut queue = self.queue.lock().map_err(|_| CommandError::MutexPoisoned)?;
+		Ok(queue
+			.batch
+			.take()
+			.map(|(_, batch)| batch)
+			.unwrap_or_default())
+	}
+}
+
+impl Drop for Batch {
+	fn drop(&mut self) {
+		if self.finished {
+			return;
+		}
+		// clear the batch even if the mutex was poisoned, since
+		// the batch itself is never left in an invalid state
+		let mut queue = match self.queue.lock() {
+			Ok(queue) => queue,
+			Err(error) => error.into_inner(),
+		};
+		queue.batch = None;
 	}
 }
 
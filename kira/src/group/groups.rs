@@ -1,28 +1,42 @@
 use basedrop::Owned;
 
-use crate::{command::GroupCommand, static_container::index_map::StaticIndexMap};
+use crate::{
+	command::GroupCommand,
+	manager::diagnostics::{Diagnostic, DiagnosticProducer, ResourceId},
+	static_container::slot_map::StaticSlotMap,
+};
 
 use super::{Group, GroupId};
 
 pub(crate) struct Groups {
-	groups: StaticIndexMap<GroupId, Owned<Group>>,
+	groups: StaticSlotMap<GroupId, Owned<Group>>,
 }
 
 impl Groups {
 	pub fn new(capacity: usize) -> Self {
 		Self {
-			groups: StaticIndexMap::new(capacity),
+			groups: StaticSlotMap::new(capacity),
 		}
 	}
 
+	/// Moves all of the groups into `other`, which has a larger
+	/// capacity, and swaps the two so that the old (now empty)
+	/// storage can be freed on another thread.
+	pub fn grow_into(&mut self, other: &mut Self) {
+		self.groups.move_into(&mut other.groups);
+		std::mem::swap(self, other);
+	}
+
 	pub fn get(&self, id: GroupId) -> Option<&Owned<Group>> {
 		self.groups.get(&id)
 	}
 
-	pub fn run_command(&mut self, command: GroupCommand) {
+	pub fn run_command(&mut self, command: GroupCommand, diagnostics: &mut DiagnosticProducer) {
 		match command {
 			GroupCommand::AddGroup(id, group) => {
-				self.groups.try_insert(id, group).ok();
+				if self.groups.try_insert(id, group).is_err() {
+					diagnostics.report(Diagnostic::ResourceLimitReached(ResourceId::Group(id)));
+				}
 			}
 			GroupCommand::RemoveGroup(id) => {
 				self.groups.remove(&id);
@@ -14,9 +14,9 @@ pub(crate) mod groups;
 pub mod handle;
 mod set;
 
+use crate::static_container::slot_map::{SlotIndex, SlotKey};
 use handle::GroupHandle;
 pub use set::GroupSet;
-use uuid::Uuid;
 
 /// A unique identifier for a group.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -26,14 +26,16 @@ use uuid::Uuid;
 	serde(transparent)
 )]
 pub struct GroupId {
-	uuid: Uuid,
+	index: SlotIndex,
 }
 
-impl GroupId {
-	pub(crate) fn new() -> Self {
-		Self {
-			uuid: Uuid::new_v4(),
-		}
+impl SlotKey for GroupId {
+	fn from_slot_index(index: SlotIndex) -> Self {
+		Self { index }
+	}
+
+	fn slot_index(&self) -> SlotIndex {
+		self.index
 	}
 }
 
@@ -55,6 +57,11 @@ pub struct GroupSettings {
 	pub id: Option<GroupId>,
 	/// The groups this group belongs to.
 	pub groups: GroupSet,
+	/// Whether instances and sequences in this group should keep
+	/// playing while the audio manager is paused.
+	///
+	/// See [`AudioManager::set_paused`](crate::manager::AudioManager::set_paused).
+	pub unpausable: bool,
 }
 
 impl GroupSettings {
@@ -67,7 +74,7 @@ impl GroupSettings {
 	pub fn id(self, id: impl Into<GroupId>) -> Self {
 		Self {
 			id: Some(id.into()),
-			..Default::default()
+			..self
 		}
 	}
 
@@ -75,7 +82,16 @@ impl GroupSettings {
 	pub fn groups(self, groups: impl Into<GroupSet>) -> Self {
 		Self {
 			groups: groups.into(),
-			..Default::default()
+			..self
+		}
+	}
+
+	/// Makes instances and sequences in this group keep playing
+	/// while the audio manager is paused.
+	pub fn unpausable(self) -> Self {
+		Self {
+			unpausable: true,
+			..self
 		}
 	}
 }
@@ -85,6 +101,7 @@ impl Default for GroupSettings {
 		Self {
 			id: None,
 			groups: GroupSet::new(),
+			unpausable: false,
 		}
 	}
 }
@@ -92,16 +109,22 @@ impl Default for GroupSettings {
 #[derive(Debug, Clone)]
 pub(crate) struct Group {
 	groups: GroupSet,
+	unpausable: bool,
 }
 
 impl Group {
 	pub fn new(settings: GroupSettings) -> Self {
 		Self {
 			groups: settings.groups,
+			unpausable: settings.unpausable,
 		}
 	}
 
 	pub fn groups(&self) -> &GroupSet {
 		&self.groups
 	}
+
+	pub fn unpausable(&self) -> bool {
+		self.unpausable
+	}
 }
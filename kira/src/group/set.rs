@@ -63,6 +63,15 @@ impl GroupSet {
 		}
 		false
 	}
+
+	/// Returns true if any of the groups in the set or any of
+	/// their ancestors are unpausable.
+	pub(crate) fn is_unpausable(&self, all_groups: &Groups) -> bool {
+		self.0.iter().any(|id| match all_groups.get(*id) {
+			Some(group) => group.unpausable() || group.groups().is_unpausable(all_groups),
+			None => false,
+		})
+	}
 }
 
 impl<'a> IntoIterator for &'a GroupSet {
@@ -0,0 +1,45 @@
+//! Hands out IDs for resources that aren't stored in slots.
+
+#[cfg(test)]
+mod tests;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Produces sequential IDs for one kind of resource.
+///
+/// Sounds, arrangements, and effects can be created before
+/// they're sent to an audio manager, or from handles on any
+/// thread, so they can't be given a slot up front like
+/// parameters and tracks. Instead they're numbered in the order
+/// they're created, which is cheap and gives the same IDs each
+/// time a program runs, as long as resources are created in
+/// the same order.
+pub(crate) struct IdCounter {
+	next: AtomicU64,
+}
+
+/// An error returned when the user chooses the largest possible
+/// ID, which can't be claimed because there would be no IDs
+/// left to hand out after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct IdTooLarge;
+
+impl IdCounter {
+	pub const fn new() -> Self {
+		Self {
+			next: AtomicU64::new(0),
+		}
+	}
+
+	pub fn next(&self) -> u64 {
+		self.next.fetch_add(1, Ordering::Relaxed)
+	}
+
+	/// Makes sure an ID the user specified (for example, one
+	/// loaded from a file) won't be handed out again later.
+	pub fn claim(&self, id: u64) -> Result<(), IdTooLarge> {
+		let next = id.checked_add(1).ok_or(IdTooLarge)?;
+		self.next.fetch_max(next, Ordering::Relaxed);
+		Ok(())
+	}
+}
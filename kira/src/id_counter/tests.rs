@@ -0,0 +1,19 @@
+use super::{IdCounter, IdTooLarge};
+
+#[test]
+fn skips_claimed_ids() {
+	let counter = IdCounter::new();
+	assert_eq!(counter.next(), 0);
+	assert_eq!(counter.claim(5), Ok(()));
+	assert_eq!(counter.next(), 6);
+	// claiming an ID that was already passed doesn't go back
+	assert_eq!(counter.claim(2), Ok(()));
+	assert_eq!(counter.next(), 7);
+}
+
+#[test]
+fn fails_to_claim_the_largest_id() {
+	let counter = IdCounter::new();
+	assert_eq!(counter.claim(u64::MAX), Err(IdTooLarge));
+	assert_eq!(counter.next(), 0);
+}
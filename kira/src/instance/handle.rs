@@ -76,6 +76,18 @@ impl InstanceHandle {
 			.push(InstanceCommand::SetInstancePanning(self.id, panning.into()).into())
 	}
 
+	/// Sets where to place the instance around the listener
+	/// (in degrees) when using a surround channel layout.
+	///
+	/// If `None`, the instance is played in stereo on its track.
+	pub fn set_surround_angle(
+		&mut self,
+		surround_angle: Option<Value<f64>>,
+	) -> Result<(), CommandError> {
+		self.command_producer
+			.push(InstanceCommand::SetInstanceSurroundAngle(self.id, surround_angle).into())
+	}
+
 	/// Offsets the playback position of the instance by the specified amount (in seconds).
 	pub fn seek(&mut self, offset: f64) -> Result<(), CommandError> {
 		self.command_producer
@@ -88,6 +100,26 @@ impl InstanceHandle {
 			.push(InstanceCommand::SeekInstanceTo(self.id, position).into())
 	}
 
+	/// Offsets the playback position of the instance by the specified amount (in seconds)
+	/// when the audio clock reaches the given time.
+	///
+	/// See [`AudioManager::clock_time`](crate::manager::AudioManager::clock_time).
+	pub fn seek_at(&mut self, offset: f64, clock_time: u64) -> Result<(), CommandError> {
+		self.command_producer
+			.push_scheduled(InstanceCommand::SeekInstance(self.id, offset), clock_time)
+	}
+
+	/// Sets the playback position of the instance to the specified time (in seconds)
+	/// when the audio clock reaches the given time.
+	///
+	/// See [`AudioManager::clock_time`](crate::manager::AudioManager::clock_time).
+	pub fn seek_to_at(&mut self, position: f64, clock_time: u64) -> Result<(), CommandError> {
+		self.command_producer.push_scheduled(
+			InstanceCommand::SeekInstanceTo(self.id, position),
+			clock_time,
+		)
+	}
+
 	/// Pauses the instance.
 	pub fn pause(&mut self, settings: PauseInstanceSettings) -> Result<(), CommandError> {
 		self.command_producer
@@ -105,4 +137,16 @@ impl InstanceHandle {
 		self.command_producer
 			.push(InstanceCommand::StopInstance(self.id, settings).into())
 	}
+
+	/// Stops the instance when the audio clock reaches the given time.
+	///
+	/// See [`AudioManager::clock_time`](crate::manager::AudioManager::clock_time).
+	pub fn stop_at(
+		&mut self,
+		settings: StopInstanceSettings,
+		clock_time: u64,
+	) -> Result<(), CommandError> {
+		self.command_producer
+			.push_scheduled(InstanceCommand::StopInstance(self.id, settings), clock_time)
+	}
 }
@@ -60,12 +60,13 @@
 
 pub mod handle;
 mod settings;
+mod slots;
 
 use atomic::Atomic;
+use basedrop::Owned;
 use handle::InstanceHandle;
 pub use settings::*;
-
-use uuid::Uuid;
+pub(crate) use slots::{InstanceSlot, InstanceSlots};
 
 use crate::{
 	frame::Frame,
@@ -73,10 +74,15 @@ use crate::{
 	parameter::{Parameter, Parameters},
 	playable::{PlayableId, Playables},
 	sequence::SequenceInstanceId,
+	sound::{streaming::StreamReader, InterpolationMode, SampleCursor},
+	static_container::slot_map::{SlotIndex, SlotKey},
 	value::CachedValue,
 	value::Value,
 };
-use std::sync::{atomic::Ordering, Arc};
+use std::{
+	fmt::{Debug, Formatter},
+	sync::{atomic::Ordering, Arc},
+};
 
 /// A unique identifier for an instance.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -86,14 +92,16 @@ use std::sync::{atomic::Ordering, Arc};
 	serde(transparent)
 )]
 pub struct InstanceId {
-	uuid: Uuid,
+	index: SlotIndex,
 }
 
-impl InstanceId {
-	pub(crate) fn new() -> Self {
-		Self {
-			uuid: Uuid::new_v4(),
-		}
+impl SlotKey for InstanceId {
+	fn from_slot_index(index: SlotIndex) -> Self {
+		Self { index }
+	}
+
+	fn slot_index(&self) -> SlotIndex {
+		self.index
 	}
 }
 
@@ -126,7 +134,6 @@ pub enum InstanceState {
 	Stopping,
 }
 
-#[derive(Debug, Clone)]
 pub(crate) struct Instance {
 	playable_id: PlayableId,
 	duration: f64,
@@ -135,13 +142,34 @@ pub(crate) struct Instance {
 	volume: CachedValue<f64>,
 	playback_rate: CachedValue<f64>,
 	panning: CachedValue<f64>,
+	surround_angle: Option<CachedValue<f64>>,
 	reverse: bool,
 	loop_start: Option<f64>,
+	// the point a looping instance jumps back to the
+	// loop start point from
+	loop_end: f64,
+	interpolation_mode: Option<InterpolationMode>,
 	state: InstanceState,
 	public_state: Arc<Atomic<InstanceState>>,
 	position: f64,
 	public_position: Arc<Atomic<f64>>,
 	fade_volume: Parameter,
+	// whether the instance's sound or arrangement is in an
+	// unpausable group. this is set when the instance is
+	// started on the audio thread
+	in_unpausable_group: bool,
+	// the frames of a streamed sound, which are decoded
+	// on a background thread
+	stream: Option<Owned<StreamReader>>,
+	// whether the instance is waiting for its stream to
+	// decode the frames at the playback position
+	buffering: bool,
+	// where the instance left off decoding its sound's
+	// samples, if they're compressed
+	sample_cursor: SampleCursor,
+	// the instance's claim on its slot, which frees the slot
+	// when the instance is dropped
+	slot: InstanceSlot,
 }
 
 impl Instance {
@@ -150,6 +178,8 @@ impl Instance {
 		duration: f64,
 		sequence_id: Option<SequenceInstanceId>,
 		settings: InternalInstanceSettings,
+		stream: Option<Owned<StreamReader>>,
+		slot: InstanceSlot,
 	) -> Self {
 		let mut fade_volume;
 		if let Some(tween) = settings.fade_in_tween {
@@ -166,16 +196,34 @@ impl Instance {
 			volume: CachedValue::new(settings.volume, 1.0),
 			playback_rate: CachedValue::new(settings.playback_rate, 1.0),
 			panning: CachedValue::new(settings.panning, 0.5).with_valid_range(0.0..1.0),
+			surround_angle: settings
+				.surround_angle
+				.map(|angle| CachedValue::new(angle, 0.0)),
 			reverse: settings.reverse,
 			loop_start: settings.loop_start,
+			loop_end: settings.loop_end.unwrap_or(duration),
+			interpolation_mode: settings.interpolation_mode,
 			state: InstanceState::Playing,
 			public_state: Arc::new(Atomic::new(InstanceState::Playing)),
 			position: settings.start_position,
 			public_position: Arc::new(Atomic::new(settings.start_position)),
 			fade_volume,
+			in_unpausable_group: false,
+			stream,
+			buffering: false,
+			sample_cursor: SampleCursor::default(),
+			slot,
 		}
 	}
 
+	pub fn slot(&self) -> &InstanceSlot {
+		&self.slot
+	}
+
+	pub fn slot_mut(&mut self) -> &mut InstanceSlot {
+		&mut self.slot
+	}
+
 	pub fn playable_id(&self) -> PlayableId {
 		self.playable_id
 	}
@@ -188,6 +236,30 @@ impl Instance {
 		self.sequence_id
 	}
 
+	/// Picks new values for any settings that are set to
+	/// random ranges.
+	///
+	/// This is called when the instance is started on the audio
+	/// thread so that the values come from the backend's random
+	/// number generator, even if the instance was created on
+	/// another thread.
+	pub fn pick_random_values(&mut self) {
+		self.volume.pick_random_value();
+		self.playback_rate.pick_random_value();
+		self.panning.pick_random_value();
+		if let Some(surround_angle) = &mut self.surround_angle {
+			surround_angle.pick_random_value();
+		}
+	}
+
+	pub fn in_unpausable_group(&self) -> bool {
+		self.in_unpausable_group
+	}
+
+	pub fn set_in_unpausable_group(&mut self, in_unpausable_group: bool) {
+		self.in_unpausable_group = in_unpausable_group;
+	}
+
 	pub fn effective_volume(&self) -> f64 {
 		self.volume.value() * self.fade_volume.value()
 	}
@@ -226,6 +298,16 @@ impl Instance {
 		self.panning.set(panning);
 	}
 
+	pub fn set_surround_angle(&mut self, surround_angle: Option<Value<f64>>) {
+		self.surround_angle = surround_angle.map(|angle| CachedValue::new(angle, 0.0));
+	}
+
+	/// Returns where to place the instance around the listener,
+	/// or `None` if it should be played in stereo.
+	pub fn surround_angle(&self) -> Option<f64> {
+		self.surround_angle.as_ref().map(|angle| angle.value())
+	}
+
 	pub fn seek(&mut self, offset: f64) {
 		self.position += offset;
 	}
@@ -270,28 +352,48 @@ impl Instance {
 		self.fade_volume.set(0.0, settings.fade_tween);
 	}
 
+	fn effective_playback_rate(&self) -> f64 {
+		if self.reverse {
+			-self.playback_rate.value()
+		} else {
+			self.playback_rate.value()
+		}
+	}
+
 	pub fn update(&mut self, dt: f64, parameters: &Parameters) {
-		if self.playing() {
+		if let Some(stream) = &self.stream {
+			// the sound can't be decoded (or can't be decoded fast
+			// enough in the direction we're playing), so there's
+			// nothing left to play
+			if stream.failed()
+				|| (self.effective_playback_rate() < 0.0 && !stream.can_play_backward())
+			{
+				self.set_state(InstanceState::Stopped);
+			}
+		}
+		// while the stream is catching up, the playback
+		// position stays where it is
+		if self.playing() && !self.buffering {
 			self.volume.update(parameters);
 			self.playback_rate.update(parameters);
 			self.panning.update(parameters);
-			let mut playback_rate = self.playback_rate.value();
-			if self.reverse {
-				playback_rate *= -1.0;
+			if let Some(surround_angle) = &mut self.surround_angle {
+				surround_angle.update(parameters);
 			}
+			let playback_rate = self.effective_playback_rate();
 			self.position += playback_rate * dt;
 			if playback_rate < 0.0 {
 				if let Some(loop_start) = self.loop_start {
 					while self.position < loop_start {
-						self.position += self.duration - loop_start;
+						self.position += self.loop_end - loop_start;
 					}
 				} else if self.position < 0.0 {
 					self.set_state(InstanceState::Stopped);
 				}
 			} else {
 				if let Some(loop_start) = self.loop_start {
-					while self.position > self.duration {
-						self.position -= self.duration - loop_start;
+					while self.position >= self.loop_end {
+						self.position -= self.loop_end - loop_start;
 					}
 				} else if self.position > self.duration {
 					self.set_state(InstanceState::Stopped);
@@ -313,11 +415,62 @@ impl Instance {
 		self.public_position.store(self.position, Ordering::Relaxed);
 	}
 
-	pub fn get_sample(&self, playables: &Playables) -> Frame {
-		let mut out = playables
-			.frame_at_position(self.playable_id, self.position)
-			.unwrap_or(Frame::from_mono(0.0));
-		out = out.panned(self.panning.value() as f32);
+	pub fn get_sample(&mut self, playables: &Playables) -> Frame {
+		let backward = self.effective_playback_rate() < 0.0;
+		let mut out = match &mut self.stream {
+			Some(stream) if backward && !stream.can_play_backward() => Frame::from_mono(0.0),
+			Some(stream) => {
+				let frame = stream.frame_at_position(self.position, backward);
+				self.buffering = frame.is_none();
+				frame.unwrap_or(Frame::from_mono(0.0))
+			}
+			None => playables
+				.frame_at_position(
+					self.playable_id,
+					self.position,
+					self.interpolation_mode,
+					&mut self.sample_cursor,
+				)
+				.unwrap_or(Frame::from_mono(0.0)),
+		};
+		// positioned instances are panned by the mixer instead
+		if self.surround_angle.is_none() {
+			out = out.panned(self.panning.value() as f32);
+		}
 		out * (self.effective_volume() as f32)
 	}
 }
+
+impl Drop for Instance {
+	fn drop(&mut self) {
+		// the stream reader is freed later on another thread, so
+		// its background thread is told to stop right away
+		if let Some(stream) = &self.stream {
+			stream.stop();
+		}
+	}
+}
+
+impl Debug for Instance {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("Instance")
+			.field("playable_id", &self.playable_id)
+			.field("duration", &self.duration)
+			.field("sequence_id", &self.sequence_id)
+			.field("track_index", &self.track_index)
+			.field("volume", &self.volume)
+			.field("playback_rate", &self.playback_rate)
+			.field("panning", &self.panning)
+			.field("reverse", &self.reverse)
+			.field("loop_start", &self.loop_start)
+			.field("loop_end", &self.loop_end)
+			.field("interpolation_mode", &self.interpolation_mode)
+			.field("state", &self.state)
+			.field("position", &self.position)
+			.field("fade_volume", &self.fade_volume)
+			.field("in_unpausable_group", &self.in_unpausable_group)
+			.field("stream", &self.stream.as_deref())
+			.field("buffering", &self.buffering)
+			.finish()
+	}
+}
@@ -1,6 +1,7 @@
 use crate::{
 	mixer::{SubTrackId, TrackIndex},
 	parameter::tween::{EaseDirection, Easing, Tween},
+	sound::InterpolationMode,
 	Value,
 };
 
@@ -90,6 +91,20 @@ pub struct InstanceSettings {
 	pub playback_rate: Value<f64>,
 	/// The panning of the instance (0 = hard left, 1 = hard right).
 	pub panning: Value<f64>,
+	/// Where to place the instance around the listener (in degrees)
+	/// when using a surround
+	/// [`ChannelLayout`](crate::channel_layout::ChannelLayout).
+	///
+	/// 0 degrees is directly in front of the listener, and positive
+	/// angles are to the right. The instance's output is mixed down
+	/// to mono and panned between the nearest speakers, and `panning`
+	/// is ignored. Like positioned tracks, positioned instances are
+	/// mixed into the output directly: the volumes of the instance's
+	/// track and its parent tracks still apply, but their effects
+	/// don't.
+	///
+	/// If `None`, the instance is played in stereo on its track.
+	pub surround_angle: Option<Value<f64>>,
 	/// The position to start playing the instance at (in seconds).
 	pub start_position: f64,
 	/// Whether to play the instance in reverse.
@@ -102,6 +117,12 @@ pub struct InstanceSettings {
 	pub loop_start: InstanceLoopStart,
 	/// Which track to play the instance on.
 	pub track: InstanceTrackIndex,
+	/// How the instance interpolates between samples.
+	///
+	/// If `None`, each sound's own
+	/// [interpolation mode](crate::sound::SoundSettings::interpolation_mode)
+	/// is used.
+	pub interpolation_mode: Option<InterpolationMode>,
 }
 
 impl InstanceSettings {
@@ -142,6 +163,15 @@ impl InstanceSettings {
 		}
 	}
 
+	/// Sets where to place the instance around the listener
+	/// (in degrees) when using a surround channel layout.
+	pub fn surround_angle<A: Into<Value<f64>>>(self, surround_angle: A) -> Self {
+		Self {
+			surround_angle: Some(surround_angle.into()),
+			..self
+		}
+	}
+
 	/// Sets where in the sound playback will start (in seconds).
 	pub fn start_position(self, start_position: f64) -> Self {
 		Self {
@@ -151,6 +181,9 @@ impl InstanceSettings {
 	}
 
 	/// Play the instance in reverse.
+	///
+	/// Sounds streamed from files other than wav files can't be
+	/// played in reverse. See [`Sound::stream_from_file`](crate::sound::Sound::stream_from_file).
 	pub fn reverse(self) -> Self {
 		Self {
 			reverse: true,
@@ -182,16 +215,32 @@ impl InstanceSettings {
 		}
 	}
 
+	/// Sets how the instance interpolates between samples,
+	/// overriding the sound's interpolation mode.
+	pub fn interpolation_mode(self, interpolation_mode: InterpolationMode) -> Self {
+		Self {
+			interpolation_mode: Some(interpolation_mode),
+			..self
+		}
+	}
+
 	pub(crate) fn into_internal(
 		self,
 		duration: f64,
 		default_loop_start: Option<f64>,
+		default_loop_end: Option<f64>,
 		default_track: TrackIndex,
 	) -> InternalInstanceSettings {
+		let loop_start = match self.loop_start {
+			InstanceLoopStart::Default => default_loop_start,
+			InstanceLoopStart::None => None,
+			InstanceLoopStart::Custom(position) => Some(position),
+		};
 		InternalInstanceSettings {
 			volume: self.volume,
 			playback_rate: self.playback_rate,
 			panning: self.panning,
+			surround_angle: self.surround_angle,
 			start_position: if self.reverse {
 				duration - self.start_position
 			} else {
@@ -199,15 +248,17 @@ impl InstanceSettings {
 			},
 			reverse: self.reverse,
 			fade_in_tween: self.fade_in_tween,
-			loop_start: match self.loop_start {
-				InstanceLoopStart::Default => default_loop_start,
-				InstanceLoopStart::None => None,
-				InstanceLoopStart::Custom(position) => Some(position),
-			},
+			loop_start,
+			// a loop end point only makes sense if it's
+			// between the loop start point and the end
+			loop_end: loop_start.and_then(|loop_start| {
+				default_loop_end.filter(|loop_end| *loop_end > loop_start && *loop_end < duration)
+			}),
 			track: match self.track {
 				InstanceTrackIndex::DefaultForSound => default_track,
 				InstanceTrackIndex::Custom(track) => track,
 			},
+			interpolation_mode: self.interpolation_mode,
 		}
 	}
 }
@@ -219,11 +270,13 @@ impl Default for InstanceSettings {
 			volume: Value::Fixed(1.0),
 			playback_rate: Value::Fixed(1.0),
 			panning: Value::Fixed(0.5),
+			surround_angle: None,
 			start_position: 0.0,
 			reverse: false,
 			fade_in_tween: None,
 			loop_start: InstanceLoopStart::default(),
 			track: InstanceTrackIndex::default(),
+			interpolation_mode: None,
 		}
 	}
 }
@@ -232,11 +285,16 @@ pub(crate) struct InternalInstanceSettings {
 	pub volume: Value<f64>,
 	pub playback_rate: Value<f64>,
 	pub panning: Value<f64>,
+	pub surround_angle: Option<Value<f64>>,
 	pub start_position: f64,
 	pub reverse: bool,
 	pub fade_in_tween: Option<Tween>,
 	pub loop_start: Option<f64>,
+	/// Where a looping instance jumps back to the loop start
+	/// point from, if it's before the end of the sound.
+	pub loop_end: Option<f64>,
 	pub track: TrackIndex,
+	pub interpolation_mode: Option<InterpolationMode>,
 }
 
 /// Settings for pausing an instance.
@@ -0,0 +1,215 @@
+//! Hands out slot IDs for instances.
+
+use std::sync::{
+	atomic::{AtomicU64, AtomicUsize, Ordering},
+	Arc,
+};
+
+use crate::static_container::slot_map::{SlotIndex, SlotKey};
+
+use super::InstanceId;
+
+// the lowest bit of a slot's state is set if the slot is in use,
+// and the rest of the bits hold the generation of the ID that's
+// using the slot (or last used it)
+const OCCUPIED: u64 = 1;
+
+fn occupied(generation: u32) -> u64 {
+	((generation as u64) << 1) | OCCUPIED
+}
+
+fn generation(state: u64) -> u32 {
+	(state >> 1) as u32
+}
+
+/// Keeps track of which instance slots are in use.
+///
+/// Unlike other resources, instances are started from handles
+/// on any thread and from sequences on the audio thread, so
+/// slots are claimed and freed with atomic operations instead
+/// of by the audio manager. Each slot is shared between every
+/// copy of the `InstanceSlots`, so when the instance pool grows,
+/// a copy with more slots can be sent to the audio thread
+/// without moving the existing slots.
+#[derive(Debug, Clone)]
+pub(crate) struct InstanceSlots {
+	states: Vec<Arc<AtomicU64>>,
+	// the slot to start looking for a free slot from
+	next_index: Arc<AtomicUsize>,
+}
+
+impl InstanceSlots {
+	pub fn new(capacity: usize) -> Self {
+		let slots = Self {
+			states: vec![],
+			next_index: Arc::new(AtomicUsize::new(0)),
+		};
+		slots.with_capacity(capacity)
+	}
+
+	/// Returns a copy of these slots with new slots added so
+	/// there are `capacity` in total.
+	pub fn with_capacity(&self, capacity: usize) -> Self {
+		let mut slots = self.clone();
+		if capacity > slots.states.len() {
+			slots
+				.states
+				.resize_with(capacity, || Arc::new(AtomicU64::new(0)));
+		}
+		slots
+	}
+
+	pub fn capacity(&self) -> usize {
+		self.states.len()
+	}
+
+	fn slot(&self, id: InstanceId, claimed: bool) -> InstanceSlot {
+		InstanceSlot {
+			id,
+			state: self.states.get(id.slot_index().index as usize).cloned(),
+			claimed,
+		}
+	}
+
+	/// Claims a slot for a new instance.
+	///
+	/// If `requested` is `Some`, that exact ID is used, and any
+	/// other instance using the slot is replaced. Otherwise, the
+	/// first free slot is used, or if every slot is in use, the
+	/// slot after the one that was claimed most recently, which
+	/// is usually the one that was claimed the longest time ago.
+	pub fn claim(&self, requested: Option<InstanceId>) -> InstanceSlot {
+		if let Some(id) = requested {
+			let generation = id.slot_index().generation;
+			return match self.states.get(id.slot_index().index as usize) {
+				Some(state) => {
+					let previous_state = state.swap(occupied(generation), Ordering::AcqRel);
+					// if an instance with this ID is already playing, it
+					// keeps its claim until it's replaced on the audio thread
+					self.slot(id, previous_state != occupied(generation))
+				}
+				// the audio thread will report that there's no room
+				// for this instance
+				None => self.slot(id, false),
+			};
+		}
+		let capacity = self.capacity();
+		if capacity == 0 {
+			// generation 0 is never handed out, so this won't be
+			// mistaken for a real ID if the pool grows later
+			return self.slot(
+				InstanceId::from_slot_index(SlotIndex {
+					index: 0,
+					generation: 0,
+				}),
+				false,
+			);
+		}
+		let start = self.next_index.load(Ordering::Relaxed) % capacity;
+		let free_slot = (0..capacity)
+			.map(|offset| (start + offset) % capacity)
+			.find_map(|index| {
+				let state = self.states[index].load(Ordering::Acquire);
+				let generation = generation(state).wrapping_add(1);
+				let claimed = state & OCCUPIED == 0
+					&& self.states[index]
+						.compare_exchange(
+							state,
+							occupied(generation),
+							Ordering::AcqRel,
+							Ordering::Acquire,
+						)
+						.is_ok();
+				if claimed {
+					Some((index, generation))
+				} else {
+					None
+				}
+			});
+		let (index, generation) = free_slot.unwrap_or_else(|| {
+			// every slot is in use, so take over the slot after the
+			// most recently claimed one
+			let previous_state = self.states[start]
+				.fetch_update(Ordering::AcqRel, Ordering::Acquire, |state| {
+					Some(occupied(generation(state).wrapping_add(1)))
+				})
+				.unwrap();
+			(start, generation(previous_state).wrapping_add(1))
+		});
+		self.next_index.store(index + 1, Ordering::Relaxed);
+		self.slot(
+			InstanceId::from_slot_index(SlotIndex {
+				index: index as u32,
+				generation,
+			}),
+			true,
+		)
+	}
+
+	/// Returns the claim on a slot that was given up with
+	/// [`InstanceSlot::into_id`].
+	pub fn reclaim(&self, id: InstanceId) -> InstanceSlot {
+		self.slot(id, true)
+	}
+}
+
+/// A claim on an instance slot. The slot is freed when this
+/// is dropped.
+pub(crate) struct InstanceSlot {
+	id: InstanceId,
+	state: Option<Arc<AtomicU64>>,
+	// whether dropping this should free the slot
+	claimed: bool,
+}
+
+impl InstanceSlot {
+	pub fn id(&self) -> InstanceId {
+		self.id
+	}
+
+	/// Returns `true` if the slot hasn't been claimed by another
+	/// instance since this claim was made.
+	pub fn is_current(&self) -> bool {
+		match &self.state {
+			Some(state) => {
+				state.load(Ordering::Acquire) == occupied(self.id.slot_index().generation)
+			}
+			None => false,
+		}
+	}
+
+	/// Takes over another claim on the same slot, so the slot
+	/// isn't freed when the other claim is dropped.
+	pub fn take_claim(&mut self, other: &mut Self) {
+		if other.id == self.id && other.claimed {
+			other.claimed = false;
+			self.claimed = true;
+		}
+	}
+
+	/// Gives up the claim without freeing the slot. The claim
+	/// can be taken back with [`InstanceSlots::reclaim`].
+	pub fn into_id(mut self) -> InstanceId {
+		self.claimed = false;
+		self.id
+	}
+}
+
+impl Drop for InstanceSlot {
+	fn drop(&mut self) {
+		if !self.claimed {
+			return;
+		}
+		if let Some(state) = &self.state {
+			let generation = self.id.slot_index().generation;
+			state
+				.compare_exchange(
+					occupied(generation),
+					occupied(generation) & !OCCUPIED,
+					Ordering::AcqRel,
+					Ordering::Acquire,
+				)
+				.ok();
+		}
+	}
+}
@@ -108,16 +108,19 @@
 
 pub mod arrangement;
 pub mod audio_stream;
+pub mod channel_layout;
 mod command;
 mod duration;
 mod frame;
 pub mod group;
+mod id_counter;
 pub mod instance;
 pub mod manager;
 pub mod metronome;
 pub mod mixer;
 pub mod parameter;
 mod playable;
+mod random;
 pub mod sequence;
 pub mod sound;
 mod static_container;
@@ -6,8 +6,11 @@ use crate::{
 	mixer::{SendTrackId, SubTrackId},
 	parameter::ParameterId,
 	sound::SoundId,
+	static_container::slot_map::{SlotIndex, SlotKey},
 };
 
+use std::{hash::Hash, marker::PhantomData};
+
 use indexmap::IndexSet;
 
 use super::{
@@ -20,15 +23,142 @@ use super::{
 	AudioManagerSettings,
 };
 
+/// A kind of resource whose IDs are tracked on the main thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceKind {
+	Sound,
+	Arrangement,
+	Parameter,
+	SubTrack,
+	SendTrack,
+	Group,
+	Metronome,
+	Stream,
+}
+
+/// Why an ID couldn't be handed out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdSlotError<K> {
+	/// Every slot is in use, or the requested ID refers to
+	/// a slot past the end of the pool.
+	Full,
+	/// The requested ID's slot is used by a different resource.
+	InUse(K),
+}
+
+/// Hands out slot IDs for one kind of resource and keeps track
+/// of which ones are in use.
+///
+/// The audio thread stores resources of this kind in a
+/// [`StaticSlotMap`](crate::static_container::slot_map::StaticSlotMap),
+/// so every ID handed out has to refer to a free slot.
+pub struct IdSlots<K: SlotKey> {
+	// the generation of the ID using each slot, if any
+	occupants: Vec<Option<u32>>,
+	// the generation the next ID for each slot will have. this
+	// only ever goes up, so an ID is never handed out twice
+	next_generations: Vec<u32>,
+	// free slots are handed out from the end, so the lowest
+	// indices are used first
+	free_indices: Vec<u32>,
+	len: usize,
+	phantom: PhantomData<K>,
+}
+
+impl<K: SlotKey> IdSlots<K> {
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			occupants: vec![None; capacity],
+			next_generations: vec![0; capacity],
+			free_indices: (0..capacity as u32).rev().collect(),
+			len: 0,
+			phantom: PhantomData,
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn capacity(&self) -> usize {
+		self.occupants.len()
+	}
+
+	pub fn contains(&self, id: K) -> bool {
+		let SlotIndex { index, generation } = id.slot_index();
+		self.occupants.get(index as usize) == Some(&Some(generation))
+	}
+
+	/// Marks a slot as used and returns its ID.
+	///
+	/// If `requested` is `Some`, that exact ID is used, which
+	/// lets users keep IDs stable across runs (for example,
+	/// when they're loaded from a file). Adding the same ID
+	/// again replaces the existing resource.
+	pub fn insert(&mut self, requested: Option<K>) -> Result<K, IdSlotError<K>> {
+		let id = match requested {
+			Some(id) => id.slot_index(),
+			None => {
+				let index = *self.free_indices.last().ok_or(IdSlotError::Full)?;
+				SlotIndex {
+					index,
+					generation: self.next_generations[index as usize],
+				}
+			}
+		};
+		let slot = id.index as usize;
+		match self.occupants.get(slot) {
+			None => return Err(IdSlotError::Full),
+			Some(Some(generation)) if *generation == id.generation => {}
+			Some(Some(_)) => return Err(IdSlotError::InUse(K::from_slot_index(id))),
+			Some(None) => {
+				self.free_indices.retain(|index| *index != id.index);
+				self.occupants[slot] = Some(id.generation);
+				self.next_generations[slot] =
+					self.next_generations[slot].max(id.generation.wrapping_add(1));
+				self.len += 1;
+			}
+		}
+		Ok(K::from_slot_index(id))
+	}
+
+	/// Frees the slot used by an ID. Returns `false` if the
+	/// ID isn't in use.
+	pub fn remove(&mut self, id: K) -> bool {
+		if !self.contains(id) {
+			return false;
+		}
+		let index = id.slot_index().index;
+		self.occupants[index as usize] = None;
+		self.free_indices.push(index);
+		self.len -= 1;
+		true
+	}
+
+	/// Adds slots until there are `capacity` in total.
+	pub fn set_capacity(&mut self, capacity: usize) {
+		let old_capacity = self.capacity();
+		if capacity <= old_capacity {
+			return;
+		}
+		self.occupants.resize(capacity, None);
+		self.next_generations.resize(capacity, 0);
+		// the new slots go at the start of the free list so
+		// they're used after the lower slots
+		self.free_indices
+			.splice(0..0, (old_capacity as u32..capacity as u32).rev());
+	}
+}
+
 pub struct ActiveIds {
 	pub active_sound_ids: IndexSet<SoundId>,
 	pub active_arrangement_ids: IndexSet<ArrangementId>,
-	pub active_parameter_ids: IndexSet<ParameterId>,
-	pub active_sub_track_ids: IndexSet<SubTrackId>,
-	pub active_send_track_ids: IndexSet<SendTrackId>,
-	pub active_group_ids: IndexSet<GroupId>,
-	pub active_metronome_ids: IndexSet<MetronomeId>,
-	pub active_stream_ids: IndexSet<AudioStreamId>,
+	pub active_parameter_ids: IdSlots<ParameterId>,
+	pub active_sub_track_ids: IdSlots<SubTrackId>,
+	pub active_send_track_ids: IdSlots<SendTrackId>,
+	pub active_group_ids: IdSlots<GroupId>,
+	pub active_metronome_ids: IdSlots<MetronomeId>,
+	pub active_stream_ids: IdSlots<AudioStreamId>,
 }
 
 impl ActiveIds {
@@ -36,12 +166,57 @@ impl ActiveIds {
 		Self {
 			active_sound_ids: IndexSet::with_capacity(settings.num_sounds),
 			active_arrangement_ids: IndexSet::with_capacity(settings.num_arrangements),
-			active_parameter_ids: IndexSet::with_capacity(settings.num_parameters),
-			active_sub_track_ids: IndexSet::with_capacity(settings.num_sub_tracks),
-			active_send_track_ids: IndexSet::with_capacity(settings.num_send_tracks),
-			active_group_ids: IndexSet::with_capacity(settings.num_groups),
-			active_metronome_ids: IndexSet::with_capacity(settings.num_metronomes),
-			active_stream_ids: IndexSet::with_capacity(settings.num_streams),
+			active_parameter_ids: IdSlots::new(settings.num_parameters),
+			active_sub_track_ids: IdSlots::new(settings.num_sub_tracks),
+			active_send_track_ids: IdSlots::new(settings.num_send_tracks),
+			active_group_ids: IdSlots::new(settings.num_groups),
+			active_metronome_ids: IdSlots::new(settings.num_metronomes),
+			active_stream_ids: IdSlots::new(settings.num_streams),
+		}
+	}
+
+	/// Returns the maximum number of resources of a kind
+	/// that can exist at a time.
+	pub fn capacity(&self, kind: ResourceKind) -> usize {
+		match kind {
+			ResourceKind::Sound => self.active_sound_ids.capacity(),
+			ResourceKind::Arrangement => self.active_arrangement_ids.capacity(),
+			ResourceKind::Parameter => self.active_parameter_ids.capacity(),
+			ResourceKind::SubTrack => self.active_sub_track_ids.capacity(),
+			ResourceKind::SendTrack => self.active_send_track_ids.capacity(),
+			ResourceKind::Group => self.active_group_ids.capacity(),
+			ResourceKind::Metronome => self.active_metronome_ids.capacity(),
+			ResourceKind::Stream => self.active_stream_ids.capacity(),
+		}
+	}
+
+	/// Returns `true` if no more resources of a kind can be added.
+	pub fn is_full(&self, kind: ResourceKind) -> bool {
+		let len = match kind {
+			ResourceKind::Sound => self.active_sound_ids.len(),
+			ResourceKind::Arrangement => self.active_arrangement_ids.len(),
+			ResourceKind::Parameter => self.active_parameter_ids.len(),
+			ResourceKind::SubTrack => self.active_sub_track_ids.len(),
+			ResourceKind::SendTrack => self.active_send_track_ids.len(),
+			ResourceKind::Group => self.active_group_ids.len(),
+			ResourceKind::Metronome => self.active_metronome_ids.len(),
+			ResourceKind::Stream => self.active_stream_ids.len(),
+		};
+		len >= self.capacity(kind)
+	}
+
+	/// Raises the maximum number of resources of a kind that can
+	/// exist at a time.
+	pub fn set_capacity(&mut self, kind: ResourceKind, capacity: usize) {
+		match kind {
+			ResourceKind::Sound => set_capacity(&mut self.active_sound_ids, capacity),
+			ResourceKind::Arrangement => set_capacity(&mut self.active_arrangement_ids, capacity),
+			ResourceKind::Parameter => self.active_parameter_ids.set_capacity(capacity),
+			ResourceKind::SubTrack => self.active_sub_track_ids.set_capacity(capacity),
+			ResourceKind::SendTrack => self.active_send_track_ids.set_capacity(capacity),
+			ResourceKind::Group => self.active_group_ids.set_capacity(capacity),
+			ResourceKind::Metronome => self.active_metronome_ids.set_capacity(capacity),
+			ResourceKind::Stream => self.active_stream_ids.set_capacity(capacity),
 		}
 	}
 
@@ -78,93 +253,120 @@ impl ActiveIds {
 		Ok(())
 	}
 
-	pub fn add_parameter_id(&mut self, id: ParameterId) -> Result<(), AddParameterError> {
-		if self.active_parameter_ids.len() >= self.active_parameter_ids.capacity() {
-			return Err(AddParameterError::ParameterLimitReached);
-		}
-		self.active_parameter_ids.insert(id);
-		Ok(())
+	pub fn add_parameter_id(
+		&mut self,
+		requested: Option<ParameterId>,
+	) -> Result<ParameterId, AddParameterError> {
+		self.active_parameter_ids
+			.insert(requested)
+			.map_err(|error| match error {
+				IdSlotError::Full => AddParameterError::ParameterLimitReached,
+				IdSlotError::InUse(id) => AddParameterError::IdInUse(id),
+			})
 	}
 
 	pub fn remove_parameter_id(&mut self, id: ParameterId) -> Result<(), RemoveParameterError> {
-		if !self.active_parameter_ids.remove(&id) {
+		if !self.active_parameter_ids.remove(id) {
 			return Err(RemoveParameterError::NoParameterWithId(id));
 		}
 		Ok(())
 	}
 
-	pub fn add_sub_track_id(&mut self, id: SubTrackId) -> Result<(), AddSubTrackError> {
-		if self.active_sub_track_ids.len() >= self.active_sub_track_ids.capacity() {
-			return Err(AddSubTrackError::TrackLimitReached);
-		}
-		self.active_sub_track_ids.insert(id);
-		Ok(())
+	pub fn add_sub_track_id(
+		&mut self,
+		requested: Option<SubTrackId>,
+	) -> Result<SubTrackId, AddSubTrackError> {
+		self.active_sub_track_ids
+			.insert(requested)
+			.map_err(|error| match error {
+				IdSlotError::Full => AddSubTrackError::TrackLimitReached,
+				IdSlotError::InUse(id) => AddSubTrackError::IdInUse(id),
+			})
 	}
 
 	pub fn remove_sub_track_id(&mut self, id: SubTrackId) -> Result<(), RemoveSubTrackError> {
-		if !self.active_sub_track_ids.remove(&id) {
+		if !self.active_sub_track_ids.remove(id) {
 			return Err(RemoveSubTrackError::NoSubTrackWithId(id));
 		}
 		Ok(())
 	}
 
-	pub fn add_send_track_id(&mut self, id: SendTrackId) -> Result<(), AddSendTrackError> {
-		if self.active_send_track_ids.len() >= self.active_send_track_ids.capacity() {
-			return Err(AddSendTrackError::TrackLimitReached);
-		}
-		self.active_send_track_ids.insert(id);
-		Ok(())
+	pub fn add_send_track_id(
+		&mut self,
+		requested: Option<SendTrackId>,
+	) -> Result<SendTrackId, AddSendTrackError> {
+		self.active_send_track_ids
+			.insert(requested)
+			.map_err(|error| match error {
+				IdSlotError::Full => AddSendTrackError::TrackLimitReached,
+				IdSlotError::InUse(id) => AddSendTrackError::IdInUse(id),
+			})
 	}
 
 	pub fn remove_send_track_id(&mut self, id: SendTrackId) -> Result<(), RemoveSendTrackError> {
-		if !self.active_send_track_ids.remove(&id) {
+		if !self.active_send_track_ids.remove(id) {
 			return Err(RemoveSendTrackError::NoSendTrackWithId(id));
 		}
 		Ok(())
 	}
 
-	pub fn add_group_id(&mut self, id: GroupId) -> Result<(), AddGroupError> {
-		if self.active_group_ids.len() >= self.active_group_ids.capacity() {
-			return Err(AddGroupError::GroupLimitReached);
-		}
-		self.active_group_ids.insert(id);
-		Ok(())
+	pub fn add_group_id(&mut self, requested: Option<GroupId>) -> Result<GroupId, AddGroupError> {
+		self.active_group_ids
+			.insert(requested)
+			.map_err(|error| match error {
+				IdSlotError::Full => AddGroupError::GroupLimitReached,
+				IdSlotError::InUse(id) => AddGroupError::IdInUse(id),
+			})
 	}
 
 	pub fn remove_group_id(&mut self, id: GroupId) -> Result<(), RemoveGroupError> {
-		if !self.active_group_ids.remove(&id) {
+		if !self.active_group_ids.remove(id) {
 			return Err(RemoveGroupError::NoGroupWithId(id));
 		}
 		Ok(())
 	}
 
-	pub fn add_metronome_id(&mut self, id: MetronomeId) -> Result<(), AddMetronomeError> {
-		if self.active_metronome_ids.len() >= self.active_metronome_ids.capacity() {
-			return Err(AddMetronomeError::MetronomeLimitReached);
-		}
-		self.active_metronome_ids.insert(id);
-		Ok(())
+	pub fn add_metronome_id(
+		&mut self,
+		requested: Option<MetronomeId>,
+	) -> Result<MetronomeId, AddMetronomeError> {
+		self.active_metronome_ids
+			.insert(requested)
+			.map_err(|error| match error {
+				IdSlotError::Full => AddMetronomeError::MetronomeLimitReached,
+				IdSlotError::InUse(id) => AddMetronomeError::IdInUse(id),
+			})
 	}
 
 	pub fn remove_metronome_id(&mut self, id: MetronomeId) -> Result<(), RemoveMetronomeError> {
-		if !self.active_metronome_ids.remove(&id) {
+		if !self.active_metronome_ids.remove(id) {
 			return Err(RemoveMetronomeError::NoMetronomeWithId(id));
 		}
 		Ok(())
 	}
 
-	pub fn add_stream_id(&mut self, id: AudioStreamId) -> Result<(), AddStreamError> {
-		if self.active_stream_ids.len() >= self.active_stream_ids.capacity() {
-			return Err(AddStreamError::StreamLimitReached);
-		}
-		self.active_stream_ids.insert(id);
-		Ok(())
+	pub fn add_stream_id(&mut self) -> Result<AudioStreamId, AddStreamError> {
+		self.active_stream_ids
+			.insert(None)
+			.map_err(|_| AddStreamError::StreamLimitReached)
 	}
 
 	pub fn remove_stream_id(&mut self, id: AudioStreamId) -> Result<(), RemoveStreamError> {
-		if !self.active_stream_ids.remove(&id) {
+		if !self.active_stream_ids.remove(id) {
 			return Err(RemoveStreamError::NoStreamWithId(id));
 		}
 		Ok(())
 	}
 }
+
+/// Replaces a set of IDs with one that can hold exactly
+/// `capacity` IDs.
+///
+/// The capacity of the set is used as the limit for how many
+/// resources can exist, so [`IndexSet::reserve`] can't be used,
+/// since it might reserve more space than requested.
+fn set_capacity<T: Hash + Eq>(ids: &mut IndexSet<T>, capacity: usize) {
+	let mut new_ids = IndexSet::with_capacity(capacity);
+	new_ids.extend(ids.drain(..));
+	*ids = new_ids;
+}
@@ -1,24 +1,84 @@
 use crate::{
 	command::InstanceCommand,
 	group::groups::Groups,
-	instance::{Instance, InstanceId, StopInstanceSettings},
+	instance::{Instance, InstanceId, InstanceSlots, StopInstanceSettings},
+	manager::diagnostics::{Diagnostic, DiagnosticProducer, ResourceId},
 	parameter::Parameters,
 	playable::{PlayableId, Playables},
-	static_container::{index_map::StaticIndexMap, vec::StaticVec},
+	static_container::{
+		slot_map::{SlotKey, StaticSlotMap},
+		vec::StaticVec,
+	},
 };
 
 use super::mixer::Mixer;
 
 pub(crate) struct Instances {
-	instances: StaticIndexMap<InstanceId, Instance>,
+	instances: StaticSlotMap<InstanceId, Instance>,
 	instances_to_remove: StaticVec<InstanceId>,
+	// used to claim slots for instances started by sequences
+	slots: InstanceSlots,
 }
 
 impl Instances {
-	pub fn new(capacity: usize) -> Self {
+	pub fn new(slots: InstanceSlots) -> Self {
+		let capacity = slots.capacity();
 		Self {
-			instances: StaticIndexMap::new(capacity),
+			instances: StaticSlotMap::new(capacity),
 			instances_to_remove: StaticVec::new(capacity),
+			slots,
+		}
+	}
+
+	/// Moves all of the instances into `other`, which has a larger
+	/// capacity, and swaps the two so that the old (now empty)
+	/// storage can be freed on another thread.
+	pub fn grow_into(&mut self, other: &mut Self) {
+		self.instances.move_into(&mut other.instances);
+		std::mem::swap(self, other);
+	}
+
+	pub fn len(&self) -> usize {
+		self.instances.len()
+	}
+
+	pub fn slots(&self) -> &InstanceSlots {
+		&self.slots
+	}
+
+	fn start_instance(
+		&mut self,
+		instance_id: InstanceId,
+		instance: Instance,
+		diagnostics: &mut DiagnosticProducer,
+	) {
+		let index = instance_id.slot_index().index as usize;
+		if index >= self.instances.capacity() {
+			diagnostics.report(Diagnostic::ResourceLimitReached(ResourceId::Instance(
+				instance_id,
+			)));
+			return;
+		}
+		// if another instance took over this slot after this one
+		// was created, this instance was replaced before it started
+		if !instance.slot().is_current() {
+			diagnostics.report(Diagnostic::InstanceLimitReached {
+				stopped: instance_id,
+			});
+			return;
+		}
+		let previous_id = self.instances.get_slot(index).map(|(id, _)| *id);
+		if let Ok(Some(mut previous_instance)) = self.instances.try_insert(instance_id, instance) {
+			if previous_id == Some(instance_id) {
+				// the new instance was given the same ID as the one
+				// it replaced, so it takes over the old instance's
+				// claim on the slot
+				let instance = self.instances.get_mut(&instance_id).unwrap();
+				instance.slot_mut().take_claim(previous_instance.slot_mut());
+			} else if let Some(stopped) = previous_id {
+				// the slot was taken over because every slot was in use
+				diagnostics.report(Diagnostic::InstanceLimitReached { stopped });
+			}
 		}
 	}
 
@@ -35,59 +95,84 @@ impl Instances {
 		command: InstanceCommand,
 		playables: &mut Playables,
 		all_groups: &Groups,
+		diagnostics: &mut DiagnosticProducer,
 	) {
 		match command {
-			InstanceCommand::Play(instance_id, instance) => {
+			InstanceCommand::Play(instance_id, mut instance) => {
+				instance.pick_random_values();
+				if let Some(playable) = playables.playable(instance.playable_id()) {
+					instance.set_in_unpausable_group(playable.is_unpausable(all_groups));
+				}
 				if let Some(mut playable) = playables.playable_mut(instance.playable_id()) {
 					if !playable.cooling_down() {
-						// if we're at the instance limit, remove the instance that was
-						// started the longest time ago.
-						if self.instances.len() >= self.instances.capacity() {
-							self.instances.shift_remove_index(0);
-						}
-						self.instances.try_insert(instance_id, instance).ok();
+						self.start_instance(instance_id, instance, diagnostics);
 						playable.start_cooldown();
 					}
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(instance.playable_id().into()));
 				}
 			}
 			InstanceCommand::SetInstanceVolume(id, value) => {
 				if let Some(instance) = self.instances.get_mut(&id) {
 					instance.set_volume(value);
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(ResourceId::Instance(id)));
 				}
 			}
 			InstanceCommand::SetInstancePlaybackRate(id, value) => {
 				if let Some(instance) = self.instances.get_mut(&id) {
 					instance.set_playback_rate(value);
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(ResourceId::Instance(id)));
 				}
 			}
 			InstanceCommand::SetInstancePanning(id, value) => {
 				if let Some(instance) = self.instances.get_mut(&id) {
 					instance.set_panning(value);
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(ResourceId::Instance(id)));
+				}
+			}
+			InstanceCommand::SetInstanceSurroundAngle(id, value) => {
+				if let Some(instance) = self.instances.get_mut(&id) {
+					instance.set_surround_angle(value);
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(ResourceId::Instance(id)));
 				}
 			}
 			InstanceCommand::SeekInstance(id, offset) => {
 				if let Some(instance) = self.instances.get_mut(&id) {
 					instance.seek(offset);
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(ResourceId::Instance(id)));
 				}
 			}
 			InstanceCommand::SeekInstanceTo(id, position) => {
 				if let Some(instance) = self.instances.get_mut(&id) {
 					instance.seek_to(position);
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(ResourceId::Instance(id)));
 				}
 			}
 			InstanceCommand::PauseInstance(id, settings) => {
 				if let Some(instance) = self.instances.get_mut(&id) {
 					instance.pause(settings);
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(ResourceId::Instance(id)));
 				}
 			}
 			InstanceCommand::ResumeInstance(id, settings) => {
 				if let Some(instance) = self.instances.get_mut(&id) {
 					instance.resume(settings);
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(ResourceId::Instance(id)));
 				}
 			}
 			InstanceCommand::StopInstance(id, settings) => {
 				if let Some(instance) = self.instances.get_mut(&id) {
 					instance.stop(settings);
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(ResourceId::Instance(id)));
 				}
 			}
 			InstanceCommand::PauseInstancesOf(playable, settings) => {
@@ -158,9 +243,17 @@ impl Instances {
 		}
 	}
 
+	/// Adds the current frame of each instance to the mixer at
+	/// `frame_index` in the current block.
+	///
+	/// While the audio manager is paused, only instances of
+	/// unpausable sounds and arrangements or on unpausable tracks
+	/// are played.
 	pub fn process(
 		&mut self,
+		frame_index: usize,
 		dt: f64,
+		paused: bool,
 		playables: &Playables,
 		mixer: &mut Mixer,
 		parameters: &Parameters,
@@ -168,16 +261,29 @@ impl Instances {
 		// TODO: simplify this code (preferably by removing self.instances_to_remove)
 		// while making sure every sample of the sound gets played before the instance is removed
 		for (instance_id, instance) in &mut self.instances {
+			if paused
+				&& !instance.in_unpausable_group()
+				&& !mixer.is_track_unpausable(instance.track_index())
+			{
+				continue;
+			}
 			if instance.playing() {
-				mixer.add_input(instance.track_index(), instance.get_sample(playables));
+				let sample = instance.get_sample(playables);
+				match instance.surround_angle() {
+					Some(angle) => {
+						mixer.add_surround_input(instance.track_index(), frame_index, sample, angle)
+					}
+					None => mixer.add_input(instance.track_index(), frame_index, sample),
+				}
 			}
 			if instance.finished() {
 				self.instances_to_remove.try_push(*instance_id).ok();
 			}
 			instance.update(dt, parameters);
 		}
+		// removing an instance frees its slot
 		for instance_id in self.instances_to_remove.drain(..) {
-			self.instances.shift_remove(&instance_id);
+			self.instances.remove(&instance_id);
 		}
 	}
 }
@@ -4,89 +4,178 @@ mod tests;
 use basedrop::Owned;
 
 use crate::{
+	channel_layout::ChannelLayout,
 	command::MixerCommand,
 	frame::Frame,
+	manager::diagnostics::{Diagnostic, DiagnosticProducer, ResourceId},
 	mixer::{
-		effect::{Effect, EffectId},
-		SendTrackId, SubTrackId, Track, TrackIndex, TrackKind,
+		effect::{Effect, EffectId, EffectSettings},
+		effect_slot::EffectSlot,
+		SendTrackId, SubTrackId, Track, TrackIndex, TrackKind, MAX_BLOCK_SIZE,
 	},
 	parameter::Parameters,
-	static_container::index_map::StaticIndexMap,
+	static_container::slot_map::StaticSlotMap,
 	Value,
 };
 
 pub(crate) struct Mixer {
 	main_track: Track,
-	sub_tracks: StaticIndexMap<SubTrackId, Owned<Track>>,
-	send_tracks: StaticIndexMap<SendTrackId, Owned<Track>>,
+	sub_tracks: StaticSlotMap<SubTrackId, Owned<Track>>,
+	send_tracks: StaticSlotMap<SendTrackId, Owned<Track>>,
+	// holds the output of each track before it's routed to
+	// other tracks
+	output: Vec<Frame>,
+	// interleaved samples from instances with a surround angle,
+	// which are mixed into the output directly
+	surround_input: Vec<f32>,
+	channel_layout: ChannelLayout,
+	sample_rate: u32,
 }
 
 impl Mixer {
-	pub fn new(sub_track_capacity: usize, send_track_capacity: usize) -> Self {
+	pub fn new(
+		sub_track_capacity: usize,
+		send_track_capacity: usize,
+		channel_layout: ChannelLayout,
+		sample_rate: u32,
+	) -> Self {
 		Self {
 			main_track: Track::new_main_track(),
-			sub_tracks: StaticIndexMap::new(sub_track_capacity),
-			send_tracks: StaticIndexMap::new(send_track_capacity),
+			sub_tracks: StaticSlotMap::new(sub_track_capacity),
+			send_tracks: StaticSlotMap::new(send_track_capacity),
+			output: vec![Frame::from_mono(0.0); MAX_BLOCK_SIZE],
+			surround_input: vec![0.0; MAX_BLOCK_SIZE * channel_layout.num_channels()],
+			channel_layout,
+			sample_rate,
 		}
 	}
 
-	pub fn run_command(&mut self, command: MixerCommand) {
-		match command {
+	/// Moves all of the tracks into `other`, which has a larger
+	/// capacity, and swaps the two so that the old (now empty)
+	/// storage can be freed on another thread.
+	pub fn grow_into(&mut self, other: &mut Self) {
+		std::mem::swap(&mut self.main_track, &mut other.main_track);
+		self.sub_tracks.move_into(&mut other.sub_tracks);
+		self.send_tracks.move_into(&mut other.send_tracks);
+		// keep any surround input from earlier in the current block
+		std::mem::swap(&mut self.surround_input, &mut other.surround_input);
+		other.sample_rate = self.sample_rate;
+		std::mem::swap(self, other);
+	}
+
+	/// Reinitializes every effect for a new sample rate.
+	///
+	/// Effects can allocate when they're initialized, so this
+	/// shouldn't be called from the audio callback.
+	pub fn set_sample_rate(&mut self, sample_rate: u32) {
+		self.sample_rate = sample_rate;
+		self.main_track.set_sample_rate(sample_rate);
+		for (_, track) in &mut self.sub_tracks {
+			track.set_sample_rate(sample_rate);
+		}
+		for (_, track) in &mut self.send_tracks {
+			track.set_sample_rate(sample_rate);
+		}
+	}
+
+	pub fn run_command(&mut self, command: MixerCommand, diagnostics: &mut DiagnosticProducer) {
+		let result = match command {
 			MixerCommand::AddTrack(track) => self.add_track(track),
-			MixerCommand::SetTrackVolume(index, volume) => {
-				self.set_track_volume(index, volume);
-			}
-			MixerCommand::AddEffect(index, id, effect, settings) => {
-				self.add_effect(index, id, effect, settings);
+			MixerCommand::SetTrackVolume(index, volume) => self.set_track_volume(index, volume),
+			MixerCommand::SetTrackSurroundAngle(index, surround_angle) => {
+				self.set_track_surround_angle(index, surround_angle)
 			}
-			MixerCommand::RemoveSubTrack(id) => {
-				self.remove_sub_track(id);
-			}
-			MixerCommand::RemoveSendTrack(id) => {
-				self.remove_send_track(id);
+			MixerCommand::AddEffect(index, id, effect, settings, sample_rate) => {
+				self.add_effect(index, id, effect, settings, sample_rate)
 			}
+			MixerCommand::RemoveSubTrack(id) => self.remove_sub_track(id),
+			MixerCommand::RemoveSendTrack(id) => self.remove_send_track(id),
 			MixerCommand::SetEffectEnabled(track_index, effect_id, enabled) => {
-				self.set_effect_enabled(track_index, effect_id, enabled);
+				self.set_effect_enabled(track_index, effect_id, enabled)
 			}
 			MixerCommand::SetEffectMix(track_index, effect_id, mix) => {
-				self.set_effect_mix(track_index, effect_id, mix);
+				self.set_effect_mix(track_index, effect_id, mix)
 			}
 			MixerCommand::RemoveEffect(track_index, effect_id) => {
-				self.remove_effect(track_index, effect_id);
+				self.remove_effect(track_index, effect_id)
 			}
+		};
+		if let Err(diagnostic) = result {
+			diagnostics.report(diagnostic);
+		}
+	}
+
+	fn track_mut(&mut self, index: TrackIndex) -> Result<&mut Track, Diagnostic> {
+		match index {
+			TrackIndex::Main => Ok(&mut self.main_track),
+			TrackIndex::Sub(id) => self
+				.sub_tracks
+				.get_mut(&id)
+				.map(|track| &mut **track)
+				.ok_or(Diagnostic::ResourceNotFound(ResourceId::SubTrack(id))),
+			TrackIndex::Send(id) => self
+				.send_tracks
+				.get_mut(&id)
+				.map(|track| &mut **track)
+				.ok_or(Diagnostic::ResourceNotFound(ResourceId::SendTrack(id))),
 		}
 	}
 
-	pub fn add_track(&mut self, track: Owned<Track>) {
+	/// Returns true if the track or any of its parent tracks
+	/// are unpausable.
+	pub fn is_track_unpausable(&self, index: TrackIndex) -> bool {
+		match index {
+			TrackIndex::Sub(id) => match self.sub_tracks.get(&id) {
+				Some(track) => {
+					track.unpausable()
+						|| matches!(track.parent_track(), Some(parent) if self.is_track_unpausable(parent))
+				}
+				None => false,
+			},
+			_ => false,
+		}
+	}
+
+	pub fn add_track(&mut self, track: Owned<Track>) -> Result<(), Diagnostic> {
 		match track.kind() {
 			TrackKind::Main => {
 				panic!("No part of the public API should be adding a main track")
 			}
 			TrackKind::Sub { id, .. } => {
-				self.sub_tracks.try_insert(*id, track).ok();
+				let id = *id;
+				self.sub_tracks
+					.try_insert(id, track)
+					.map_err(|_| Diagnostic::ResourceLimitReached(ResourceId::SubTrack(id)))?;
 			}
 			TrackKind::Send { id } => {
-				self.send_tracks.try_insert(*id, track).ok();
+				let id = *id;
+				self.send_tracks
+					.try_insert(id, track)
+					.map_err(|_| Diagnostic::ResourceLimitReached(ResourceId::SendTrack(id)))?;
 			}
 		}
+		Ok(())
 	}
 
-	pub fn set_track_volume(&mut self, index: TrackIndex, volume: Value<f64>) {
-		match index {
-			TrackIndex::Main => {
-				self.main_track.set_volume(volume);
-			}
-			TrackIndex::Sub(id) => {
-				if let Some(track) = self.sub_tracks.get_mut(&id) {
-					track.set_volume(volume);
-				}
-			}
-			TrackIndex::Send(id) => {
-				if let Some(track) = self.send_tracks.get_mut(&id) {
-					track.set_volume(volume);
-				}
-			}
+	pub fn set_track_volume(
+		&mut self,
+		index: TrackIndex,
+		volume: Value<f64>,
+	) -> Result<(), Diagnostic> {
+		self.track_mut(index)?.set_volume(volume);
+		Ok(())
+	}
+
+	pub fn set_track_surround_angle(
+		&mut self,
+		index: TrackIndex,
+		surround_angle: Option<Value<f64>>,
+	) -> Result<(), Diagnostic> {
+		// the main track's output is never panned
+		if index != TrackIndex::Main {
+			self.track_mut(index)?.set_surround_angle(surround_angle);
 		}
+		Ok(())
 	}
 
 	pub fn add_effect(
@@ -94,185 +183,323 @@ impl Mixer {
 		index: TrackIndex,
 		effect_id: EffectId,
 		effect: Owned<Box<dyn Effect>>,
-		settings: crate::mixer::effect::EffectSettings,
-	) {
-		match index {
-			TrackIndex::Main => {
-				self.main_track.add_effect(effect_id, effect, settings);
+		settings: EffectSettings,
+		sample_rate: u32,
+	) -> Result<(), Diagnostic> {
+		let current_sample_rate = self.sample_rate;
+		let track = self.track_mut(index)?;
+		if track.add_effect(effect_id, effect, settings, sample_rate) {
+			// if the sample rate changed while the effect was being
+			// sent to the audio thread, it has to be initialized again
+			// here. this is rare, since it only happens when the output
+			// device changes.
+			if let Some(effect_slot) = track.effect_mut(effect_id) {
+				effect_slot.set_sample_rate(current_sample_rate);
 			}
-			TrackIndex::Sub(sub_track_id) => {
-				if let Some(track) = self.sub_tracks.get_mut(&sub_track_id) {
-					track.add_effect(effect_id, effect, settings);
-				}
-			}
-			TrackIndex::Send(send_track_id) => {
-				if let Some(track) = self.send_tracks.get_mut(&send_track_id) {
-					track.add_effect(effect_id, effect, settings);
-				}
-			}
-		};
+			Ok(())
+		} else {
+			Err(Diagnostic::ResourceLimitReached(ResourceId::Effect(
+				effect_id,
+			)))
+		}
+	}
+
+	pub fn remove_sub_track(&mut self, id: SubTrackId) -> Result<(), Diagnostic> {
+		self.sub_tracks
+			.remove(&id)
+			.map(|_| ())
+			.ok_or(Diagnostic::ResourceNotFound(ResourceId::SubTrack(id)))
 	}
 
-	pub fn remove_sub_track(&mut self, id: SubTrackId) {
-		self.sub_tracks.remove(&id);
+	pub fn remove_send_track(&mut self, id: SendTrackId) -> Result<(), Diagnostic> {
+		self.send_tracks
+			.remove(&id)
+			.map(|_| ())
+			.ok_or(Diagnostic::ResourceNotFound(ResourceId::SendTrack(id)))
 	}
 
-	pub fn remove_send_track(&mut self, id: SendTrackId) {
-		self.send_tracks.remove(&id);
+	fn effect_mut(
+		&mut self,
+		track_index: TrackIndex,
+		effect_id: EffectId,
+	) -> Result<&mut EffectSlot, Diagnostic> {
+		self.track_mut(track_index)?
+			.effect_mut(effect_id)
+			.ok_or(Diagnostic::ResourceNotFound(ResourceId::Effect(effect_id)))
 	}
 
 	pub fn set_effect_enabled(
 		&mut self,
 		track_index: TrackIndex,
-		effect_id: crate::mixer::effect::EffectId,
+		effect_id: EffectId,
 		enabled: bool,
-	) {
-		match track_index {
-			TrackIndex::Main => {
-				if let Some(effect_slot) = self.main_track.effect_mut(effect_id) {
-					effect_slot.enabled = enabled;
-				}
-			}
-			TrackIndex::Sub(id) => {
-				if let Some(track) = self.sub_tracks.get_mut(&id) {
-					if let Some(effect_slot) = track.effect_mut(effect_id) {
-						effect_slot.enabled = enabled;
-					}
-				}
-			}
-			TrackIndex::Send(id) => {
-				if let Some(track) = self.send_tracks.get_mut(&id) {
-					if let Some(effect_slot) = track.effect_mut(effect_id) {
-						effect_slot.enabled = enabled;
-					}
-				}
-			}
-		};
+	) -> Result<(), Diagnostic> {
+		self.effect_mut(track_index, effect_id)?.enabled = enabled;
+		Ok(())
 	}
 
 	pub fn set_effect_mix(
 		&mut self,
 		track_index: TrackIndex,
-		effect_id: crate::mixer::effect::EffectId,
+		effect_id: EffectId,
 		mix: Value<f64>,
-	) {
-		match track_index {
-			TrackIndex::Main => {
-				if let Some(effect_slot) = self.main_track.effect_mut(effect_id) {
-					effect_slot.mix.set(mix);
-				}
-			}
-			TrackIndex::Sub(id) => {
-				if let Some(track) = self.sub_tracks.get_mut(&id) {
-					if let Some(effect_slot) = track.effect_mut(effect_id) {
-						effect_slot.mix.set(mix);
-					}
-				}
-			}
-			TrackIndex::Send(id) => {
-				if let Some(track) = self.send_tracks.get_mut(&id) {
-					if let Some(effect_slot) = track.effect_mut(effect_id) {
-						effect_slot.mix.set(mix);
-					}
-				}
-			}
-		};
+	) -> Result<(), Diagnostic> {
+		self.effect_mut(track_index, effect_id)?.mix.set(mix);
+		Ok(())
 	}
 
 	pub fn remove_effect(
 		&mut self,
 		track_index: TrackIndex,
-		effect_id: crate::mixer::effect::EffectId,
-	) {
-		match track_index {
+		effect_id: EffectId,
+	) -> Result<(), Diagnostic> {
+		self.track_mut(track_index)?
+			.remove_effect(effect_id)
+			.map(|_| ())
+			.ok_or(Diagnostic::ResourceNotFound(ResourceId::Effect(effect_id)))
+	}
+
+	/// Adds audio to the frame at `frame_index` in the current
+	/// block of a track.
+	pub fn add_input(&mut self, index: TrackIndex, frame_index: usize, input: Frame) {
+		match index {
 			TrackIndex::Main => {
-				self.main_track.remove_effect(effect_id);
+				self.main_track.add_input(frame_index, input);
 			}
 			TrackIndex::Sub(id) => {
 				if let Some(track) = self.sub_tracks.get_mut(&id) {
-					track.remove_effect(effect_id);
+					track.add_input(frame_index, input);
 				}
 			}
 			TrackIndex::Send(id) => {
 				if let Some(track) = self.send_tracks.get_mut(&id) {
-					track.remove_effect(effect_id);
+					track.add_input(frame_index, input);
 				}
 			}
-		};
+		}
 	}
 
-	pub fn add_input(&mut self, index: TrackIndex, input: Frame) {
+	/// Adds a block of audio to the current block of a track.
+	pub fn add_input_block(&mut self, index: TrackIndex, input: &[Frame]) {
 		match index {
 			TrackIndex::Main => {
-				self.main_track.add_input(input);
+				self.main_track.add_input_block(input);
 			}
 			TrackIndex::Sub(id) => {
 				if let Some(track) = self.sub_tracks.get_mut(&id) {
-					track.add_input(input);
+					track.add_input_block(input);
 				}
 			}
 			TrackIndex::Send(id) => {
 				if let Some(track) = self.send_tracks.get_mut(&id) {
-					track.add_input(input);
+					track.add_input_block(input);
 				}
 			}
 		}
 	}
 
-	/// Processes a sub-track.
-	fn process_sub_track(&mut self, id: SubTrackId, dt: f64, parameters: &Parameters) -> Frame {
-		// process all children of this sub-track and accumulate their outputs
-		let mut children_input = Frame::from_mono(0.0);
-		for i in 0..self.sub_tracks.len() {
-			let (child_id, child_track) = self.sub_tracks.get_index(i).unwrap();
+	/// Pans audio from an instance with a surround angle into the
+	/// frame at `frame_index` of the current block of the output.
+	///
+	/// The audio skips the track's effects, but it's scaled by the
+	/// volumes of the track and its parent tracks. The main track's
+	/// volume is applied in [`process`](Self::process).
+	pub fn add_surround_input(
+		&mut self,
+		index: TrackIndex,
+		frame_index: usize,
+		input: Frame,
+		angle: f64,
+	) {
+		let volume = match self.track_chain_volume(index) {
+			Some(volume) => volume,
+			None => return,
+		};
+		let channels = self.channel_layout.num_channels();
+		let out = &mut self.surround_input[frame_index * channels..(frame_index + 1) * channels];
+		self.channel_layout.pan(input * volume, angle, out);
+	}
+
+	/// Returns the combined volume of a track and all of its
+	/// parent tracks, not including the main track, or `None`
+	/// if any of the tracks don't exist.
+	fn track_chain_volume(&self, mut index: TrackIndex) -> Option<f32> {
+		let mut volume = 1.0;
+		loop {
+			let track = match index {
+				TrackIndex::Main => return Some(volume),
+				TrackIndex::Sub(id) => self.sub_tracks.get(&id)?,
+				TrackIndex::Send(id) => self.send_tracks.get(&id)?,
+			};
+			volume *= track.volume() as f32;
+			index = track.parent_track()?;
+		}
+	}
+
+	/// Sends a block of a track's output to the main track, or places
+	/// it directly in the output if the track has a surround angle.
+	fn route_to_main_track(
+		main_track: &mut Track,
+		channel_layout: ChannelLayout,
+		output: &[Frame],
+		surround_angle: Option<f64>,
+		out: &mut [f32],
+	) {
+		match surround_angle {
+			Some(angle) => {
+				let channels = channel_layout.num_channels();
+				for (out, frame) in out.chunks_exact_mut(channels).zip(output.iter()) {
+					channel_layout.pan(*frame, angle, out);
+				}
+			}
+			None => main_track.add_input_block(output),
+		}
+	}
+
+	/// Processes a sub-track and routes its output to its parent
+	/// track and send tracks.
+	fn process_sub_track(
+		&mut self,
+		id: SubTrackId,
+		dt: f64,
+		parameters: &Parameters,
+		num_frames: usize,
+		out: &mut [f32],
+	) {
+		// process all children of this sub-track. they'll add their
+		// output to this track's input
+		for i in 0..self.sub_tracks.capacity() {
+			let (child_id, child_track) = match self.sub_tracks.get_slot(i) {
+				Some(slot) => slot,
+				None => continue,
+			};
 			let child_id = *child_id;
 			if let Some(parent_track) = child_track.parent_track() {
 				if parent_track == TrackIndex::Sub(id) {
-					children_input += self.process_sub_track(child_id, dt, parameters);
+					self.process_sub_track(child_id, dt, parameters, num_frames, out);
 				}
 			}
 		}
-		if let Some(sub_track) = self.sub_tracks.get_mut(&id) {
-			// process this track
-			sub_track.add_input(children_input);
-			let output = sub_track.process(dt, parameters);
-			// route this track's output to send tracks
-			if let TrackKind::Sub { sends, .. } = &sub_track.kind() {
-				for (send_track_id, send_volume) in sends.iter() {
-					if let Some(send_track) = self.send_tracks.get_mut(send_track_id) {
-						send_track.add_input(output * send_volume.value() as f32);
+		let output = &mut self.output[..num_frames];
+		let (parent_track, surround_angle) = match self.sub_tracks.get_mut(&id) {
+			Some(sub_track) => {
+				// process this track
+				sub_track.process(dt, parameters, output);
+				// route this track's output to send tracks
+				if let TrackKind::Sub { sends, .. } = &sub_track.kind() {
+					for (send_track_id, send_volume) in sends.iter() {
+						if let Some(send_track) = self.send_tracks.get_mut(send_track_id) {
+							send_track.add_input_block_with_volume(output, send_volume);
+						}
 					}
 				}
-				return output;
+				(sub_track.parent_track(), sub_track.surround_angle())
+			}
+			None => return,
+		};
+		// route this track's output to its parent track
+		match parent_track {
+			Some(TrackIndex::Main) => Self::route_to_main_track(
+				&mut self.main_track,
+				self.channel_layout,
+				output,
+				surround_angle,
+				out,
+			),
+			Some(TrackIndex::Sub(parent_id)) => {
+				if let Some(parent_track) = self.sub_tracks.get_mut(&parent_id) {
+					parent_track.add_input_block(output);
+				}
 			}
+			_ => {}
 		}
-		Frame::from_mono(0.0)
 	}
 
 	/// Processes all top-level sub-tracks (sub-tracks that output directly
 	/// to the main track) and sends their output to the main and send tracks.
-	fn process_sub_tracks(&mut self, dt: f64, parameters: &Parameters) {
-		for i in 0..self.sub_tracks.len() {
-			let (id, track) = self.sub_tracks.get_index(i).unwrap();
+	fn process_sub_tracks(
+		&mut self,
+		dt: f64,
+		parameters: &Parameters,
+		num_frames: usize,
+		out: &mut [f32],
+	) {
+		for i in 0..self.sub_tracks.capacity() {
+			let (id, track) = match self.sub_tracks.get_slot(i) {
+				Some(slot) => slot,
+				None => continue,
+			};
 			let id = *id;
 			if let Some(TrackIndex::Main) = track.parent_track() {
-				let output = self.process_sub_track(id, dt, parameters);
-				self.main_track.add_input(output);
+				self.process_sub_track(id, dt, parameters, num_frames, out);
 			}
 		}
 	}
 
 	/// Processes all send tracks and sends their output to the main track.
-	fn process_send_tracks(&mut self, dt: f64, parameters: &Parameters) {
+	fn process_send_tracks(
+		&mut self,
+		dt: f64,
+		parameters: &Parameters,
+		num_frames: usize,
+		out: &mut [f32],
+	) {
+		let output = &mut self.output[..num_frames];
+		for (_, track) in &mut self.send_tracks {
+			track.process(dt, parameters, output);
+			Self::route_to_main_track(
+				&mut self.main_track,
+				self.channel_layout,
+				output,
+				track.surround_angle(),
+				out,
+			);
+		}
+	}
+
+	/// Processes all tracks and writes the output to `out` as
+	/// interleaved samples in the mixer's channel layout.
+	///
+	/// The number of frames processed is the length of `out` divided
+	/// by the number of channels, which can be at most [`MAX_BLOCK_SIZE`].
+	pub fn process(&mut self, dt: f64, parameters: &Parameters, out: &mut [f32]) {
+		let channels = self.channel_layout.num_channels();
+		let num_frames = out.len() / channels;
+		// start with the output of positioned instances
+		for (out, input) in out.iter_mut().zip(self.surround_input.iter_mut()) {
+			*out = *input;
+			*input = 0.0;
+		}
+		self.process_sub_tracks(dt, parameters, num_frames, out);
+		self.process_send_tracks(dt, parameters, num_frames, out);
+		let output = &mut self.output[..num_frames];
+		self.main_track.process(dt, parameters, output);
+		// tracks and instances with a surround angle skip the main
+		// track, but they should still be affected by its volume
+		for ((out, frame), main_volume) in out
+			.chunks_exact_mut(channels)
+			.zip(output.iter())
+			.zip(self.main_track.volume_ramp(num_frames))
+		{
+			out.iter_mut().for_each(|sample| *sample *= main_volume);
+			self.channel_layout.upmix(*frame, out);
+		}
+	}
+
+	/// Reports any effects that have panicked since the last
+	/// time this was called.
+	pub fn report_panicked_effects(&mut self, diagnostics: &mut DiagnosticProducer) {
+		self.main_track.report_panicked_effects(diagnostics);
+		for (_, track) in &mut self.sub_tracks {
+			track.report_panicked_effects(diagnostics);
+		}
 		for (_, track) in &mut self.send_tracks {
-			self.main_track.add_input(track.process(dt, parameters));
+			track.report_panicked_effects(diagnostics);
 		}
 	}
 
-	/// Processes all tracks.
-	pub fn process(&mut self, dt: f64, parameters: &Parameters) -> Frame {
-		self.process_sub_tracks(dt, parameters);
-		self.process_send_tracks(dt, parameters);
-		self.main_track.process(dt, parameters)
+	/// Returns the channel layout the mixer outputs.
+	pub fn channel_layout(&self) -> ChannelLayout {
+		self.channel_layout
 	}
 }
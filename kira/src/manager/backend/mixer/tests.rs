@@ -1,88 +1,113 @@
 use basedrop::{Collector, Owned};
 
 use crate::{
-	mixer::{SendTrackId, SendTrackSettings, SubTrackId, SubTrackSettings, Track, TrackSends},
+	channel_layout::ChannelLayout,
+	mixer::{
+		effect::{Effect, EffectId, EffectSettings},
+		SendTrackSettings, SubTrackSettings, Track, TrackIndex, TrackSends,
+	},
 	parameter::Parameters,
-	Frame,
+	static_container::slot_map::{SlotIndex, SlotKey},
+	Frame, Value,
 };
 
 use super::Mixer;
 
+fn slot_id<K: SlotKey>(index: u32) -> K {
+	K::from_slot_index(SlotIndex {
+		index,
+		generation: 0,
+	})
+}
+
 #[test]
 fn routes_audio_to_parent_tracks() {
 	let collector = Collector::new();
 	let parameters = Parameters::new(100);
-	let mut mixer = Mixer::new(100, 100);
+	let mut mixer = Mixer::new(100, 100, ChannelLayout::Stereo, 48000);
 	// parent track has a volume of 50%
 	let parent_track_id = {
 		let settings = SubTrackSettings::new().volume(0.5);
-		let id = settings.id.unwrap_or(SubTrackId::new());
-		mixer.add_track(Owned::new(
-			&collector.handle(),
-			Track::new_sub_track(id, settings),
-		));
+		let id = settings.id.unwrap_or_else(|| slot_id(0));
+		mixer
+			.add_track(Owned::new(
+				&collector.handle(),
+				Track::new_sub_track(id, settings),
+			))
+			.unwrap();
 		id
 	};
 	// sub tracks 1 and 2 are routed into the parent track
 	let sub_track_1_id = {
 		let settings = SubTrackSettings::new().parent_track(parent_track_id);
-		let id = settings.id.unwrap_or(SubTrackId::new());
-		mixer.add_track(Owned::new(
-			&collector.handle(),
-			Track::new_sub_track(id, settings),
-		));
+		let id = settings.id.unwrap_or_else(|| slot_id(1));
+		mixer
+			.add_track(Owned::new(
+				&collector.handle(),
+				Track::new_sub_track(id, settings),
+			))
+			.unwrap();
 		id
 	};
 	let sub_track_2_id = {
 		let settings = SubTrackSettings::new().parent_track(parent_track_id);
-		let id = settings.id.unwrap_or(SubTrackId::new());
-		mixer.add_track(Owned::new(
-			&collector.handle(),
-			Track::new_sub_track(id, settings),
-		));
+		let id = settings.id.unwrap_or_else(|| slot_id(2));
+		mixer
+			.add_track(Owned::new(
+				&collector.handle(),
+				Track::new_sub_track(id, settings),
+			))
+			.unwrap();
 		id
 	};
 	// sub track 3 is routed directly to main
 	let sub_track_3_id = {
 		let settings = SubTrackSettings::new();
-		let id = settings.id.unwrap_or(SubTrackId::new());
-		mixer.add_track(Owned::new(
-			&collector.handle(),
-			Track::new_sub_track(id, settings),
-		));
+		let id = settings.id.unwrap_or_else(|| slot_id(3));
+		mixer
+			.add_track(Owned::new(
+				&collector.handle(),
+				Track::new_sub_track(id, settings),
+			))
+			.unwrap();
 		id
 	};
 	// each sub-track will contribute one digit of signal to the final output.
 	// sub-tracks 1 and 2 should have their digits be halved to 1,
 	// since their parent track has a volume factor of 0.5.
-	mixer.add_input(sub_track_1_id.into(), Frame::from_mono(200.0));
-	mixer.add_input(sub_track_2_id.into(), Frame::from_mono(020.0));
-	mixer.add_input(sub_track_3_id.into(), Frame::from_mono(002.0));
-	let out = mixer.process(1.0, &parameters);
-	assert_eq!(out, Frame::from_mono(112.0));
+	mixer.add_input(sub_track_1_id.into(), 0, Frame::from_mono(200.0));
+	mixer.add_input(sub_track_2_id.into(), 0, Frame::from_mono(020.0));
+	mixer.add_input(sub_track_3_id.into(), 0, Frame::from_mono(002.0));
+	let mut out = [0.0; 2];
+	mixer.process(1.0, &parameters, &mut out);
+	assert_eq!(out, [112.0; 2]);
 }
 
 #[test]
 fn routes_audio_to_send_tracks() {
 	let collector = Collector::new();
 	let parameters = Parameters::new(100);
-	let mut mixer = Mixer::new(100, 100);
+	let mut mixer = Mixer::new(100, 100, ChannelLayout::Stereo, 48000);
 	let send_track_1_id = {
 		let settings = SendTrackSettings::new();
-		let id = settings.id.unwrap_or(SendTrackId::new());
-		mixer.add_track(Owned::new(
-			&collector.handle(),
-			Track::new_send_track(id, settings),
-		));
+		let id = settings.id.unwrap_or_else(|| slot_id(4));
+		mixer
+			.add_track(Owned::new(
+				&collector.handle(),
+				Track::new_send_track(id, settings),
+			))
+			.unwrap();
 		id
 	};
 	let send_track_2_id = {
 		let settings = SendTrackSettings::new();
-		let id = settings.id.unwrap_or(SendTrackId::new());
-		mixer.add_track(Owned::new(
-			&collector.handle(),
-			Track::new_send_track(id, settings),
-		));
+		let id = settings.id.unwrap_or_else(|| slot_id(5));
+		mixer
+			.add_track(Owned::new(
+				&collector.handle(),
+				Track::new_send_track(id, settings),
+			))
+			.unwrap();
 		id
 	};
 	let sub_track_id = {
@@ -91,14 +116,218 @@ fn routes_audio_to_send_tracks() {
 				.add(send_track_1_id, 0.1)
 				.add(send_track_2_id, 0.01),
 		);
-		let id = settings.id.unwrap_or(SubTrackId::new());
-		mixer.add_track(Owned::new(
-			&collector.handle(),
-			Track::new_sub_track(id, settings),
-		));
+		let id = settings.id.unwrap_or_else(|| slot_id(6));
+		mixer
+			.add_track(Owned::new(
+				&collector.handle(),
+				Track::new_sub_track(id, settings),
+			))
+			.unwrap();
+		id
+	};
+	mixer.add_input(sub_track_id.into(), 0, Frame::from_mono(100.0));
+	let mut out = [0.0; 2];
+	mixer.process(1.0, &parameters, &mut out);
+	assert_eq!(out, [111.0; 2]);
+}
+
+#[test]
+fn applies_per_frame_effects_to_blocks() {
+	#[derive(Debug)]
+	struct Doubler;
+
+	impl Effect for Doubler {
+		fn process(&mut self, _dt: f64, input: Frame, _parameters: &Parameters) -> Frame {
+			input * 2.0
+		}
+	}
+
+	let collector = Collector::new();
+	let parameters = Parameters::new(100);
+	let mut mixer = Mixer::new(100, 100, ChannelLayout::Stereo, 48000);
+	mixer
+		.add_effect(
+			TrackIndex::Main,
+			EffectId::new(),
+			Owned::new(&collector.handle(), Box::new(Doubler)),
+			EffectSettings::new(),
+			48000,
+		)
+		.unwrap();
+	for i in 0..4 {
+		mixer.add_input(TrackIndex::Main, i, Frame::from_mono(i as f32));
+	}
+	let mut out = [0.0; 8];
+	mixer.process(1.0, &parameters, &mut out);
+	for (i, frame) in out.chunks_exact(2).enumerate() {
+		assert_eq!(frame, [i as f32 * 2.0; 2]);
+	}
+	// the input should be cleared after each block
+	mixer.process(1.0, &parameters, &mut out);
+	assert_eq!(out, [0.0; 8]);
+}
+
+#[test]
+fn ramps_volume_and_effect_mix_changes_over_a_block() {
+	#[derive(Debug)]
+	struct Silencer;
+
+	impl Effect for Silencer {
+		fn process(&mut self, _dt: f64, _input: Frame, _parameters: &Parameters) -> Frame {
+			Frame::from_mono(0.0)
+		}
+	}
+
+	let collector = Collector::new();
+	let parameters = Parameters::new(100);
+	let mut mixer = Mixer::new(100, 100, ChannelLayout::Stereo, 48000);
+	let effect_id = EffectId::new();
+	mixer
+		.add_effect(
+			TrackIndex::Main,
+			effect_id,
+			Owned::new(&collector.handle(), Box::new(Silencer)),
+			EffectSettings::new().mix(0.0),
+			48000,
+		)
+		.unwrap();
+	let process = |mixer: &mut Mixer| {
+		for i in 0..4 {
+			mixer.add_input(TrackIndex::Main, i, Frame::from_mono(1.0));
+		}
+		let mut out = [0.0; 8];
+		mixer.process(1.0, &parameters, &mut out);
+		out.chunks_exact(2)
+			.map(|frame| frame[0])
+			.collect::<Vec<_>>()
+	};
+	assert_eq!(process(&mut mixer), [1.0; 4]);
+	// turning the effect on fades from the dry signal to the
+	// wet signal over the next block
+	mixer
+		.set_effect_mix(TrackIndex::Main, effect_id, Value::Fixed(1.0))
+		.unwrap();
+	assert_eq!(process(&mut mixer), [0.75, 0.5, 0.25, 0.0]);
+	assert_eq!(process(&mut mixer), [0.0; 4]);
+	mixer
+		.set_effect_mix(TrackIndex::Main, effect_id, Value::Fixed(0.0))
+		.unwrap();
+	assert_eq!(process(&mut mixer), [0.25, 0.5, 0.75, 1.0]);
+	// volume changes are ramped the same way
+	mixer
+		.set_track_volume(TrackIndex::Main, Value::Fixed(0.5))
+		.unwrap();
+	assert_eq!(process(&mut mixer), [0.875, 0.75, 0.625, 0.5]);
+	assert_eq!(process(&mut mixer), [0.5; 4]);
+}
+
+#[test]
+fn places_tracks_in_surround_layouts() {
+	let collector = Collector::new();
+	let parameters = Parameters::new(100);
+	let mut mixer = Mixer::new(100, 100, ChannelLayout::Quad, 48000);
+	let mut next_index = 0;
+	let mut add_sub_track = |settings: SubTrackSettings| {
+		let id = settings.id.unwrap_or_else(|| slot_id(next_index));
+		next_index += 1;
+		mixer
+			.add_track(Owned::new(
+				&collector.handle(),
+				Track::new_sub_track(id, settings),
+			))
+			.unwrap();
 		id
 	};
-	mixer.add_input(sub_track_id.into(), Frame::from_mono(100.0));
-	let out = mixer.process(1.0, &parameters);
-	assert_eq!(out, Frame::from_mono(111.0));
+	// directly on the front right speaker
+	let front_right_track_id = add_sub_track(SubTrackSettings::new().surround_angle(45.0));
+	// halfway between the front right and back right speakers
+	let right_track_id = add_sub_track(SubTrackSettings::new().surround_angle(90.0));
+	// no surround angle, so it should be sent to the front speakers
+	let stereo_track_id = add_sub_track(SubTrackSettings::new());
+
+	mixer.add_input(front_right_track_id.into(), 0, Frame::from_mono(1.0));
+	let mut out = [0.0; 4];
+	mixer.process(1.0, &parameters, &mut out);
+	assert_eq!(out, [0.0, 1.0, 0.0, 0.0]);
+
+	mixer.add_input(right_track_id.into(), 0, Frame::from_mono(1.0));
+	mixer.process(1.0, &parameters, &mut out);
+	assert!(out[0].abs() < 0.0001);
+	assert!((out[1] - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.0001);
+	assert!(out[2].abs() < 0.0001);
+	assert!((out[3] - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.0001);
+
+	mixer.add_input(stereo_track_id.into(), 0, Frame::new(1.0, 2.0));
+	mixer.process(1.0, &parameters, &mut out);
+	assert_eq!(out, [1.0, 2.0, 0.0, 0.0]);
+}
+
+#[test]
+fn mixes_positioned_audio_after_the_main_track() {
+	#[derive(Debug)]
+	struct Muter;
+
+	impl Effect for Muter {
+		fn process(&mut self, _dt: f64, _input: Frame, _parameters: &Parameters) -> Frame {
+			Frame::from_mono(0.0)
+		}
+	}
+
+	let collector = Collector::new();
+	let parameters = Parameters::new(100);
+	let mut mixer = Mixer::new(100, 100, ChannelLayout::Quad, 48000);
+	// the main track mutes everything that goes through it
+	// and has a volume of 50%
+	mixer
+		.add_effect(
+			TrackIndex::Main,
+			EffectId::new(),
+			Owned::new(&collector.handle(), Box::new(Muter)),
+			EffectSettings::new(),
+			48000,
+		)
+		.unwrap();
+	mixer
+		.set_track_volume(TrackIndex::Main, 0.5.into())
+		.unwrap();
+	let positioned_track_id = {
+		let settings = SubTrackSettings::new().surround_angle(45.0);
+		let id = settings.id.unwrap_or_else(|| slot_id(0));
+		mixer
+			.add_track(Owned::new(
+				&collector.handle(),
+				Track::new_sub_track(id, settings),
+			))
+			.unwrap();
+		id
+	};
+	let stereo_track_id = {
+		let settings = SubTrackSettings::new().volume(0.5);
+		let id = settings.id.unwrap_or_else(|| slot_id(1));
+		mixer
+			.add_track(Owned::new(
+				&collector.handle(),
+				Track::new_sub_track(id, settings),
+			))
+			.unwrap();
+		id
+	};
+	let mut out = [0.0; 4];
+
+	// positioned tracks skip the main track's effects,
+	// but not its volume
+	mixer.add_input(positioned_track_id.into(), 0, Frame::from_mono(1.0));
+	mixer.process(1.0, &parameters, &mut out);
+	assert_eq!(out, [0.0, 0.5, 0.0, 0.0]);
+
+	// so do positioned instances, which are also affected by
+	// the volume of the track they're playing on
+	mixer.add_surround_input(stereo_track_id.into(), 0, Frame::from_mono(1.0), -45.0);
+	mixer.process(1.0, &parameters, &mut out);
+	assert_eq!(out, [0.25, 0.0, 0.0, 0.0]);
+
+	// stereo audio goes through the main track's effects
+	mixer.add_input(stereo_track_id.into(), 0, Frame::from_mono(1.0));
+	mixer.process(1.0, &parameters, &mut out);
+	assert_eq!(out, [0.0; 4]);
 }
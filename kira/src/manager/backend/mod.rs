@@ -1,25 +1,53 @@
-mod instances;
-mod mixer;
-mod sequences;
-mod streams;
+pub(crate) mod instances;
+pub(crate) mod mixer;
+pub(crate) mod sequences;
+pub(crate) mod streams;
+mod suspension;
 
 use self::mixer::Mixer;
 
-use super::AudioManagerSettings;
+use std::sync::Arc;
+
+use super::{
+	diagnostics::{Diagnostic, DiagnosticProducer},
+	stats::SharedStats,
+	AudioManagerSettings,
+};
 use crate::{
-	command::Command, frame::Frame, group::groups::Groups, metronome::Metronomes,
-	parameter::Parameters, playable::Playables, static_container::vec::StaticVec,
+	audio_stream::AudioStreamId,
+	channel_layout::{ChannelLayout, MAX_CHANNELS},
+	command::{Command, GrowPoolCommand, ScheduledCommand},
+	frame::Frame,
+	group::groups::Groups,
+	instance::InstanceSlots,
+	metronome::Metronomes,
+	mixer::MAX_BLOCK_SIZE,
+	parameter::Parameters,
+	playable::Playables,
+	random,
+	static_container::vec::StaticVec,
 };
 use instances::Instances;
-use ringbuf::Consumer;
+use instant::Instant;
+use rand::{rngs::StdRng, SeedableRng};
+use ringbuf::{Consumer, Producer};
 use sequences::Sequences;
 use streams::Streams;
+use suspension::Suspension;
 
 /// Processes audio on the audio thread.
 pub struct Backend {
 	dt: f64,
+	// the number of frames rendered since the backend was created
+	clock_time: u64,
 	playables: Playables,
+	// lent to the current thread while producing audio
+	// so random values are picked deterministically
+	rng: Option<StdRng>,
 	command_queue: StaticVec<Command>,
+	// commands waiting for the audio clock to reach a certain time
+	scheduled_commands: StaticVec<(u64, ScheduledCommand)>,
+	sequence_command_queue: StaticVec<Command>,
 	command_consumer: Consumer<Command>,
 	metronomes: Metronomes,
 	parameters: Parameters,
@@ -28,6 +56,18 @@ pub struct Backend {
 	mixer: Mixer,
 	groups: Groups,
 	streams: Streams,
+	// interleaved samples for the block currently being rendered
+	output: Vec<f32>,
+	// whether the audio manager is paused. only unpausable
+	// instances, sequences, and streams are updated while paused
+	paused: bool,
+	suspension: Suspension,
+	// the volume of each frame in the current block while fading
+	// out to suspend or fading in to resume
+	suspension_volumes: [f32; MAX_BLOCK_SIZE],
+	stats: Arc<SharedStats>,
+	diagnostics: DiagnosticProducer,
+	removed_stream_id_producer: Producer<AudioStreamId>,
 }
 
 impl Backend {
@@ -35,80 +75,377 @@ impl Backend {
 		sample_rate: u32,
 		settings: AudioManagerSettings,
 		command_consumer: Consumer<Command>,
+		stats: Arc<SharedStats>,
+		diagnostic_producer: Producer<Diagnostic>,
+		removed_stream_id_producer: Producer<AudioStreamId>,
+		instance_slots: InstanceSlots,
 	) -> Self {
 		Self {
 			dt: 1.0 / sample_rate as f64,
+			clock_time: 0,
 			playables: Playables::new(settings.num_sounds, settings.num_arrangements),
+			rng: Some(match settings.seed {
+				Some(seed) => StdRng::seed_from_u64(seed),
+				None => StdRng::from_entropy(),
+			}),
 			command_queue: StaticVec::new(settings.num_commands),
+			scheduled_commands: StaticVec::new(settings.num_scheduled_commands),
 			command_consumer,
 			parameters: Parameters::new(settings.num_parameters),
 			metronomes: Metronomes::new(settings.num_metronomes),
-			instances: Instances::new(settings.num_instances),
-			sequences: Sequences::new(settings.num_sequences, settings.num_commands),
-			mixer: Mixer::new(settings.num_sub_tracks, settings.num_send_tracks),
+			instances: Instances::new(instance_slots),
+			sequence_command_queue: StaticVec::new(settings.num_sequence_commands),
+			sequences: Sequences::new(settings.num_sequences, settings.num_sequence_commands),
+			mixer: Mixer::new(
+				settings.num_sub_tracks,
+				settings.num_send_tracks,
+				settings.channel_layout,
+				sample_rate,
+			),
 			groups: Groups::new(settings.num_groups),
 			streams: Streams::new(settings.num_streams),
+			output: vec![0.0; MAX_BLOCK_SIZE * MAX_CHANNELS],
+			paused: false,
+			suspension: Suspension::new(),
+			suspension_volumes: [1.0; MAX_BLOCK_SIZE],
+			stats,
+			diagnostics: DiagnosticProducer::new(diagnostic_producer),
+			removed_stream_id_producer,
+		}
+	}
+
+	/// Changes the sample rate the backend produces audio at.
+	///
+	/// This should be called if the output the backend is
+	/// sending audio to changes its sample rate, for example
+	/// because it moved to a different device. Effects are
+	/// initialized again with the new sample rate, which can
+	/// allocate memory, so this shouldn't be called from the
+	/// audio callback.
+	pub fn set_sample_rate(&mut self, sample_rate: u32) {
+		self.dt = 1.0 / sample_rate as f64;
+		self.stats.set_sample_rate(sample_rate);
+		self.mixer.set_sample_rate(sample_rate);
+	}
+
+	/// Adds a command sent from the audio manager to the command
+	/// queue, or to the list of scheduled commands if it should
+	/// run later.
+	fn queue_command(&mut self, command: Command) {
+		let result = match command {
+			Command::Batch(mut commands) => {
+				self.queue_batch(&mut commands);
+				return;
+			}
+			Command::Scheduled(time, command) if time > self.clock_time => {
+				// keep the list sorted from latest to earliest, with
+				// commands scheduled for the same time in reverse order,
+				// so the next command to run is always at the end
+				let index = self
+					.scheduled_commands
+					.iter()
+					.position(|(scheduled_time, _)| *scheduled_time <= time)
+					.unwrap_or_else(|| self.scheduled_commands.len());
+				self.scheduled_commands.try_insert(index, (time, command))
+			}
+			// commands scheduled for the current time (or a time
+			// that's already passed) run immediately
+			Command::Scheduled(_, command) => self.command_queue.try_push(command.into()),
+			command => self.command_queue.try_push(command),
+		};
+		if result.is_err() {
+			self.stats.record_dropped_command();
+			self.diagnostics.report(Diagnostic::CommandDropped);
+		}
+	}
+
+	/// Queues up every command in a batch, or none of them if
+	/// there isn't room for all of them.
+	fn queue_batch(&mut self, commands: &mut Vec<Command>) {
+		let clock_time = self.clock_time;
+		let num_scheduled_commands = commands
+			.iter()
+			.filter(|command| matches!(command, Command::Scheduled(time, _) if *time > clock_time))
+			.count();
+		let num_immediate_commands = commands.len() - num_scheduled_commands;
+		if num_immediate_commands > self.command_queue.capacity() - self.command_queue.len()
+			|| num_scheduled_commands
+				> self.scheduled_commands.capacity() - self.scheduled_commands.len()
+		{
+			for _ in 0..commands.len() {
+				self.stats.record_dropped_command();
+			}
+			self.diagnostics.report(Diagnostic::BatchDropped);
+			return;
+		}
+		for command in commands.drain(..) {
+			self.queue_command(command);
 		}
 	}
 
 	fn process_commands(&mut self) {
 		while let Some(command) = self.command_consumer.pop() {
-			// TODO: find a way to avoid sharing the command queue
-			// between user-called functions and sequence-produced
-			// commands. I don't want sequence commands cutting
-			// into the capacity of the command queue
-			self.command_queue.try_push(command).ok();
+			self.queue_command(command);
+		}
+		while let Some((time, _)) = self.scheduled_commands.last() {
+			if *time > self.clock_time {
+				break;
+			}
+			let (_, command) = self.scheduled_commands.pop().unwrap();
+			if self.command_queue.try_push(command.into()).is_err() {
+				self.stats.record_dropped_command();
+				self.diagnostics.report(Diagnostic::CommandDropped);
+			}
 		}
-		for command in self.command_queue.drain(..) {
+		// run the commands sent from the audio manager and handles first,
+		// followed by the commands sequences produced on the last frame
+		for command in self
+			.command_queue
+			.drain(..)
+			.chain(self.sequence_command_queue.drain(..))
+		{
 			match command {
+				// batches and scheduled commands are unwrapped before
+				// they're added to the command queue
+				Command::Batch(..) | Command::Scheduled(..) => {}
+				// the old storage is dropped along with the command, so
+				// it's freed by the resource collector rather than on
+				// the audio thread
+				Command::GrowPool(command) => match command {
+					GrowPoolCommand::Playables(mut playables) => {
+						self.playables.grow_into(&mut playables)
+					}
+					GrowPoolCommand::Instances(mut instances) => {
+						self.instances.grow_into(&mut instances)
+					}
+					GrowPoolCommand::Sequences(mut sequences) => {
+						self.sequences.grow_into(&mut sequences)
+					}
+					GrowPoolCommand::Mixer(mut mixer) => self.mixer.grow_into(&mut mixer),
+					GrowPoolCommand::Parameters(mut parameters) => {
+						self.parameters.grow_into(&mut parameters)
+					}
+					GrowPoolCommand::Groups(mut groups) => self.groups.grow_into(&mut groups),
+					GrowPoolCommand::Streams(mut streams) => self.streams.grow_into(&mut streams),
+					GrowPoolCommand::Metronomes(mut metronomes) => {
+						self.metronomes.grow_into(&mut metronomes)
+					}
+				},
 				Command::Resource(command) => {
-					self.playables.run_command(command);
+					self.playables.run_command(command, &mut self.diagnostics);
 				}
 				Command::Metronome(command) => {
-					self.metronomes.run_command(command);
+					self.metronomes.run_command(command, &mut self.diagnostics);
 				}
 				Command::Instance(command) => {
-					self.instances
-						.run_command(command, &mut self.playables, &self.groups);
+					self.instances.run_command(
+						command,
+						&mut self.playables,
+						&self.groups,
+						&mut self.diagnostics,
+					);
 				}
 				Command::Sequence(command) => {
-					self.sequences.run_command(command, &self.groups);
+					self.sequences
+						.run_command(command, &self.groups, &mut self.diagnostics);
 				}
 				Command::Mixer(command) => {
-					self.mixer.run_command(command);
+					self.mixer.run_command(command, &mut self.diagnostics);
 				}
 				Command::Parameter(command) => {
-					self.parameters.run_command(command);
+					self.parameters.run_command(command, &mut self.diagnostics);
 				}
 				Command::Group(command) => {
-					self.groups.run_command(command);
+					self.groups.run_command(command, &mut self.diagnostics);
 				}
 				Command::Stream(command) => {
-					self.streams.run_command(command);
+					self.streams.run_command(command, &mut self.diagnostics);
 				}
+				Command::SetPaused(paused) => self.paused = paused,
+				Command::Suspend(fade_tween) => self.suspension.suspend(fade_tween),
+				Command::Resume(fade_tween) => self.suspension.resume(fade_tween),
 			}
 		}
 	}
 
 	fn update_sequences(&mut self) {
-		for command in self
-			.sequences
-			.update(self.dt, &self.playables, &self.metronomes)
-		{
-			self.command_queue.try_push(command.into()).ok();
+		for command in self.sequences.update(
+			self.dt,
+			self.paused,
+			&self.playables,
+			&self.metronomes,
+			self.instances.slots(),
+			&mut self.diagnostics,
+		) {
+			// the sequence command queue is emptied every frame, so this
+			// should only fail if the queues were created with different
+			// capacities, but the commands shouldn't disappear silently
+			if self.sequence_command_queue.try_push(command).is_err() {
+				self.stats.record_dropped_command();
+				self.diagnostics.report(Diagnostic::CommandDropped);
+			}
 		}
 	}
 
 	/// Produces a frame of audio.
 	pub fn process(&mut self) -> Frame {
-		self.process_commands();
-		self.parameters.update(self.dt);
-		self.playables.update(self.dt);
-		self.metronomes.update(self.dt, &self.parameters);
-		self.update_sequences();
-		self.streams.process(self.dt, &mut self.mixer);
-		self.instances
-			.process(self.dt, &self.playables, &mut self.mixer, &self.parameters);
-		self.mixer.process(self.dt, &self.parameters)
+		let mut frames = [Frame::from_mono(0.0)];
+		self.process_block(&mut frames);
+		frames[0]
+	}
+
+	/// Fills a buffer with frames of audio.
+	///
+	/// If the backend is using a channel layout other than stereo,
+	/// the output is mixed down to stereo.
+	///
+	/// Commands, parameters, sequences, and instances are updated
+	/// every frame, but audio streams and the mixer (including
+	/// effects) process blocks of up to 128 frames at a time.
+	pub fn process_block(&mut self, frames: &mut [Frame]) {
+		let start_time = Instant::now();
+		random::swap_rng(&mut self.rng);
+		let channel_layout = self.mixer.channel_layout();
+		let channels = channel_layout.num_channels();
+		for block in frames.chunks_mut(MAX_BLOCK_SIZE) {
+			self.render_block(block.len());
+			for (frame, samples) in block.iter_mut().zip(self.output.chunks_exact(channels)) {
+				*frame = channel_layout.downmix_to_stereo(samples);
+			}
+		}
+		random::swap_rng(&mut self.rng);
+		self.record_stats(start_time, frames.len());
+	}
+
+	/// Fills a buffer of interleaved samples with audio for an
+	/// output with the given number of channels.
+	///
+	/// The number of frames produced is the length of the buffer
+	/// divided by the number of channels. Any samples left over at
+	/// the end of the buffer are set to `0.0`, and if `channels` is
+	/// `0`, nothing is produced.
+	///
+	/// The audio is up or downmixed from the backend's
+	/// [`ChannelLayout`] if the output has a different number of
+	/// channels. The output is assumed to play audio at the sample
+	/// rate the backend was created with; if that changes, call
+	/// [`set_sample_rate`](Self::set_sample_rate).
+	///
+	/// This doesn't allocate memory or wait on locks, so it's
+	/// safe to call from a real-time audio callback.
+	pub fn process_into(&mut self, out: &mut [f32], channels: usize) {
+		if channels == 0 {
+			return;
+		}
+		let num_frames = out.len() / channels;
+		let (out, remainder) = out.split_at_mut(num_frames * channels);
+		remainder.iter_mut().for_each(|sample| *sample = 0.0);
+		let start_time = Instant::now();
+		random::swap_rng(&mut self.rng);
+		let channel_layout = self.mixer.channel_layout();
+		let layout_channels = channel_layout.num_channels();
+		for out in out.chunks_mut(MAX_BLOCK_SIZE * channels) {
+			self.render_block(out.len() / channels);
+			for (out, samples) in out
+				.chunks_exact_mut(channels)
+				.zip(self.output.chunks_exact(layout_channels))
+			{
+				channel_layout.remap(samples, out);
+			}
+		}
+		random::swap_rng(&mut self.rng);
+		self.record_stats(start_time, num_frames);
+	}
+
+	/// Reports how long it took to produce some audio and how many
+	/// resources are in use to the audio manager.
+	fn record_stats(&self, start_time: Instant, num_frames: usize) {
+		if num_frames > 0 {
+			let available_time = num_frames as f64 * self.dt;
+			let elapsed_time = (Instant::now() - start_time).as_secs_f64();
+			self.stats
+				.record_callback((elapsed_time / available_time) as f32);
+		}
+		self.stats.set_resource_counts(
+			self.instances.len(),
+			self.sequences.len(),
+			self.streams.len(),
+		);
+		self.stats.set_suspended(self.suspension.is_suspended());
+	}
+
+	/// Returns the channel layout the backend produces audio in.
+	pub fn channel_layout(&self) -> ChannelLayout {
+		self.mixer.channel_layout()
+	}
+
+	/// Renders a block of at most [`MAX_BLOCK_SIZE`] frames to the
+	/// output buffer.
+	///
+	/// Commands, parameters, sequences, and instances are updated
+	/// for each frame, and the instances write their output into
+	/// the mixer's input blocks. Streams and the mixer then process
+	/// the whole block, so track volumes, send volumes, and effect
+	/// mixes only see the parameter values at the end of the block.
+	fn render_block(&mut self, num_frames: usize) {
+		let channels = self.mixer.channel_layout().num_channels();
+		if self.suspension.is_suspended() {
+			// keep receiving commands so the backend can be resumed,
+			// but don't advance the clock or produce any audio
+			self.process_commands();
+			if self.suspension.is_suspended() {
+				self.output[..num_frames * channels]
+					.iter_mut()
+					.for_each(|sample| *sample = 0.0);
+				return;
+			}
+		}
+		let mut fading = false;
+		for frame_index in 0..num_frames {
+			self.process_commands();
+			let suspension_volume = self.suspension.update(self.dt);
+			self.suspension_volumes[frame_index] = suspension_volume;
+			fading |= suspension_volume != 1.0;
+			if !self.paused {
+				self.parameters.update(self.dt);
+				self.metronomes.update(self.dt, &self.parameters);
+			}
+			self.playables
+				.update(self.dt, self.paused, &self.groups, &self.mixer);
+			self.update_sequences();
+			self.instances.process(
+				frame_index,
+				self.dt,
+				self.paused,
+				&self.playables,
+				&mut self.mixer,
+				&self.parameters,
+			);
+			self.clock_time += 1;
+		}
+		self.stats.set_clock_time(self.clock_time);
+		self.streams.process(
+			self.dt,
+			num_frames,
+			self.paused,
+			&mut self.mixer,
+			&mut self.diagnostics,
+			&mut self.removed_stream_id_producer,
+		);
+		self.mixer.process(
+			self.dt,
+			&self.parameters,
+			&mut self.output[..num_frames * channels],
+		);
+		self.mixer.report_panicked_effects(&mut self.diagnostics);
+		if fading {
+			for (samples, volume) in self
+				.output
+				.chunks_exact_mut(channels)
+				.zip(&self.suspension_volumes[..num_frames])
+			{
+				samples.iter_mut().for_each(|sample| *sample *= volume);
+			}
+		}
 	}
 }
@@ -1,7 +1,8 @@
 use crate::{
 	command::{Command, InstanceCommand, MetronomeCommand, ParameterCommand, SequenceCommand},
 	group::groups::Groups,
-	instance::Instance,
+	instance::{Instance, InstanceSlots},
+	manager::diagnostics::{Diagnostic, DiagnosticProducer, ResourceId},
 	metronome::Metronomes,
 	playable::Playables,
 	sequence::{SequenceInstance, SequenceInstanceId, SequenceOutputCommand},
@@ -27,43 +28,88 @@ impl Sequences {
 		}
 	}
 
+	/// Moves all of the sequence instances into `other`, which has a larger
+	/// capacity, and swaps the two so that the old (now empty)
+	/// storage can be freed on another thread.
+	pub fn grow_into(&mut self, other: &mut Self) {
+		self.sequence_instances
+			.move_into(&mut other.sequence_instances);
+		std::mem::swap(self, other);
+	}
+
+	pub fn len(&self) -> usize {
+		self.sequence_instances.len()
+	}
+
 	fn start_sequence_instance(
 		&mut self,
 		id: SequenceInstanceId,
 		mut instance: Owned<SequenceInstance>,
+		groups: &Groups,
+		diagnostics: &mut DiagnosticProducer,
 	) {
+		instance.set_in_unpausable_group(groups);
 		instance.start();
-		self.sequence_instances.try_insert(id, instance).ok();
+		if self.sequence_instances.try_insert(id, instance).is_err() {
+			diagnostics.report(Diagnostic::ResourceLimitReached(
+				ResourceId::SequenceInstance(id),
+			));
+		}
 	}
 
-	pub fn run_command(&mut self, command: SequenceCommand, groups: &Groups) {
+	pub fn run_command(
+		&mut self,
+		command: SequenceCommand,
+		groups: &Groups,
+		diagnostics: &mut DiagnosticProducer,
+	) {
 		match command {
 			SequenceCommand::StartSequenceInstance(id, instance) => {
-				self.start_sequence_instance(id, instance);
+				self.start_sequence_instance(id, instance, groups, diagnostics);
 			}
 			SequenceCommand::MuteSequenceInstance(id) => {
 				if let Some(instance) = self.sequence_instances.get_mut(&id) {
 					instance.mute();
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(ResourceId::SequenceInstance(
+						id,
+					)));
 				}
 			}
 			SequenceCommand::UnmuteSequenceInstance(id) => {
 				if let Some(instance) = self.sequence_instances.get_mut(&id) {
 					instance.unmute();
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(ResourceId::SequenceInstance(
+						id,
+					)));
 				}
 			}
 			SequenceCommand::PauseSequenceInstance(id) => {
 				if let Some(instance) = self.sequence_instances.get_mut(&id) {
 					instance.pause();
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(ResourceId::SequenceInstance(
+						id,
+					)));
 				}
 			}
 			SequenceCommand::ResumeSequenceInstance(id) => {
 				if let Some(instance) = self.sequence_instances.get_mut(&id) {
 					instance.resume();
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(ResourceId::SequenceInstance(
+						id,
+					)));
 				}
 			}
 			SequenceCommand::StopSequenceInstance(id) => {
 				if let Some(instance) = self.sequence_instances.get_mut(&id) {
 					instance.stop();
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(ResourceId::SequenceInstance(
+						id,
+					)));
 				}
 			}
 			SequenceCommand::PauseGroup(id) => {
@@ -93,168 +139,132 @@ impl Sequences {
 	pub fn update(
 		&mut self,
 		dt: f64,
+		paused: bool,
 		playables: &Playables,
 		metronomes: &Metronomes,
+		instance_slots: &InstanceSlots,
+		diagnostics: &mut DiagnosticProducer,
 	) -> Drain<Command> {
 		// update sequences and process their commands
 		for (id, sequence_instance) in &mut self.sequence_instances {
-			sequence_instance.update(dt, metronomes, &mut self.sequence_output_command_queue);
+			// while the audio manager is paused, only unpausable
+			// sequences keep running
+			if !paused || sequence_instance.in_unpausable_group() {
+				sequence_instance.update(
+					dt,
+					metronomes,
+					&mut self.sequence_output_command_queue,
+					instance_slots,
+				);
+			}
 			// convert sequence commands to commands that can be consumed
 			// by the backend
 			for command in self.sequence_output_command_queue.drain(..) {
-				match command {
+				let command = match command {
 					SequenceOutputCommand::PlaySound(playable_id, instance_id, settings) => {
-						if let Some(playable) = playables.playable(playable_id) {
-							self.output_command_queue
-								.try_push(Command::Instance(InstanceCommand::Play(
-									instance_id,
-									Instance::new(
-										playable_id,
+						// the sequence claimed a slot for the instance, which
+						// is freed if the instance isn't created
+						let slot = instance_slots.reclaim(instance_id);
+						match playables.playable(playable_id) {
+							// sequences that play streamed sounds can't be started,
+							// but the sound could have been replaced with a streamed
+							// sound with the same ID since the sequence started
+							Some(playable) if playable.is_streamed() => {
+								diagnostics.report(Diagnostic::StreamedSoundPlayedBySequence {
+									playable: playable_id,
+									sequence: *id,
+								});
+								continue;
+							}
+							Some(playable) => Command::Instance(InstanceCommand::Play(
+								instance_id,
+								Instance::new(
+									playable_id,
+									playable.duration(),
+									Some(*id),
+									settings.into_internal(
 										playable.duration(),
-										Some(*id),
-										settings.into_internal(
-											playable.duration(),
-											playable.default_loop_start(),
-											playable.default_track(),
-										),
+										playable.default_loop_start(),
+										playable.default_loop_end(),
+										playable.default_track(),
 									),
-								)))
-								.ok();
+									None,
+									slot,
+								),
+							)),
+							None => {
+								diagnostics
+									.report(Diagnostic::ResourceNotFound(playable_id.into()));
+								continue;
+							}
 						}
 					}
 					SequenceOutputCommand::SetInstanceVolume(id, volume) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::SetInstanceVolume(
-								id, volume,
-							)))
-							.ok();
+						Command::Instance(InstanceCommand::SetInstanceVolume(id, volume))
 					}
 					SequenceOutputCommand::SetInstancePlaybackRate(id, playback_rate) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::SetInstancePlaybackRate(
-								id,
-								playback_rate,
-							)))
-							.ok();
+						Command::Instance(InstanceCommand::SetInstancePlaybackRate(
+							id,
+							playback_rate,
+						))
 					}
 					SequenceOutputCommand::SetInstancePanning(id, panning) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::SetInstancePanning(
-								id, panning,
-							)))
-							.ok();
+						Command::Instance(InstanceCommand::SetInstancePanning(id, panning))
 					}
 					SequenceOutputCommand::PauseInstance(id, settings) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::PauseInstance(
-								id, settings,
-							)))
-							.ok();
+						Command::Instance(InstanceCommand::PauseInstance(id, settings))
 					}
 					SequenceOutputCommand::ResumeInstance(id, settings) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::ResumeInstance(
-								id, settings,
-							)))
-							.ok();
+						Command::Instance(InstanceCommand::ResumeInstance(id, settings))
 					}
 					SequenceOutputCommand::StopInstance(id, settings) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::StopInstance(
-								id, settings,
-							)))
-							.ok();
+						Command::Instance(InstanceCommand::StopInstance(id, settings))
 					}
 					SequenceOutputCommand::PauseInstancesOf(id, settings) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::PauseInstancesOf(
-								id, settings,
-							)))
-							.ok();
+						Command::Instance(InstanceCommand::PauseInstancesOf(id, settings))
 					}
 					SequenceOutputCommand::ResumeInstancesOf(id, settings) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::ResumeInstancesOf(
-								id, settings,
-							)))
-							.ok();
+						Command::Instance(InstanceCommand::ResumeInstancesOf(id, settings))
 					}
 					SequenceOutputCommand::StopInstancesOf(id, settings) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::StopInstancesOf(
-								id, settings,
-							)))
-							.ok();
+						Command::Instance(InstanceCommand::StopInstancesOf(id, settings))
 					}
 					SequenceOutputCommand::PauseSequence(id) => {
-						self.output_command_queue
-							.try_push(Command::Sequence(SequenceCommand::PauseSequenceInstance(
-								id,
-							)))
-							.ok();
+						Command::Sequence(SequenceCommand::PauseSequenceInstance(id))
 					}
 					SequenceOutputCommand::ResumeSequence(id) => {
-						self.output_command_queue
-							.try_push(Command::Sequence(SequenceCommand::ResumeSequenceInstance(
-								id,
-							)))
-							.ok();
+						Command::Sequence(SequenceCommand::ResumeSequenceInstance(id))
 					}
 					SequenceOutputCommand::StopSequence(id) => {
-						self.output_command_queue
-							.try_push(Command::Sequence(SequenceCommand::StopSequenceInstance(id)))
-							.ok();
+						Command::Sequence(SequenceCommand::StopSequenceInstance(id))
 					}
 					SequenceOutputCommand::PauseInstancesOfSequence(id, settings) => {
-						self.output_command_queue
-							.try_push(Command::Instance(
-								InstanceCommand::PauseInstancesOfSequence(id, settings),
-							))
-							.ok();
+						Command::Instance(InstanceCommand::PauseInstancesOfSequence(id, settings))
 					}
 					SequenceOutputCommand::ResumeInstancesOfSequence(id, settings) => {
-						self.output_command_queue
-							.try_push(Command::Instance(
-								InstanceCommand::ResumeInstancesOfSequence(id, settings),
-							))
-							.ok();
+						Command::Instance(InstanceCommand::ResumeInstancesOfSequence(id, settings))
 					}
 					SequenceOutputCommand::StopInstancesOfSequence(id, settings) => {
-						self.output_command_queue
-							.try_push(Command::Instance(InstanceCommand::StopInstancesOfSequence(
-								id, settings,
-							)))
-							.ok();
+						Command::Instance(InstanceCommand::StopInstancesOfSequence(id, settings))
 					}
 					SequenceOutputCommand::SetMetronomeTempo(id, tempo) => {
-						self.output_command_queue
-							.try_push(Command::Metronome(MetronomeCommand::SetMetronomeTempo(
-								id, tempo,
-							)))
-							.ok();
+						Command::Metronome(MetronomeCommand::SetMetronomeTempo(id, tempo))
 					}
 					SequenceOutputCommand::StartMetronome(id) => {
-						self.output_command_queue
-							.try_push(Command::Metronome(MetronomeCommand::StartMetronome(id)))
-							.ok();
+						Command::Metronome(MetronomeCommand::StartMetronome(id))
 					}
 					SequenceOutputCommand::PauseMetronome(id) => {
-						self.output_command_queue
-							.try_push(Command::Metronome(MetronomeCommand::PauseMetronome(id)))
-							.ok();
+						Command::Metronome(MetronomeCommand::PauseMetronome(id))
 					}
 					SequenceOutputCommand::StopMetronome(id) => {
-						self.output_command_queue
-							.try_push(Command::Metronome(MetronomeCommand::StopMetronome(id)))
-							.ok();
+						Command::Metronome(MetronomeCommand::StopMetronome(id))
 					}
 					SequenceOutputCommand::SetParameter(id, target, tween) => {
-						self.output_command_queue
-							.try_push(Command::Parameter(ParameterCommand::SetParameter(
-								id, target, tween,
-							)))
-							.ok();
+						Command::Parameter(ParameterCommand::SetParameter(id, target, tween))
 					}
+				};
+				if self.output_command_queue.try_push(command).is_err() {
+					diagnostics.report(Diagnostic::SequenceCommandDropped(*id));
 				}
 			}
 			if sequence_instance.finished() {
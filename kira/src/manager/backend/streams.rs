@@ -1,29 +1,64 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
 use crate::{
 	audio_stream::{AudioStream, AudioStreamId},
 	command::StreamCommand,
+	manager::diagnostics::{Diagnostic, DiagnosticProducer, ResourceId},
 	manager::TrackIndex,
-	static_container::index_map::StaticIndexMap,
+	mixer::MAX_BLOCK_SIZE,
+	static_container::{slot_map::StaticSlotMap, vec::StaticVec},
+	Frame,
 };
 
 use super::mixer::Mixer;
 
 use basedrop::Owned;
+use ringbuf::Producer;
 
 pub(crate) struct Streams {
-	streams: StaticIndexMap<AudioStreamId, (TrackIndex, Owned<Box<dyn AudioStream>>)>,
+	streams: StaticSlotMap<AudioStreamId, (TrackIndex, Owned<Box<dyn AudioStream>>)>,
+	buffer: Vec<Frame>,
+	// streams that panicked and whose IDs haven't been sent
+	// back to the audio manager yet
+	panicked_stream_ids: StaticVec<AudioStreamId>,
 }
 
 impl Streams {
 	pub fn new(capacity: usize) -> Self {
 		Self {
-			streams: StaticIndexMap::new(capacity),
+			streams: StaticSlotMap::new(capacity),
+			buffer: vec![Frame::from_mono(0.0); MAX_BLOCK_SIZE],
+			panicked_stream_ids: StaticVec::new(capacity),
+		}
+	}
+
+	/// Moves all of the streams into `other`, which has a larger
+	/// capacity, and swaps the two so that the old (now empty)
+	/// storage can be freed on another thread.
+	pub fn grow_into(&mut self, other: &mut Self) {
+		self.streams.move_into(&mut other.streams);
+		for id in self.panicked_stream_ids.drain(..) {
+			other.panicked_stream_ids.try_push(id).ok();
 		}
+		std::mem::swap(self, other);
+	}
+
+	pub fn len(&self) -> usize {
+		self.streams.len()
 	}
 
-	pub fn run_command(&mut self, command: StreamCommand) {
+	pub fn run_command(&mut self, command: StreamCommand, diagnostics: &mut DiagnosticProducer) {
 		match command {
 			StreamCommand::AddStream(stream_id, track_id, stream) => {
-				self.streams.try_insert(stream_id, (track_id, stream)).ok();
+				if self
+					.streams
+					.try_insert(stream_id, (track_id, stream))
+					.is_err()
+				{
+					diagnostics.report(Diagnostic::ResourceLimitReached(ResourceId::Stream(
+						stream_id,
+					)));
+				}
 			}
 			StreamCommand::RemoveStream(stream_id) => {
 				self.streams.remove(&stream_id);
@@ -31,9 +66,49 @@ impl Streams {
 		}
 	}
 
-	pub fn process(&mut self, dt: f64, mixer: &mut Mixer) {
-		for (track, stream) in self.streams.values_mut() {
-			mixer.add_input(*track, stream.next(dt));
+	/// Gets the next `num_frames` frames from each stream and
+	/// sends them to the mixer.
+	///
+	/// While the audio manager is paused, only streams on
+	/// unpausable tracks are processed.
+	///
+	/// Streams that panic are removed, and their IDs are sent
+	/// to `removed_stream_ids` so the audio manager can reuse them.
+	pub fn process(
+		&mut self,
+		dt: f64,
+		num_frames: usize,
+		paused: bool,
+		mixer: &mut Mixer,
+		diagnostics: &mut DiagnosticProducer,
+		removed_stream_ids: &mut Producer<AudioStreamId>,
+	) {
+		let buffer = &mut self.buffer[..num_frames];
+		for (id, (track, stream)) in &mut self.streams {
+			if paused && !mixer.is_track_unpausable(*track) {
+				continue;
+			}
+			let result = catch_unwind(AssertUnwindSafe(|| {
+				stream.next_block(dt, &mut *buffer);
+			}));
+			if result.is_err() {
+				self.panicked_stream_ids.try_push(*id).ok();
+				continue;
+			}
+			mixer.add_input_block(*track, buffer);
+		}
+		for id in self.panicked_stream_ids.iter() {
+			if self.streams.remove(id).is_some() {
+				diagnostics.report(Diagnostic::StreamPanicked(*id));
+			}
+		}
+		// if the queue is full, the remaining IDs are sent on
+		// a later block
+		while let Some(id) = self.panicked_stream_ids.last() {
+			if removed_stream_ids.push(*id).is_err() {
+				break;
+			}
+			self.panicked_stream_ids.pop();
 		}
 	}
 }
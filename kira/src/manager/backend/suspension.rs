@@ -0,0 +1,57 @@
+use crate::parameter::{tween::Tween, Parameter};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SuspensionState {
+	Running,
+	Suspending,
+	Suspended,
+}
+
+/// Keeps track of whether the backend is suspended and fades
+/// the output in and out when suspending and resuming.
+pub(crate) struct Suspension {
+	state: SuspensionState,
+	fade_volume: Parameter,
+}
+
+impl Suspension {
+	pub fn new() -> Self {
+		Self {
+			state: SuspensionState::Running,
+			fade_volume: Parameter::new(1.0),
+		}
+	}
+
+	/// Returns `true` if the backend has finished fading out
+	/// and should stop producing audio.
+	pub fn is_suspended(&self) -> bool {
+		self.state == SuspensionState::Suspended
+	}
+
+	pub fn suspend(&mut self, fade_tween: Option<Tween>) {
+		if self.state != SuspensionState::Running {
+			return;
+		}
+		self.state = if fade_tween.is_some() {
+			SuspensionState::Suspending
+		} else {
+			SuspensionState::Suspended
+		};
+		self.fade_volume.set(0.0, fade_tween);
+	}
+
+	pub fn resume(&mut self, fade_tween: Option<Tween>) {
+		self.state = SuspensionState::Running;
+		self.fade_volume.set(1.0, fade_tween);
+	}
+
+	/// Updates the fade and returns the volume the output
+	/// should be multiplied by.
+	pub fn update(&mut self, dt: f64) -> f32 {
+		let finished_fading = self.fade_volume.update(dt);
+		if finished_fading && self.state == SuspensionState::Suspending {
+			self.state = SuspensionState::Suspended;
+		}
+		self.fade_volume.value() as f32
+	}
+}
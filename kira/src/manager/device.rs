@@ -0,0 +1,223 @@
+//! Information about the audio output devices available on the system.
+
+use cpal::{
+	traits::{DeviceTrait, HostTrait},
+	BufferSize, Device, SampleRate, StreamConfig, SupportedBufferSize, SupportedStreamConfigRange,
+};
+
+use super::error::SetupError;
+
+/// A range of stream configurations supported by an output device.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SupportedOutputConfig {
+	/// The number of output channels.
+	pub channels: u16,
+	/// The lowest supported sample rate.
+	pub min_sample_rate: u32,
+	/// The highest supported sample rate.
+	pub max_sample_rate: u32,
+	/// The smallest and largest supported buffer sizes (in frames),
+	/// if they are known.
+	pub buffer_size_range: Option<(u32, u32)>,
+}
+
+impl SupportedOutputConfig {
+	/// Returns whether the given sample rate is within the
+	/// supported range.
+	pub fn supports_sample_rate(&self, sample_rate: u32) -> bool {
+		sample_rate >= self.min_sample_rate && sample_rate <= self.max_sample_rate
+	}
+}
+
+/// Something that happened to the audio output device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceEvent {
+	/// The audio output device stopped working, and audio
+	/// output was moved to another device.
+	DeviceChanged {
+		/// The name of the device audio is now being sent to.
+		name: String,
+		/// The sample rate of the new device.
+		sample_rate: u32,
+	},
+	/// The audio output device stopped working, and no other
+	/// device is available. The audio manager will keep trying
+	/// to find a device to use.
+	DeviceLost,
+	/// The output stopped producing audio because of an error,
+	/// for example because a `FileOutput` couldn't write to its
+	/// file. The output won't recover from this.
+	OutputFailed(String),
+}
+
+/// Information about an audio output device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputDeviceInfo {
+	/// The name of the device.
+	///
+	/// This can be used as the
+	/// [`output_device`](super::AudioManagerSettings::output_device)
+	/// setting to select this device.
+	pub name: String,
+	/// Whether this is the system's default output device.
+	pub is_default: bool,
+	/// The stream configurations the device supports.
+	pub supported_configs: Vec<SupportedOutputConfig>,
+}
+
+/// Lists the output devices on the default host.
+///
+/// Devices that can't report their name or their supported
+/// configurations (for example, because they're busy or were
+/// just unplugged) are left out.
+pub(super) fn output_devices() -> Result<Vec<OutputDeviceInfo>, cpal::DevicesError> {
+	let host = cpal::default_host();
+	let default_device_name = host
+		.default_output_device()
+		.and_then(|device| device.name().ok());
+	let mut devices = vec![];
+	for device in host.output_devices()? {
+		let name = match device.name() {
+			Ok(name) => name,
+			Err(_) => continue,
+		};
+		let supported_configs = match device.supported_output_configs() {
+			Ok(configs) => configs
+				.map(|config| supported_output_config(&config))
+				.collect(),
+			Err(_) => continue,
+		};
+		devices.push(OutputDeviceInfo {
+			is_default: default_device_name.as_ref() == Some(&name),
+			name,
+			supported_configs,
+		});
+	}
+	Ok(devices)
+}
+
+/// Gets the output device with the given name, or the
+/// default output device if no name is given.
+pub(super) fn find_output_device(name: Option<&str>) -> Result<Device, SetupError> {
+	let host = cpal::default_host();
+	match name {
+		Some(name) => host
+			.output_devices()?
+			.find(|device| device.name().ok().as_deref() == Some(name))
+			.ok_or_else(|| SetupError::NoOutputDeviceWithName(name.to_string())),
+		None => host
+			.default_output_device()
+			.ok_or(SetupError::NoDefaultOutputDevice),
+	}
+}
+
+/// The number of channels and sample rate to open an
+/// output stream with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(super) struct ChosenOutputConfig {
+	pub channels: u16,
+	pub sample_rate: u32,
+}
+
+/// Chooses the number of channels and sample rate to use for an
+/// output device, given the device's default configuration and
+/// the configurations it supports.
+///
+/// The default configuration is used unless a sample rate is
+/// requested or the requested number of channels is different.
+/// If the device doesn't support the requested number of channels
+/// at the requested sample rate, its default number of channels is
+/// used instead. If no sample rate was requested, the default sample
+/// rate is preferred, but the closest supported sample rate is used
+/// if the requested number of channels doesn't support it.
+pub(super) fn choose_output_config(
+	default_config: ChosenOutputConfig,
+	supported_configs: &[SupportedOutputConfig],
+	sample_rate: Option<u32>,
+	channels: u16,
+) -> Result<ChosenOutputConfig, SetupError> {
+	if sample_rate.is_none() && channels == default_config.channels {
+		return Ok(default_config);
+	}
+	let requested_sample_rate = sample_rate.unwrap_or(default_config.sample_rate);
+	let supports = |channels: u16| {
+		supported_configs.iter().any(|config| {
+			config.channels == channels && config.supports_sample_rate(requested_sample_rate)
+		})
+	};
+	if supports(channels) {
+		return Ok(ChosenOutputConfig {
+			channels,
+			sample_rate: requested_sample_rate,
+		});
+	}
+	if sample_rate.is_none() {
+		if let Some(config) = supported_configs
+			.iter()
+			.find(|config| config.channels == channels)
+		{
+			return Ok(ChosenOutputConfig {
+				channels,
+				sample_rate: requested_sample_rate
+					.max(config.min_sample_rate)
+					.min(config.max_sample_rate),
+			});
+		}
+		return Ok(default_config);
+	}
+	if supports(default_config.channels) {
+		return Ok(ChosenOutputConfig {
+			channels: default_config.channels,
+			sample_rate: requested_sample_rate,
+		});
+	}
+	Err(SetupError::UnsupportedSampleRate(requested_sample_rate))
+}
+
+fn supported_output_config(config: &SupportedStreamConfigRange) -> SupportedOutputConfig {
+	SupportedOutputConfig {
+		channels: config.channels(),
+		min_sample_rate: config.min_sample_rate().0,
+		max_sample_rate: config.max_sample_rate().0,
+		buffer_size_range: match config.buffer_size() {
+			SupportedBufferSize::Range { min, max } => Some((*min, *max)),
+			SupportedBufferSize::Unknown => None,
+		},
+	}
+}
+
+/// Gets the stream configuration to use for an output device,
+/// taking into account the requested sample rate, buffer size,
+/// and number of channels.
+///
+/// See [`choose_output_config`] for how the number of channels
+/// and sample rate are chosen.
+pub(super) fn stream_config(
+	device: &Device,
+	sample_rate: Option<u32>,
+	buffer_size: Option<u32>,
+	channels: u16,
+) -> Result<StreamConfig, SetupError> {
+	let default_config = device.default_output_config()?;
+	let supported_configs = device
+		.supported_output_configs()?
+		.map(|config| supported_output_config(&config))
+		.collect::<Vec<_>>();
+	let chosen_config = choose_output_config(
+		ChosenOutputConfig {
+			channels: default_config.channels(),
+			sample_rate: default_config.sample_rate().0,
+		},
+		&supported_configs,
+		sample_rate,
+		channels,
+	)?;
+	Ok(StreamConfig {
+		channels: chosen_config.channels,
+		sample_rate: SampleRate(chosen_config.sample_rate),
+		buffer_size: match buffer_size {
+			Some(buffer_size) => BufferSize::Fixed(buffer_size),
+			None => default_config.config().buffer_size,
+		},
+	})
+}
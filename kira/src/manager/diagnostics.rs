@@ -0,0 +1,126 @@
+//! Reports problems that happen on the audio thread.
+
+use ringbuf::Producer;
+
+use crate::{
+	arrangement::ArrangementId,
+	audio_stream::AudioStreamId,
+	group::GroupId,
+	instance::InstanceId,
+	metronome::MetronomeId,
+	mixer::{effect::EffectId, SendTrackId, SubTrackId},
+	parameter::ParameterId,
+	sequence::SequenceInstanceId,
+	sound::SoundId,
+	PlayableId,
+};
+
+/// Identifies a resource on the audio thread.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ResourceId {
+	/// A sound.
+	Sound(SoundId),
+	/// An arrangement.
+	Arrangement(ArrangementId),
+	/// An instance of a sound or arrangement.
+	Instance(InstanceId),
+	/// An instance of a sequence.
+	SequenceInstance(SequenceInstanceId),
+	/// A parameter.
+	Parameter(ParameterId),
+	/// A mixer sub-track.
+	SubTrack(SubTrackId),
+	/// A mixer send track.
+	SendTrack(SendTrackId),
+	/// An effect on a mixer track.
+	Effect(EffectId),
+	/// A group.
+	Group(GroupId),
+	/// A metronome.
+	Metronome(MetronomeId),
+	/// An audio stream.
+	Stream(AudioStreamId),
+}
+
+impl From<PlayableId> for ResourceId {
+	fn from(id: PlayableId) -> Self {
+		match id {
+			PlayableId::Sound(id) => Self::Sound(id),
+			PlayableId::Arrangement(id) => Self::Arrangement(id),
+		}
+	}
+}
+
+/// A problem that happened on the audio thread.
+///
+/// None of these problems cause errors, since the audio thread
+/// has no way of returning them, but they usually mean something
+/// isn't behaving the way you'd expect. Diagnostics can be
+/// retrieved using [`AudioManager::pop_diagnostic`](super::AudioManager::pop_diagnostic).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Diagnostic {
+	/// A command sent from the audio manager or a handle was
+	/// discarded because the audio thread received too many
+	/// commands at once.
+	CommandDropped,
+	/// A batch of commands sent with
+	/// [`AudioManager::batch`](super::AudioManager::batch) was
+	/// discarded because the audio thread didn't have room
+	/// for all of them.
+	BatchDropped,
+	/// A command produced by a sequence was discarded because
+	/// the audio thread received too many commands at once.
+	SequenceCommandDropped(SequenceInstanceId),
+	/// A resource couldn't be added because the maximum number
+	/// of resources of that kind already exist.
+	ResourceLimitReached(ResourceId),
+	/// An instance was stopped early to make room for a new
+	/// instance because the maximum number of instances were
+	/// already playing.
+	InstanceLimitReached {
+		/// The instance that was stopped.
+		stopped: InstanceId,
+	},
+	/// A command referred to a resource that doesn't exist
+	/// (or no longer exists) on the audio thread.
+	ResourceNotFound(ResourceId),
+	/// An effect panicked while processing audio. The effect
+	/// is bypassed from then on, but it stays on its track
+	/// until it's removed.
+	EffectPanicked(EffectId),
+	/// An audio stream panicked while producing audio and was
+	/// removed.
+	StreamPanicked(AudioStreamId),
+	/// A sequence tried to play a sound that's decoded from disk
+	/// while it plays. Streamed sounds can only be played using a
+	/// [`SoundHandle`](crate::sound::handle::SoundHandle).
+	///
+	/// Sequences that play streamed sounds can't be started, so
+	/// this only happens if a sound the sequence plays was removed
+	/// and replaced with a streamed sound with the same ID.
+	StreamedSoundPlayedBySequence {
+		/// The sound the sequence tried to play.
+		playable: PlayableId,
+		/// The sequence instance that tried to play the sound.
+		sequence: SequenceInstanceId,
+	},
+}
+
+/// Sends diagnostics from the audio thread to the audio manager.
+pub(crate) struct DiagnosticProducer {
+	producer: Producer<Diagnostic>,
+}
+
+impl DiagnosticProducer {
+	pub fn new(producer: Producer<Diagnostic>) -> Self {
+		Self { producer }
+	}
+
+	/// Reports a problem to the audio manager.
+	///
+	/// If the audio manager has too many diagnostics waiting to
+	/// be popped, the diagnostic is discarded.
+	pub fn report(&mut self, diagnostic: Diagnostic) {
+		self.producer.push(diagnostic).ok();
+	}
+}
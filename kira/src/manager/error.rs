@@ -1,6 +1,9 @@
 //! Things that can go wrong when using an [`AudioManager`](super::AudioManager).
 
-use cpal::{BuildStreamError, DefaultStreamConfigError, PlayStreamError};
+use cpal::{
+	BuildStreamError, DefaultStreamConfigError, DevicesError, PlayStreamError,
+	SupportedStreamConfigsError,
+};
 use thiserror::Error;
 
 use crate::{
@@ -11,7 +14,7 @@ use crate::{
 	metronome::MetronomeId,
 	mixer::{SendTrackId, SubTrackId, TrackIndex},
 	parameter::ParameterId,
-	sequence::error::SequenceError,
+	sequence::{error::SequenceError, SequenceInstanceId},
 	sound::{error::SoundFromFileError, SoundId},
 };
 
@@ -22,6 +25,22 @@ pub enum SetupError {
 	#[error("Cannot find the default audio output device")]
 	NoDefaultOutputDevice,
 
+	/// No audio output device with the requested name exists.
+	#[error("Cannot find an audio output device named {0}")]
+	NoOutputDeviceWithName(String),
+
+	/// The audio output device does not support the requested sample rate.
+	#[error("The audio output device does not support a sample rate of {0} Hz")]
+	UnsupportedSampleRate(u32),
+
+	/// An error occurred when listing the available audio output devices.
+	#[error("{0}")]
+	DevicesError(#[from] DevicesError),
+
+	/// An error occurred when getting the supported output configurations.
+	#[error("{0}")]
+	SupportedStreamConfigsError(#[from] SupportedStreamConfigsError),
+
 	/// An error occurred when getting the default output configuration.
 	#[error("{0}")]
 	DefaultStreamConfigError(#[from] DefaultStreamConfigError),
@@ -33,6 +52,33 @@ pub enum SetupError {
 	/// An error occured when starting the audio stream.
 	#[error("{0}")]
 	PlayStreamError(#[from] PlayStreamError),
+
+	/// An error occurred when creating a WAV file to write audio to.
+	#[cfg(feature = "wav")]
+	#[error("{0}")]
+	WavError(#[from] hound::Error),
+}
+
+/// Things that can go wrong when listing the available audio output devices.
+#[derive(Debug, Error)]
+pub enum ListOutputDevicesError {
+	/// An error occurred when listing the available audio output devices.
+	#[error("{0}")]
+	DevicesError(#[from] DevicesError),
+}
+
+/// Things that can go wrong when rendering audio to a file
+/// with an [`OfflineRenderer`](super::OfflineRenderer).
+#[cfg(feature = "wav")]
+#[derive(Debug, Error)]
+pub enum RenderToFileError {
+	/// An error occurred when interacting with the filesystem.
+	#[error("{0}")]
+	IoError(#[from] std::io::Error),
+
+	/// An error occurred when writing a wav file.
+	#[error("{0}")]
+	WavError(#[from] hound::Error),
 }
 
 /// Things that can go wrong when adding a sound to the audio thread.
@@ -50,6 +96,11 @@ pub enum AddSoundError {
 	#[error("The sound belongs to a group that does not exist")]
 	NoGroupWithId(GroupId),
 
+	/// The sound was given the largest possible ID, which can't
+	/// be used because there would be no IDs left for other sounds.
+	#[error("The sound's ID is too large to be used")]
+	IdTooLarge(SoundId),
+
 	/// A command could not be sent to the audio thread.
 	#[error("Could not send the command to the audio thread.")]
 	CommandProducerError(#[from] CommandError),
@@ -96,6 +147,17 @@ pub enum AddArrangementError {
 	#[error("The arrangement belongs to a group that does not exist")]
 	NoGroupWithId(GroupId),
 
+	/// The arrangement has a clip of a sound that's decoded from
+	/// disk while it plays, which arrangements can't use.
+	#[error("The arrangement has a clip of a streamed sound, which arrangements can't use")]
+	StreamedSound(SoundId),
+
+	/// The arrangement was given the largest possible ID, which
+	/// can't be used because there would be no IDs left for
+	/// other arrangements.
+	#[error("The arrangement's ID is too large to be used")]
+	IdTooLarge(ArrangementId),
+
 	/// A command could not be sent to the audio thread.
 	#[error("Could not send the command to the audio thread.")]
 	CommandProducerError(#[from] CommandError),
@@ -121,6 +183,10 @@ pub enum AddMetronomeError {
 	#[error("Cannot add a metronome because the max number of metronomes has been reached")]
 	MetronomeLimitReached,
 
+	/// The requested ID is already used by another metronome.
+	#[error("The requested ID is already used by another metronome")]
+	IdInUse(MetronomeId),
+
 	/// A command could not be sent to the audio thread.
 	#[error("Could not send the command to the audio thread.")]
 	CommandProducerError(#[from] CommandError),
@@ -146,6 +212,10 @@ pub enum AddGroupError {
 	#[error("Cannot add an group because the max number of groups has been reached")]
 	GroupLimitReached,
 
+	/// The requested ID is already used by another group.
+	#[error("The requested ID is already used by another group")]
+	IdInUse(GroupId),
+
 	/// The group belongs to a parent group that does not exist.
 	#[error("The group belongs to a parent group that does not exist")]
 	NoGroupWithId(GroupId),
@@ -175,6 +245,10 @@ pub enum AddParameterError {
 	#[error("Cannot add an parameter because the max number of parameters has been reached")]
 	ParameterLimitReached,
 
+	/// The requested ID is already used by another parameter.
+	#[error("The requested ID is already used by another parameter")]
+	IdInUse(ParameterId),
+
 	/// A command could not be sent to the audio thread.
 	#[error("Could not send the command to the audio thread.")]
 	CommandProducerError(#[from] CommandError),
@@ -200,6 +274,10 @@ pub enum AddSubTrackError {
 	#[error("Cannot add an track because the max number of tracks has been reached")]
 	TrackLimitReached,
 
+	/// The requested ID is already used by another sub-track.
+	#[error("The requested ID is already used by another sub-track")]
+	IdInUse(SubTrackId),
+
 	/// The track's parent track does not exist.
 	#[error("The track's parent track does not exist")]
 	NonexistentParentTrack(SubTrackId),
@@ -220,6 +298,10 @@ pub enum AddSendTrackError {
 	#[error("Cannot add an track because the max number of tracks has been reached")]
 	TrackLimitReached,
 
+	/// The requested ID is already used by another send track.
+	#[error("The requested ID is already used by another send track")]
+	IdInUse(SendTrackId),
+
 	/// A command could not be sent to the audio thread.
 	#[error("Could not send the command to the audio thread.")]
 	CommandProducerError(#[from] CommandError),
@@ -291,6 +373,17 @@ pub enum StartSequenceError {
 	#[error("The sequence belongs to a group that does not exist")]
 	NoGroupWithId(GroupId),
 
+	/// The sequence plays a sound that's decoded from disk while
+	/// it plays, which sequences can't play.
+	#[error("The sequence plays a streamed sound, which sequences can't play")]
+	StreamedSound(SoundId),
+
+	/// The sequence instance was given the largest possible ID,
+	/// which can't be used because there would be no IDs left
+	/// for other sequence instances.
+	#[error("The sequence instance's ID is too large to be used")]
+	IdTooLarge(SequenceInstanceId),
+
 	/// A command could not be sent to the audio thread.
 	#[error("Could not send the command to the audio thread.")]
 	CommandProducerError(#[from] CommandError),
@@ -1,57 +1,82 @@
 //! Bridges the main thread and the audio thread.
 
 mod active_ids;
-mod backend;
+pub(crate) mod backend;
+mod device;
+pub(crate) mod diagnostics;
 pub mod error;
+mod offline;
+pub mod output;
+mod resource_collector;
+mod stats;
+mod stream_manager;
 #[cfg(test)]
 mod tests;
 
 use std::{
+	collections::{HashMap, HashSet},
 	hash::Hash,
 	io::{stderr, Write},
-	sync::Arc,
+	sync::{Arc, Mutex, PoisonError},
 };
 
-use active_ids::ActiveIds;
-#[cfg(not(feature = "benchmarking"))]
-use backend::Backend;
-#[cfg(feature = "benchmarking")]
+pub use crate::command::producer::CommandQueuePolicy;
+use active_ids::{ActiveIds, ResourceKind};
 pub use backend::Backend;
-use basedrop::{Collector, Handle, Owned};
+use backend::{instances::Instances, mixer::Mixer, sequences::Sequences, streams::Streams};
+use basedrop::{Handle, Owned};
+pub use device::{DeviceEvent, OutputDeviceInfo, SupportedOutputConfig};
+pub use diagnostics::{Diagnostic, ResourceId};
+pub use offline::OfflineRenderer;
+pub use stats::AudioManagerStats;
+
 use error::{
 	AddArrangementError, AddGroupError, AddMetronomeError, AddParameterError, AddSendTrackError,
-	AddSoundError, AddStreamError, AddSubTrackError, RemoveArrangementError, RemoveGroupError,
-	RemoveMetronomeError, RemoveParameterError, RemoveSendTrackError, RemoveSoundError,
-	RemoveStreamError, RemoveSubTrackError, SetupError, StartSequenceError,
+	AddSoundError, AddStreamError, AddSubTrackError, ListOutputDevicesError,
+	RemoveArrangementError, RemoveGroupError, RemoveMetronomeError, RemoveParameterError,
+	RemoveSendTrackError, RemoveSoundError, RemoveStreamError, RemoveSubTrackError, SetupError,
+	StartSequenceError,
 };
-use ringbuf::{Consumer, Producer, RingBuffer};
+use ringbuf::{Consumer, RingBuffer};
 
 use crate::{
 	arrangement::{handle::ArrangementHandle, Arrangement, ArrangementId},
 	audio_stream::{AudioStream, AudioStreamId},
+	channel_layout::ChannelLayout,
 	command::{
-		producer::CommandProducer, Command, GroupCommand, MetronomeCommand, MixerCommand,
-		ParameterCommand, ResourceCommand, SequenceCommand, StreamCommand,
+		producer::{CommandError, CommandProducer},
+		Command, GroupCommand, GrowPoolCommand, MetronomeCommand, MixerCommand, ParameterCommand,
+		ResourceCommand, SequenceCommand, StreamCommand,
 	},
-	group::{handle::GroupHandle, Group, GroupId, GroupSet, GroupSettings},
-	metronome::{handle::MetronomeHandle, Metronome, MetronomeId, MetronomeSettings},
+	group::{groups::Groups, handle::GroupHandle, Group, GroupId, GroupSet, GroupSettings},
+	instance::InstanceSlots,
+	metronome::{handle::MetronomeHandle, Metronome, MetronomeId, MetronomeSettings, Metronomes},
 	mixer::{
 		MainTrackHandle, SendTrackHandle, SendTrackId, SendTrackSettings, SubTrackHandle,
 		SubTrackId, SubTrackSettings, Track, TrackIndex,
 	},
-	parameter::{handle::ParameterHandle, ParameterId, ParameterSettings},
+	parameter::{
+		handle::ParameterHandle, tween::Tween, ParameterId, ParameterSettings, Parameters,
+	},
+	playable::Playables,
 	sequence::{
 		handle::SequenceInstanceHandle, Sequence, SequenceInstanceId, SequenceInstanceSettings,
 	},
-	sound::{handle::SoundHandle, Sound, SoundId},
-};
-use cpal::{
-	traits::{DeviceTrait, HostTrait, StreamTrait},
-	Stream,
+	sound::{handle::SoundHandle, streaming::DecodingThread, Sound, SoundId},
+	static_container::slot_map::{SlotIndex, SlotKey},
 };
 use instant::Instant;
+use output::{CpalOutput, OutputBackend};
+use resource_collector::ResourceCollector;
+pub(crate) use stats::SharedStats;
 
 const DROP_CLEANUP_TIMEOUT_MILLIS: u64 = 1000;
+// how much longer than the fade-out to wait for the audio
+// thread to suspend before suspending the output anyway
+const SUSPEND_TIMEOUT_MILLIS: u64 = 1000;
+// the sample rate the backend is created with before an output
+// tells it what sample rate to use
+const DEFAULT_SAMPLE_RATE: u32 = 48000;
 
 /// Settings for an [`AudioManager`](crate::manager::AudioManager).
 #[derive(Debug, Clone)]
@@ -66,6 +91,22 @@ pub struct AudioManagerSettings {
 	/// Each action you take, like starting an instance or pausing a sequence,
 	/// queues up one command.
 	pub num_commands: usize,
+	/// What to do when a command is sent while the command queue
+	/// is full.
+	pub command_queue_policy: CommandQueuePolicy,
+	/// The number of commands sequences can queue up for the audio
+	/// thread at a time.
+	///
+	/// Commands produced by sequences, like playing a sound or setting
+	/// a parameter, are queued separately from the commands you send,
+	/// so busy sequences can't cause your own commands to be discarded
+	/// (and vice versa).
+	pub num_sequence_commands: usize,
+	/// The maximum number of commands that can be waiting to run
+	/// at a later time on the audio clock.
+	///
+	/// See [`AudioManager::clock_time`].
+	pub num_scheduled_commands: usize,
 	/// The maximum number of sounds that can be loaded at a time.
 	pub num_sounds: usize,
 	/// The maximum number of arrangements that can be loaded at a time.
@@ -73,6 +114,10 @@ pub struct AudioManagerSettings {
 	/// The maximum number of parameters that can exist at a time.
 	pub num_parameters: usize,
 	/// The maximum number of instances of sounds that can be playing at a time.
+	///
+	/// When this many instances are playing, starting a new instance
+	/// stops the instance in the slot after the most recently used
+	/// one, which is usually one of the oldest instances.
 	pub num_instances: usize,
 	/// The maximum number of sequences that can be running at a time.
 	pub num_sequences: usize,
@@ -86,12 +131,98 @@ pub struct AudioManagerSettings {
 	pub num_streams: usize,
 	/// The maximum number of metronomes that can be used at a time.
 	pub num_metronomes: usize,
+	/// The maximum number of [`Diagnostic`]s that can be waiting to be
+	/// retrieved with [`AudioManager::pop_diagnostic`].
+	///
+	/// Diagnostics reported while the queue is full are discarded.
+	pub num_diagnostics: usize,
+	/// The name of the audio output device to use.
+	///
+	/// If `None`, the system's default output device will be used.
+	/// The names of the available devices can be retrieved using
+	/// [`AudioManager::output_devices`].
+	pub output_device: Option<String>,
+	/// The sample rate to request from the audio output device.
+	///
+	/// If `None`, the device's default sample rate will be used.
+	pub sample_rate: Option<u32>,
+	/// Whether sounds should be resampled to the audio manager's
+	/// sample rate when they're added.
+	///
+	/// This uses [`Sound::resampled`], which gives higher quality
+	/// results than resampling while the sound plays, but makes
+	/// adding sounds slower. Sounds that were added before the
+	/// sample rate changed (for example, because the output device
+	/// was switched) keep their old sample rate. Use
+	/// [`AudioManager::outdated_resampled_sounds`] to find them.
+	pub resample_sounds: bool,
+	/// The number of frames the audio output device should
+	/// request at a time.
+	///
+	/// Smaller buffers reduce latency, but make audio glitches
+	/// more likely. If `None`, the device's default buffer size
+	/// will be used.
+	pub buffer_size: Option<u32>,
+	/// The arrangement of speakers to output audio to.
+	///
+	/// If the output device has a different number of channels,
+	/// the audio will be up or downmixed to fit.
+	pub channel_layout: ChannelLayout,
+	/// Whether resource pools should grow when they're full instead
+	/// of refusing to add more resources.
+	///
+	/// If `true`, the `num_*` settings for sounds, arrangements,
+	/// parameters, instances, sequences, tracks, groups, streams,
+	/// and metronomes are only the starting capacities. When a
+	/// pool fills up, the audio manager allocates a pool with
+	/// twice the capacity and sends it to the audio thread, and
+	/// the old pool is freed by
+	/// [`AudioManager::free_unused_resources`].
+	///
+	/// Instances and sequences are started from handles as well as the
+	/// audio manager, so their pools are grown when they're at least half
+	/// full and [`AudioManager::free_unused_resources`] is called. Make sure
+	/// to call it regularly, or older instances will still be stopped
+	/// to make room for new ones.
+	pub growable_pools: bool,
+	/// The seed for the audio thread's random number generator.
+	///
+	/// Every random decision made on the audio thread, like picking
+	/// a value for a [`Value::Random`](crate::Value::Random) or
+	/// a sound for a [`play_random`](crate::sequence::Sequence::play_random)
+	/// step, comes from this generator, so an audio manager with a
+	/// fixed seed behaves the same way every time it's given the
+	/// same commands. This is useful for replays and automated tests.
+	///
+	/// If `None`, the generator is seeded randomly.
+	pub seed: Option<u64>,
+	/// How often (in seconds) to free unused resources on a
+	/// background thread.
+	///
+	/// Sounds, instances, effects, and other resources that the
+	/// audio thread is done with aren't freed until the audio manager
+	/// collects them. If `None`, that only happens when
+	/// [`AudioManager::free_unused_resources`] is called, so it
+	/// should be called regularly (for example, once per frame).
+	/// Otherwise, a background thread collects them on this interval,
+	/// and [`AudioManager::free_unused_resources`] only needs to be
+	/// called to grow the instance and sequence pools.
+	///
+	/// [`AudioManagerStats::num_unfreed_resources`] can be used to
+	/// check whether resources are being freed often enough.
+	///
+	/// Background collection isn't available on wasm, so this
+	/// setting is ignored there.
+	pub resource_collection_interval: Option<f64>,
 }
 
 impl Default for AudioManagerSettings {
 	fn default() -> Self {
 		Self {
 			num_commands: 100,
+			command_queue_policy: CommandQueuePolicy::default(),
+			num_sequence_commands: 100,
+			num_scheduled_commands: 100,
 			num_sounds: 100,
 			num_arrangements: 100,
 			num_parameters: 100,
@@ -102,6 +233,15 @@ impl Default for AudioManagerSettings {
 			num_groups: 100,
 			num_streams: 10,
 			num_metronomes: 5,
+			num_diagnostics: 100,
+			output_device: None,
+			sample_rate: None,
+			resample_sounds: false,
+			buffer_size: None,
+			channel_layout: ChannelLayout::Stereo,
+			growable_pools: false,
+			seed: None,
+			resource_collection_interval: None,
 		}
 	}
 }
@@ -114,7 +254,7 @@ and the audio thread.
 */
 pub struct AudioManager {
 	command_producer: CommandProducer,
-	resource_collector: Option<Collector>,
+	resource_collector: Option<ResourceCollector>,
 	// this handle is specifically meant to be shared with
 	// TrackHandles. i want them to have a Weak<Handle>
 	// so that they don't prevent the collector from being
@@ -125,117 +265,86 @@ pub struct AudioManager {
 	// cleaned up.
 	resource_collector_handle: Option<Arc<Handle>>,
 	active_ids: ActiveIds,
-	sample_rate: u32,
-
-	#[cfg(not(target_arch = "wasm32"))]
-	quit_signal_producer: Producer<bool>,
-	// on wasm, holds the stream (as it has been created on the main thread)
-	// so it can live for as long as the audio manager
-	// in all cases, in benchmarking mode, we do not want an
-	// audio stream anyway so we leave it out
-	#[cfg(all(target_arch = "wasm32", not(feature = "benchmarking")))]
-	_stream: Stream,
+	// sounds that are decoded from disk while they play,
+	// which arrangements and sequences can't use
+	streamed_sound_ids: HashSet<SoundId>,
+	// decodes every instance of a streamed sound
+	decoding_thread: DecodingThread,
+	// sounds that were resampled when they were added, and the
+	// sample rate they were resampled to
+	resampled_sounds: HashMap<SoundId, u32>,
+	diagnostic_consumer: Consumer<Diagnostic>,
+	removed_stream_id_consumer: Consumer<AudioStreamId>,
+	stats: Arc<SharedStats>,
+	// shared with sound and arrangement handles, which claim
+	// slots for the instances they start
+	instance_slots: Arc<Mutex<InstanceSlots>>,
+	instance_capacity: usize,
+	sequence_capacity: usize,
+	stream_capacity: usize,
+	growable_pools: bool,
+	resample_sounds: bool,
+	paused: bool,
+	// when to give up waiting for the audio thread to fade out
+	// and suspend the output anyway, if a suspension is in progress
+	suspend_deadline: Option<Instant>,
+	// used to create larger pools for the audio thread
+	num_sequence_commands: usize,
+	channel_layout: ChannelLayout,
+	// owns the backend (and the audio stream, if there is one).
+	// when rendering offline, there is no output.
+	output: Option<Box<dyn OutputBackend>>,
 }
 
 impl AudioManager {
 	/// Creates a new audio manager and starts an audio thread.
-	#[cfg(not(target_arch = "wasm32"))]
+	///
+	/// Audio is sent to the output device chosen by the settings
+	/// using a [`CpalOutput`].
 	pub fn new(settings: AudioManagerSettings) -> Result<Self, SetupError> {
-		let active_ids = ActiveIds::new(&settings);
-		let (quit_signal_producer, mut quit_signal_consumer) = RingBuffer::new(1).split();
-		let (command_producer, command_consumer) = RingBuffer::new(settings.num_commands).split();
-		let resource_collector = Collector::new();
-		let resource_collector_handle = resource_collector.handle();
-
-		const WRAPPER_THREAD_SLEEP_DURATION: f64 = 1.0 / 60.0;
-
-		let (mut setup_result_producer, mut setup_result_consumer) = RingBuffer::new(1).split();
-		// set up a cpal stream on a new thread. we could do this on the main thread,
-		// but that causes issues with LÖVE.
-		std::thread::spawn(move || {
-			match Self::setup_stream(settings, command_consumer) {
-				Ok((_stream, sample_rate)) => {
-					setup_result_producer.push(Ok(sample_rate)).unwrap();
-					// wait for a quit message before ending the thread and dropping
-					// the stream
-					while quit_signal_consumer.pop().is_none() {
-						std::thread::sleep(std::time::Duration::from_secs_f64(
-							WRAPPER_THREAD_SLEEP_DURATION,
-						));
-					}
-				}
-				Err(error) => {
-					setup_result_producer.push(Err(error)).unwrap();
-				}
-			}
-		});
-		// wait for the audio thread to report back a result
-		let sample_rate = loop {
-			if let Some(result) = setup_result_consumer.pop() {
-				match result {
-					Ok(sample_rate) => break sample_rate,
-					Err(error) => return Err(error),
-				}
-			}
-		};
+		let output = CpalOutput::from_settings(&settings);
+		Self::with_output(settings, output)
+	}
 
-		Ok(Self {
-			quit_signal_producer,
-			command_producer: CommandProducer::new(command_producer),
-			active_ids,
-			sample_rate,
-			resource_collector: Some(resource_collector),
-			resource_collector_handle: Some(Arc::new(resource_collector_handle)),
-		})
+	/// Creates a new audio manager that sends audio to the given output.
+	///
+	/// The [`output_device`](AudioManagerSettings::output_device),
+	/// [`sample_rate`](AudioManagerSettings::sample_rate), and
+	/// [`buffer_size`](AudioManagerSettings::buffer_size) settings
+	/// are ignored, since the output decides where audio goes and
+	/// how it's produced.
+	pub fn with_output(
+		settings: AudioManagerSettings,
+		mut output: impl OutputBackend + 'static,
+	) -> Result<Self, SetupError> {
+		let (mut audio_manager, backend) = Self::new_with_backend(settings, DEFAULT_SAMPLE_RATE);
+		let sample_rate = output.start(backend)?;
+		audio_manager.stats.set_sample_rate(sample_rate);
+		audio_manager.output = Some(Box::new(output));
+		Ok(audio_manager)
 	}
 
-	/// Creates a new audio manager and starts an audio thread.
-	#[cfg(target_arch = "wasm32")]
-	pub fn new(settings: AudioManagerSettings) -> Result<Self, SetupError> {
-		let active_ids = ActiveIds::new(&settings);
-		let (command_producer, command_consumer) = RingBuffer::new(settings.num_commands).split();
-		let resource_collector = Collector::new();
-		let resource_collector_handle = resource_collector.handle();
-		let (_stream, sample_rate) = Self::setup_stream(settings, command_consumer)?;
-		Ok(Self {
-			command_producer: CommandProducer::new(command_producer),
-			active_ids,
-			resource_collector: Some(resource_collector),
-			resource_collector_handle: Some(Arc::new(resource_collector_handle)),
-			sample_rate,
-			_stream,
-		})
+	/// Lists the audio output devices available on the system.
+	///
+	/// Devices that can't report their name or supported
+	/// configurations are left out.
+	pub fn output_devices() -> Result<Vec<OutputDeviceInfo>, ListOutputDevicesError> {
+		Ok(device::output_devices()?)
 	}
 
-	fn setup_stream(
+	/// Creates an audio manager that renders audio on demand
+	/// instead of sending it to an audio device.
+	///
+	/// This is useful for rendering audio to files, generating
+	/// reference output for tests, or running audio logic on
+	/// machines with no sound card. The returned
+	/// [`OfflineRenderer`] produces audio as fast as it's asked to.
+	pub fn new_offline(
 		settings: AudioManagerSettings,
-		command_consumer: Consumer<Command>,
-	) -> Result<(Stream, u32), SetupError> {
-		let host = cpal::default_host();
-		let device = host
-			.default_output_device()
-			.ok_or(SetupError::NoDefaultOutputDevice)?;
-		let config = device.default_output_config()?.config();
-		let sample_rate = config.sample_rate.0;
-		let channels = config.channels;
-		let mut backend = Backend::new(sample_rate, settings, command_consumer);
-		let stream = device.build_output_stream(
-			&config,
-			move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-				for frame in data.chunks_exact_mut(channels as usize) {
-					let out = backend.process();
-					if channels == 1 {
-						frame[0] = (out.left + out.right) / 2.0;
-					} else {
-						frame[0] = out.left;
-						frame[1] = out.right;
-					}
-				}
-			},
-			move |_| {},
-		)?;
-		stream.play()?;
-		Ok((stream, sample_rate))
+		sample_rate: u32,
+	) -> (Self, OfflineRenderer) {
+		let (audio_manager, backend) = Self::new_with_backend(settings, sample_rate);
+		(audio_manager, OfflineRenderer::new(backend, sample_rate))
 	}
 
 	#[cfg(any(feature = "benchmarking", test))]
@@ -245,27 +354,442 @@ impl AudioManager {
 	/// This is useful for updating the backend manually for
 	/// benchmarking.
 	pub fn new_without_audio_thread(settings: AudioManagerSettings) -> (Self, Backend) {
-		const SAMPLE_RATE: u32 = 48000;
-		let (quit_signal_producer, _) = RingBuffer::new(1).split();
+		Self::new_with_backend(settings, DEFAULT_SAMPLE_RATE)
+	}
+
+	/// Creates an [`AudioManager`] and a [`Backend`] that produces
+	/// audio at the given sample rate, without opening an audio
+	/// stream.
+	///
+	/// This is useful when the audio device is owned by something
+	/// else, like a game engine or a platform SDK that calls your
+	/// code from a fixed audio callback. Send the backend to that
+	/// callback and fill each buffer with [`Backend::process_into`].
+	/// Commands sent from the audio manager or any handles are
+	/// applied as the backend produces audio.
+	///
+	/// The backend owns all of the resources on the audio thread,
+	/// so it should be dropped before the audio manager to make sure
+	/// those resources are cleaned up.
+	///
+	/// ```
+	/// # use kira::manager::{AudioManager, AudioManagerSettings};
+	/// let (audio_manager, mut backend) =
+	///     AudioManager::new_with_backend(AudioManagerSettings::default(), 48000);
+	/// // in the host's audio callback:
+	/// # let mut buffer = vec![0.0; 512 * 2];
+	/// backend.process_into(&mut buffer, 2);
+	/// ```
+	pub fn new_with_backend(settings: AudioManagerSettings, sample_rate: u32) -> (Self, Backend) {
 		let (command_producer, command_consumer) = RingBuffer::new(settings.num_commands).split();
-		let resource_collector = Collector::new();
+		let command_producer =
+			CommandProducer::new(command_producer, settings.command_queue_policy);
+		let resource_collector = ResourceCollector::new(
+			settings.resource_collection_interval,
+			command_producer.clone(),
+		);
 		let resource_collector_handle = resource_collector.handle();
+		let (diagnostic_producer, diagnostic_consumer) =
+			RingBuffer::new(settings.num_diagnostics).split();
+		let (removed_stream_id_producer, removed_stream_id_consumer) =
+			RingBuffer::new(settings.num_streams.max(1)).split();
+		let stats = Arc::new(SharedStats::new(sample_rate));
+		let instance_slots = InstanceSlots::new(settings.num_instances);
 		let audio_manager = Self {
-			quit_signal_producer,
-			command_producer: CommandProducer::new(command_producer),
+			command_producer,
 			active_ids: ActiveIds::new(&settings),
-			sample_rate: SAMPLE_RATE,
+			streamed_sound_ids: HashSet::new(),
+			decoding_thread: DecodingThread::new(),
+			resampled_sounds: HashMap::new(),
+			diagnostic_consumer,
+			removed_stream_id_consumer,
+			stats: stats.clone(),
+			instance_slots: Arc::new(Mutex::new(instance_slots.clone())),
+			instance_capacity: settings.num_instances,
+			sequence_capacity: settings.num_sequences,
+			stream_capacity: settings.num_streams,
+			growable_pools: settings.growable_pools,
+			resample_sounds: settings.resample_sounds,
+			paused: false,
+			suspend_deadline: None,
+			num_sequence_commands: settings.num_sequence_commands,
+			channel_layout: settings.channel_layout,
 			resource_collector: Some(resource_collector),
 			resource_collector_handle: Some(Arc::new(resource_collector_handle)),
+			output: None,
 		};
-		let backend = Backend::new(SAMPLE_RATE, settings, command_consumer);
+		let backend = Backend::new(
+			sample_rate,
+			settings,
+			command_consumer,
+			stats,
+			diagnostic_producer,
+			removed_stream_id_producer,
+			instance_slots,
+		);
 		(audio_manager, backend)
 	}
 
+	/// Returns the next event about the audio output device, if any.
+	///
+	/// If the output device stops working (for example, because
+	/// headphones were unplugged), the audio manager will move
+	/// audio output to another device and report a
+	/// [`DeviceEvent::DeviceChanged`]. If no device is available,
+	/// a [`DeviceEvent::DeviceLost`] is reported, and the audio
+	/// manager will keep looking for a device to use. Outputs that
+	/// can't recover from an error, like a `FileOutput` that can't
+	/// write to its file, report a [`DeviceEvent::OutputFailed`].
+	pub fn pop_device_event(&mut self) -> Option<DeviceEvent> {
+		self.output.as_mut()?.pop_device_event()
+	}
+
+	/// Calls a function and sends all of the commands that handles
+	/// send from the current thread during it to the audio thread
+	/// as a single batch.
+	///
+	/// The audio thread applies every command in a batch on the same
+	/// frame, or none of them if there isn't room for all of them.
+	/// This is useful for changes that need to happen together, like
+	/// starting several stems of a song at once.
+	///
+	/// Calls to handle functions inside the batch will succeed even
+	/// if the command queue is full. Instead, this function returns
+	/// an error if the batch can't be sent.
+	///
+	/// If `f` panics, the commands it sent are discarded.
+	///
+	/// ```no_run
+	/// # use kira::{instance::InstanceSettings, manager::AudioManager, sound::Sound};
+	/// # let mut audio_manager = AudioManager::new(Default::default())?;
+	/// # let mut drums = audio_manager.add_sound(Sound::from_file("drums.ogg", Default::default())?)?;
+	/// # let mut bass = audio_manager.add_sound(Sound::from_file("bass.ogg", Default::default())?)?;
+	/// audio_manager.batch(|| -> Result<(), kira::CommandError> {
+	///     drums.play(InstanceSettings::new())?;
+	///     bass.play(InstanceSettings::new())?;
+	///     Ok(())
+	/// })??;
+	/// # Result::<(), Box<dyn std::error::Error>>::Ok(())
+	/// ```
+	pub fn batch<T>(&mut self, f: impl FnOnce() -> T) -> Result<T, CommandError> {
+		// if `f` panics, the batch is discarded when it's dropped
+		let batch = self.command_producer.start_batch()?;
+		let output = f();
+		let commands = batch.finish()?;
+		if commands.is_empty() {
+			return Ok(output);
+		}
+		if commands.len() > self.command_producer.capacity() {
+			return Err(CommandError::CommandQueueFull);
+		}
+		let commands = Owned::new(&self.resource_collector().handle(), commands);
+		self.command_producer.push(Command::Batch(commands))?;
+		Ok(output)
+	}
+
+	/// Pauses or resumes everything in the game world.
+	///
+	/// While paused, the audio thread stops updating parameters,
+	/// metronomes, sequences, instances, audio streams, and the
+	/// cooldowns of sounds and arrangements, so everything picks
+	/// up exactly where it left off when resumed. Instances and
+	/// sequences in an [unpausable group](crate::group::GroupSettings::unpausable),
+	/// as well as instances and streams on an
+	/// [unpausable track](crate::mixer::SubTrackSettings::unpausable),
+	/// keep playing, which is useful for things like menu sounds.
+	///
+	/// Pausing takes effect immediately. To fade sounds out, pause
+	/// their groups or instances with a fade tween instead.
+	pub fn set_paused(&mut self, paused: bool) -> Result<(), CommandError> {
+		self.command_producer.push(Command::SetPaused(paused))?;
+		self.paused = paused;
+		Ok(())
+	}
+
+	/// Returns whether the audio manager is paused.
+	///
+	/// See [`set_paused`](Self::set_paused).
+	pub fn is_paused(&self) -> bool {
+		self.paused
+	}
+
+	/// Stops producing audio, optionally fading out first.
+	///
+	/// Every sound, instance, sequence, and track is kept as it is,
+	/// and the audio clock stops advancing, so the audio manager can
+	/// pick up where it left off when [`resume`](Self::resume) is
+	/// called. This is useful for stopping audio while a mobile
+	/// app is in the background.
+	///
+	/// This blocks until the fade-out is finished, and then the
+	/// output (for example, the audio stream) is suspended as well.
+	/// To avoid blocking, use [`start_suspend`](Self::start_suspend)
+	/// and [`poll_suspend`](Self::poll_suspend) instead. On wasm, the
+	/// output is suspended right away, so the fade-out may be cut
+	/// short. If the audio manager was created with
+	/// [`new_with_backend`](Self::new_with_backend), this returns
+	/// immediately, and the backend will produce silence once
+	/// it's finished fading out.
+	pub fn suspend(&mut self, fade_tween: impl Into<Option<Tween>>) -> Result<(), CommandError> {
+		self.start_suspend(fade_tween)?;
+		#[cfg(not(target_arch = "wasm32"))]
+		while !self.poll_suspend() {
+			std::thread::sleep(std::time::Duration::from_millis(1));
+		}
+		#[cfg(target_arch = "wasm32")]
+		if self.suspend_deadline.take().is_some() {
+			if let Some(output) = &mut self.output {
+				output.suspend();
+			}
+		}
+		Ok(())
+	}
+
+	/// Starts fading out and suspending the audio manager without
+	/// waiting for the fade-out to finish.
+	///
+	/// Call [`poll_suspend`](Self::poll_suspend) regularly (for example,
+	/// once per game frame) afterward to suspend the output once the
+	/// audio has faded out. See [`suspend`](Self::suspend) for details.
+	pub fn start_suspend(
+		&mut self,
+		fade_tween: impl Into<Option<Tween>>,
+	) -> Result<(), CommandError> {
+		let fade_tween = fade_tween.into();
+		self.command_producer.push(Command::Suspend(fade_tween))?;
+		if self.output.is_some() {
+			let fade_duration = fade_tween.map_or(0.0, |tween| tween.duration);
+			self.suspend_deadline = Some(
+				Instant::now()
+					+ std::time::Duration::from_secs_f64(fade_duration)
+					+ std::time::Duration::from_millis(SUSPEND_TIMEOUT_MILLIS),
+			);
+		}
+		Ok(())
+	}
+
+	/// Suspends the output if the audio thread has finished fading
+	/// out after [`start_suspend`](Self::start_suspend).
+	///
+	/// Returns `true` if there's no suspension left to finish. This
+	/// never blocks.
+	pub fn poll_suspend(&mut self) -> bool {
+		let deadline = match self.suspend_deadline {
+			Some(deadline) => deadline,
+			None => return true,
+		};
+		// if the audio thread isn't running, it'll never finish fading
+		// out, so suspend the output anyway after a while
+		if !self.stats.suspended() && Instant::now() < deadline {
+			return false;
+		}
+		self.suspend_deadline = None;
+		if let Some(output) = &mut self.output {
+			output.suspend();
+		}
+		true
+	}
+
+	/// Starts producing audio again after [`suspend`](Self::suspend),
+	/// optionally fading in.
+	pub fn resume(&mut self, fade_tween: impl Into<Option<Tween>>) -> Result<(), CommandError> {
+		self.command_producer
+			.push(Command::Resume(fade_tween.into()))?;
+		self.suspend_deadline = None;
+		if let Some(output) = &mut self.output {
+			output.resume();
+		}
+		Ok(())
+	}
+
+	/// Returns the sample rate the audio thread is producing
+	/// audio at.
+	///
+	/// This can change if audio output moves to a different
+	/// device (see [`pop_device_event`](Self::pop_device_event)).
+	pub fn sample_rate(&self) -> u32 {
+		self.stats.sample_rate()
+	}
+
+	/// Returns the current time on the audio clock.
+	///
+	/// The audio clock counts the number of frames of audio
+	/// that have been produced since the audio manager was
+	/// created, so it advances by [`sample_rate`](Self::sample_rate)
+	/// every second.
+	///
+	/// Some commands, like [`SoundHandle::play_at`](crate::sound::handle::SoundHandle::play_at)
+	/// and [`ParameterHandle::set_at`](crate::parameter::handle::ParameterHandle::set_at),
+	/// can be scheduled to take effect on a specific frame. Since
+	/// audio is produced in buffers, commands should be scheduled
+	/// at least one buffer ahead of the current time to avoid
+	/// running late.
+	pub fn clock_time(&self) -> u64 {
+		self.stats.clock_time()
+	}
+
+	/// Returns information about what the audio thread is doing,
+	/// such as how much of the available processing time it's using
+	/// and how many instances are playing.
+	pub fn stats(&self) -> AudioManagerStats {
+		AudioManagerStats {
+			cpu_load: self.stats.cpu_load(),
+			peak_cpu_load: self.stats.peak_cpu_load(),
+			num_late_callbacks: self.stats.num_late_callbacks(),
+			num_callbacks: self.stats.num_callbacks(),
+			num_instances: self.stats.num_instances(),
+			instance_capacity: self.instance_capacity,
+			num_sequences: self.stats.num_sequences(),
+			sequence_capacity: self.sequence_capacity,
+			num_streams: self.stats.num_streams(),
+			stream_capacity: self.stream_capacity,
+			num_queued_commands: self.command_producer.len(),
+			num_spilled_commands: self.command_producer.num_spilled(),
+			command_capacity: self.command_producer.capacity(),
+			num_dropped_commands: self.stats.num_dropped_commands(),
+			num_unfreed_resources: self.resource_collector().num_unfreed_resources(),
+		}
+	}
+
+	/// Returns the next problem reported by the audio thread, if any.
+	///
+	/// The audio thread can't return errors, so problems like commands
+	/// being discarded or referring to resources that don't exist are
+	/// reported here instead. This can be useful for logging in
+	/// debug builds.
+	///
+	/// Panics in custom effects and audio streams are caught on the
+	/// audio thread and reported here as well. A stream that panicked
+	/// has already been removed.
+	pub fn pop_diagnostic(&mut self) -> Option<Diagnostic> {
+		self.diagnostic_consumer.pop()
+	}
+
+	/// Frees the IDs of streams that the audio thread removed
+	/// because they panicked.
+	fn free_panicked_stream_ids(&mut self) {
+		while let Some(id) = self.removed_stream_id_consumer.pop() {
+			self.active_ids.remove_stream_id(id).ok();
+		}
+	}
+
+	/// If pools are allowed to grow and no more resources of the
+	/// given kind can be added, sends storage with twice the
+	/// capacity to the audio thread. If a specific ID is
+	/// `requested` and its slot is past the end of the pool, the
+	/// pool grows enough to fit it.
+	fn grow_pool_if_needed(
+		&mut self,
+		kind: ResourceKind,
+		requested: Option<SlotIndex>,
+	) -> Result<(), CommandError> {
+		let old_capacity = self.active_ids.capacity(kind);
+		let required_capacity = requested.map_or(0, |id| id.index as usize + 1);
+		if !self.growable_pools
+			|| (!self.active_ids.is_full(kind) && required_capacity <= old_capacity)
+		{
+			return Ok(());
+		}
+		let capacity = (old_capacity * 2).max(required_capacity).max(1);
+		let sound_capacity = self.active_ids.capacity(ResourceKind::Sound);
+		let arrangement_capacity = self.active_ids.capacity(ResourceKind::Arrangement);
+		let sub_track_capacity = self.active_ids.capacity(ResourceKind::SubTrack);
+		let send_track_capacity = self.active_ids.capacity(ResourceKind::SendTrack);
+		let handle = self.resource_collector().handle();
+		let command = match kind {
+			ResourceKind::Sound => GrowPoolCommand::Playables(Owned::new(
+				&handle,
+				Playables::new(capacity, arrangement_capacity),
+			)),
+			ResourceKind::Arrangement => GrowPoolCommand::Playables(Owned::new(
+				&handle,
+				Playables::new(sound_capacity, capacity),
+			)),
+			ResourceKind::Parameter => {
+				GrowPoolCommand::Parameters(Owned::new(&handle, Parameters::new(capacity)))
+			}
+			ResourceKind::SubTrack => GrowPoolCommand::Mixer(Owned::new(
+				&handle,
+				Mixer::new(
+					capacity,
+					send_track_capacity,
+					self.channel_layout,
+					self.sample_rate(),
+				),
+			)),
+			ResourceKind::SendTrack => GrowPoolCommand::Mixer(Owned::new(
+				&handle,
+				Mixer::new(
+					sub_track_capacity,
+					capacity,
+					self.channel_layout,
+					self.sample_rate(),
+				),
+			)),
+			ResourceKind::Group => {
+				GrowPoolCommand::Groups(Owned::new(&handle, Groups::new(capacity)))
+			}
+			ResourceKind::Metronome => {
+				GrowPoolCommand::Metronomes(Owned::new(&handle, Metronomes::new(capacity)))
+			}
+			ResourceKind::Stream => {
+				GrowPoolCommand::Streams(Owned::new(&handle, Streams::new(capacity)))
+			}
+		};
+		self.command_producer.push(command.into())?;
+		self.active_ids.set_capacity(kind, capacity);
+		if kind == ResourceKind::Stream {
+			self.stream_capacity = capacity;
+		}
+		Ok(())
+	}
+
+	/// If pools are allowed to grow, sends storage with twice the
+	/// capacity to the audio thread for the instance and sequence
+	/// pools if they're at least half full.
+	fn grow_instance_and_sequence_pools(&mut self) -> Result<(), CommandError> {
+		if !self.growable_pools {
+			return Ok(());
+		}
+		if self.stats.num_instances() * 2 >= self.instance_capacity {
+			let capacity = (self.instance_capacity * 2).max(1);
+			let instance_slots = self
+				.instance_slots
+				.lock()
+				.unwrap_or_else(PoisonError::into_inner)
+				.with_capacity(capacity);
+			let instances = Owned::new(
+				&self.resource_collector().handle(),
+				Instances::new(instance_slots.clone()),
+			);
+			self.command_producer
+				.push(GrowPoolCommand::Instances(instances).into())?;
+			// handles only start using the new slots once the audio
+			// thread has storage for them, which it will by the time
+			// it receives commands sent after this one
+			*self
+				.instance_slots
+				.lock()
+				.unwrap_or_else(PoisonError::into_inner) = instance_slots;
+			self.instance_capacity = capacity;
+		}
+		if self.stats.num_sequences() * 2 >= self.sequence_capacity {
+			let capacity = (self.sequence_capacity * 2).max(1);
+			let sequences = Owned::new(
+				&self.resource_collector().handle(),
+				Sequences::new(capacity, self.num_sequence_commands),
+			);
+			self.command_producer
+				.push(GrowPoolCommand::Sequences(sequences).into())?;
+			self.sequence_capacity = capacity;
+		}
+		Ok(())
+	}
+
 	fn does_track_exist(&self, track: TrackIndex) -> bool {
 		match track {
 			TrackIndex::Main => true,
-			TrackIndex::Sub(id) => self.active_ids.active_sub_track_ids.contains(&id),
+			TrackIndex::Sub(id) => self.active_ids.active_sub_track_ids.contains(id),
 			_ => todo!(),
 		}
 	}
@@ -273,15 +797,15 @@ impl AudioManager {
 	/// Finds the first group that doesn't exist in a group set.
 	fn first_missing_group_in_set(&self, set: &GroupSet) -> Option<GroupId> {
 		set.iter()
-			.find(|id| !self.active_ids.active_group_ids.contains(*id))
+			.find(|id| !self.active_ids.active_group_ids.contains(**id))
 			.copied()
 	}
 
-	fn resource_collector(&self) -> &Collector {
+	fn resource_collector(&self) -> &ResourceCollector {
 		self.resource_collector.as_ref().unwrap()
 	}
 
-	fn resource_collector_mut(&mut self) -> &mut Collector {
+	fn resource_collector_mut(&mut self) -> &mut ResourceCollector {
 		self.resource_collector.as_mut().unwrap()
 	}
 
@@ -293,8 +817,31 @@ impl AudioManager {
 		if let Some(group) = self.first_missing_group_in_set(sound.groups()) {
 			return Err(AddSoundError::NoGroupWithId(group));
 		}
+		self.grow_pool_if_needed(ResourceKind::Sound, None)?;
+		// the ID is claimed here rather than when the sound is
+		// created, since creating a sound can't fail
+		sound
+			.id()
+			.claim()
+			.map_err(|_| AddSoundError::IdTooLarge(sound.id()))?;
 		self.active_ids.add_sound_id(sound.id())?;
-		let handle = SoundHandle::new(&sound, self.command_producer.clone());
+		let sound = if self.resample_sounds && !sound.is_streamed() {
+			let sample_rate = self.sample_rate();
+			self.resampled_sounds.insert(sound.id(), sample_rate);
+			sound.resampled(sample_rate)
+		} else {
+			sound
+		};
+		if sound.is_streamed() {
+			self.streamed_sound_ids.insert(sound.id());
+		}
+		let handle = SoundHandle::new(
+			&sound,
+			self.command_producer.clone(),
+			self.instance_slots.clone(),
+			self.decoding_thread.clone(),
+			self.resource_collector().handle(),
+		);
 		let sound = Owned::new(&self.resource_collector().handle(), sound);
 		self.command_producer
 			.push(ResourceCommand::AddSound(sound).into())?;
@@ -319,11 +866,31 @@ impl AudioManager {
 	pub fn remove_sound(&mut self, id: impl Into<SoundId>) -> Result<(), RemoveSoundError> {
 		let id = id.into();
 		self.active_ids.remove_sound_id(id)?;
+		self.streamed_sound_ids.remove(&id);
+		self.resampled_sounds.remove(&id);
 		self.command_producer
 			.push(ResourceCommand::RemoveSound(id).into())?;
 		Ok(())
 	}
 
+	/// Returns the sounds that were resampled when they were added
+	/// (see [`AudioManagerSettings::resample_sounds`]), but no longer
+	/// match the audio manager's sample rate because audio output
+	/// moved to a device with a different sample rate.
+	///
+	/// These sounds still play at the right pitch, but they're
+	/// resampled with simple interpolation as they play. To resample
+	/// them with the higher quality filter again, remove them and
+	/// add them again.
+	pub fn outdated_resampled_sounds(&self) -> Vec<SoundId> {
+		let sample_rate = self.sample_rate();
+		self.resampled_sounds
+			.iter()
+			.filter(|(_, resampled_rate)| **resampled_rate != sample_rate)
+			.map(|(id, _)| *id)
+			.collect()
+	}
+
 	/// Sends a arrangement to the audio thread and returns a handle to the arrangement.
 	pub fn add_arrangement(
 		&mut self,
@@ -337,8 +904,25 @@ impl AudioManager {
 		if let Some(group) = self.first_missing_group_in_set(arrangement.groups()) {
 			return Err(AddArrangementError::NoGroupWithId(group));
 		}
+		if let Some(sound_id) = arrangement
+			.sound_ids()
+			.find(|id| self.streamed_sound_ids.contains(id))
+		{
+			return Err(AddArrangementError::StreamedSound(sound_id));
+		}
+		self.grow_pool_if_needed(ResourceKind::Arrangement, None)?;
+		// the ID is claimed here rather than when the arrangement
+		// is created, since creating an arrangement can't fail
+		arrangement
+			.id()
+			.claim()
+			.map_err(|_| AddArrangementError::IdTooLarge(arrangement.id()))?;
 		self.active_ids.add_arrangement_id(arrangement.id())?;
-		let handle = ArrangementHandle::new(&arrangement, self.command_producer.clone());
+		let handle = ArrangementHandle::new(
+			&arrangement,
+			self.command_producer.clone(),
+			self.instance_slots.clone(),
+		);
 		let arrangement = Owned::new(&self.resource_collector().handle(), arrangement);
 		self.command_producer
 			.push(ResourceCommand::AddArrangement(arrangement).into())?;
@@ -362,8 +946,11 @@ impl AudioManager {
 		&mut self,
 		settings: MetronomeSettings,
 	) -> Result<MetronomeHandle, AddMetronomeError> {
-		let id = settings.id.unwrap_or(MetronomeId::new());
-		self.active_ids.add_metronome_id(id)?;
+		self.grow_pool_if_needed(
+			ResourceKind::Metronome,
+			settings.id.map(|id| id.slot_index()),
+		)?;
+		let id = self.active_ids.add_metronome_id(settings.id)?;
 		let (event_producer, event_consumer) =
 			RingBuffer::new(settings.event_queue_capacity).split();
 		let metronome = Owned::new(
@@ -402,7 +989,17 @@ impl AudioManager {
 			return Err(StartSequenceError::NoGroupWithId(group));
 		}
 		sequence.validate()?;
-		let id = settings.id.unwrap_or(SequenceInstanceId::new());
+		if let Some(sound_id) = sequence
+			.sound_ids()
+			.find(|id| self.streamed_sound_ids.contains(id))
+		{
+			return Err(StartSequenceError::StreamedSound(sound_id));
+		}
+		self.grow_instance_and_sequence_pools()?;
+		let id = match settings.id {
+			Some(id) => id.claim().map_err(|_| StartSequenceError::IdTooLarge(id))?,
+			None => SequenceInstanceId::new(),
+		};
 		let (instance, handle) =
 			sequence.create_instance(id, settings, self.command_producer.clone());
 		let instance = Owned::new(&self.resource_collector().handle(), instance);
@@ -416,8 +1013,11 @@ impl AudioManager {
 		&mut self,
 		settings: ParameterSettings,
 	) -> Result<ParameterHandle, AddParameterError> {
-		let id = settings.id.unwrap_or(ParameterId::new());
-		self.active_ids.add_parameter_id(id)?;
+		self.grow_pool_if_needed(
+			ResourceKind::Parameter,
+			settings.id.map(|id| id.slot_index()),
+		)?;
+		let id = self.active_ids.add_parameter_id(settings.id)?;
 		self.command_producer
 			.push(ParameterCommand::AddParameter(id, settings.value).into())?;
 		Ok(ParameterHandle::new(id, self.command_producer.clone()))
@@ -439,7 +1039,7 @@ impl AudioManager {
 	pub fn main_track(&mut self) -> MainTrackHandle {
 		MainTrackHandle::new(
 			self.command_producer.clone(),
-			self.sample_rate,
+			self.stats.clone(),
 			Arc::downgrade(self.resource_collector_handle.as_ref().unwrap()),
 		)
 	}
@@ -450,7 +1050,7 @@ impl AudioManager {
 		settings: SubTrackSettings,
 	) -> Result<SubTrackHandle, AddSubTrackError> {
 		if let TrackIndex::Sub(id) = settings.parent_track {
-			if !self.active_ids.active_sub_track_ids.contains(&id) {
+			if !self.active_ids.active_sub_track_ids.contains(id) {
 				return Err(AddSubTrackError::NonexistentParentTrack(id));
 			}
 		}
@@ -458,18 +1058,21 @@ impl AudioManager {
 			if !self
 				.active_ids
 				.active_send_track_ids
-				.contains(send_track_id)
+				.contains(*send_track_id)
 			{
 				return Err(AddSubTrackError::NonexistentSendTrack(*send_track_id));
 			}
 		}
-		let id = settings.id.unwrap_or(SubTrackId::new());
-		self.active_ids.add_sub_track_id(id)?;
+		self.grow_pool_if_needed(
+			ResourceKind::SubTrack,
+			settings.id.map(|id| id.slot_index()),
+		)?;
+		let id = self.active_ids.add_sub_track_id(settings.id)?;
 		let handle = SubTrackHandle::new(
 			id,
 			&settings,
 			self.command_producer.clone(),
-			self.sample_rate,
+			self.stats.clone(),
 			Arc::downgrade(self.resource_collector_handle.as_ref().unwrap()),
 		);
 		let track = Owned::new(
@@ -498,13 +1101,16 @@ impl AudioManager {
 		&mut self,
 		settings: SendTrackSettings,
 	) -> Result<SendTrackHandle, AddSendTrackError> {
-		let id = settings.id.unwrap_or(SendTrackId::new());
-		self.active_ids.add_send_track_id(id)?;
+		self.grow_pool_if_needed(
+			ResourceKind::SendTrack,
+			settings.id.map(|id| id.slot_index()),
+		)?;
+		let id = self.active_ids.add_send_track_id(settings.id)?;
 		let handle = SendTrackHandle::new(
 			id,
 			&settings,
 			self.command_producer.clone(),
-			self.sample_rate,
+			self.stats.clone(),
 			Arc::downgrade(self.resource_collector_handle.as_ref().unwrap()),
 		);
 		let track = Owned::new(
@@ -533,8 +1139,8 @@ impl AudioManager {
 		if let Some(group) = self.first_missing_group_in_set(&settings.groups) {
 			return Err(AddGroupError::NoGroupWithId(group));
 		}
-		let id = settings.id.unwrap_or(GroupId::new());
-		self.active_ids.add_group_id(id)?;
+		self.grow_pool_if_needed(ResourceKind::Group, settings.id.map(|id| id.slot_index()))?;
+		let id = self.active_ids.add_group_id(settings.id)?;
 		let group = Owned::new(&self.resource_collector().handle(), Group::new(settings));
 		self.command_producer
 			.push(GroupCommand::AddGroup(id, group).into())?;
@@ -559,8 +1165,9 @@ impl AudioManager {
 		if !self.does_track_exist(track) {
 			return Err(AddStreamError::NoTrackWithIndex(track));
 		}
-		let id = AudioStreamId::new();
-		self.active_ids.add_stream_id(id)?;
+		self.free_panicked_stream_ids();
+		self.grow_pool_if_needed(ResourceKind::Stream, None)?;
+		let id = self.active_ids.add_stream_id()?;
 		self.command_producer.push(
 			StreamCommand::AddStream(
 				id,
@@ -574,6 +1181,7 @@ impl AudioManager {
 
 	/// Removes an audio stream.
 	pub fn remove_stream(&mut self, id: AudioStreamId) -> Result<(), RemoveStreamError> {
+		self.free_panicked_stream_ids();
 		self.active_ids.remove_stream_id(id)?;
 		self.command_producer
 			.push(StreamCommand::RemoveStream(id).into())?;
@@ -582,7 +1190,21 @@ impl AudioManager {
 
 	/// Frees resources that are no longer in use, such as unloaded sounds
 	/// or finished sequences.
+	///
+	/// If [`AudioManagerSettings::resource_collection_interval`] is set,
+	/// resources are freed on a background thread instead, and this
+	/// doesn't free anything.
+	///
+	/// If [`AudioManagerSettings::growable_pools`] is `true`, this also
+	/// grows the instance and sequence pools if they're getting full.
+	///
+	/// If [`AudioManagerSettings::command_queue_policy`] is
+	/// [`CommandQueuePolicy::Spill`], this also sends held commands
+	/// to the audio thread if there's room for them.
 	pub fn free_unused_resources(&mut self) {
+		self.command_producer.flush_spilled().ok();
+		// if the command queue is full, we'll try again next time
+		self.grow_instance_and_sequence_pools().ok();
 		self.resource_collector_mut().collect();
 	}
 }
@@ -592,28 +1214,25 @@ impl Drop for AudioManager {
 	fn drop(&mut self) {
 		std::mem::drop(self.resource_collector_handle.take());
 
-		#[cfg(not(target_arch = "wasm32"))]
-		self.quit_signal_producer.push(true).ok();
+		// stop the output so it releases the backend
+		std::mem::drop(self.output.take());
 
 		// cleanup all unused resources. if we can't get everything to successfully
 		// drop within a reasonable amount of time, just give up
-		let mut resource_collector = Err(self.resource_collector.take().unwrap());
-		let start_time = Instant::now();
-		while let Err(mut collector) = resource_collector {
-			if Instant::now() - start_time
-				> std::time::Duration::from_millis(DROP_CLEANUP_TIMEOUT_MILLIS)
-			{
-				// TODO: consider integrating with the log crate
-				writeln!(
-					stderr(),
-					"Kira failed to free up resources after {} milliseconds, giving up",
-					DROP_CLEANUP_TIMEOUT_MILLIS
-				)
-				.ok();
-				return;
-			}
-			collector.collect();
-			resource_collector = collector.try_cleanup();
+		let resource_collector = self.resource_collector.take().unwrap();
+		if resource_collector
+			.cleanup(std::time::Duration::from_millis(
+				DROP_CLEANUP_TIMEOUT_MILLIS,
+			))
+			.is_err()
+		{
+			// TODO: consider integrating with the log crate
+			writeln!(
+				stderr(),
+				"Kira failed to free up resources after {} milliseconds, giving up",
+				DROP_CLEANUP_TIMEOUT_MILLIS
+			)
+			.ok();
 		}
 	}
 }
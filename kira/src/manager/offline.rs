@@ -0,0 +1,144 @@
+//! Renders audio without an audio device.
+
+#[cfg(feature = "wav")]
+use std::{
+	io::{Seek, Write},
+	path::Path,
+};
+
+#[cfg(feature = "wav")]
+use crate::mixer::MAX_BLOCK_SIZE;
+use crate::{channel_layout::ChannelLayout, Frame};
+
+use super::backend::Backend;
+#[cfg(feature = "wav")]
+use super::error::RenderToFileError;
+
+/// Renders the output of an [`AudioManager`](super::AudioManager)
+/// faster than real time.
+///
+/// An `OfflineRenderer` is created along with an `AudioManager` by
+/// [`AudioManager::new_offline`](super::AudioManager::new_offline).
+/// Commands sent from the audio manager or any handles are applied
+/// whenever the renderer produces audio, so you can interleave
+/// calls to the audio manager with calls to the renderer to script
+/// what happens at each point in time.
+///
+/// The renderer owns all of the resources on the "audio thread",
+/// so it should be dropped before the audio manager to make sure
+/// those resources are cleaned up.
+pub struct OfflineRenderer {
+	backend: Backend,
+	sample_rate: u32,
+}
+
+impl OfflineRenderer {
+	pub(crate) fn new(backend: Backend, sample_rate: u32) -> Self {
+		Self {
+			backend,
+			sample_rate,
+		}
+	}
+
+	/// Returns the sample rate audio is rendered at.
+	pub fn sample_rate(&self) -> u32 {
+		self.sample_rate
+	}
+
+	/// Returns the channel layout audio is rendered in.
+	pub fn channel_layout(&self) -> ChannelLayout {
+		self.backend.channel_layout()
+	}
+
+	/// Produces a single frame of audio.
+	pub fn process(&mut self) -> Frame {
+		self.backend.process()
+	}
+
+	/// Fills a buffer with frames of audio.
+	///
+	/// If the audio manager uses a surround [`ChannelLayout`],
+	/// the audio is mixed down to stereo.
+	pub fn render_into(&mut self, frames: &mut [Frame]) {
+		self.backend.process_block(frames);
+	}
+
+	/// Fills a buffer with interleaved samples of audio in the
+	/// audio manager's [`ChannelLayout`].
+	///
+	/// The number of frames rendered is the length of the buffer
+	/// divided by the number of channels in the layout. This is
+	/// useful for rendering surround audio, which can't be
+	/// represented by stereo [`Frame`]s.
+	pub fn render_interleaved(&mut self, samples: &mut [f32]) {
+		let channels = self.channel_layout().num_channels();
+		self.backend.process_into(samples, channels);
+	}
+
+	/// Renders the specified number of frames of audio.
+	pub fn render(&mut self, num_frames: usize) -> Vec<Frame> {
+		let mut frames = vec![Frame::from_mono(0.0); num_frames];
+		self.render_into(&mut frames);
+		frames
+	}
+
+	/// Renders the specified amount of audio (in seconds).
+	pub fn render_seconds(&mut self, duration: f64) -> Vec<Frame> {
+		self.render(self.seconds_to_frames(duration))
+	}
+
+	/// Renders the specified amount of audio (in seconds) and writes
+	/// it to a writer as a 32-bit float WAV file with one channel for
+	/// each speaker in the audio manager's [`ChannelLayout`].
+	#[cfg(feature = "wav")]
+	pub fn render_to_wav_writer<W>(
+		&mut self,
+		writer: W,
+		duration: f64,
+	) -> Result<(), RenderToFileError>
+	where
+		W: Write + Seek,
+	{
+		let channels = self.channel_layout().num_channels();
+		let mut writer = hound::WavWriter::new(
+			writer,
+			hound::WavSpec {
+				channels: channels as u16,
+				sample_rate: self.sample_rate,
+				bits_per_sample: 32,
+				sample_format: hound::SampleFormat::Float,
+			},
+		)?;
+		let mut samples = vec![0.0; MAX_BLOCK_SIZE * channels];
+		let mut frames_remaining = self.seconds_to_frames(duration);
+		while frames_remaining > 0 {
+			let num_frames = frames_remaining.min(MAX_BLOCK_SIZE);
+			let block = &mut samples[..num_frames * channels];
+			self.backend.process_into(block, channels);
+			for sample in block.iter() {
+				writer.write_sample(*sample)?;
+			}
+			frames_remaining -= num_frames;
+		}
+		writer.finalize()?;
+		Ok(())
+	}
+
+	/// Renders the specified amount of audio (in seconds) and saves
+	/// it as a 32-bit float WAV file with one channel for each speaker
+	/// in the audio manager's [`ChannelLayout`].
+	#[cfg(feature = "wav")]
+	pub fn render_to_wav_file<P>(&mut self, path: P, duration: f64) -> Result<(), RenderToFileError>
+	where
+		P: AsRef<Path>,
+	{
+		self.render_to_wav_writer(
+			std::io::BufWriter::new(std::fs::File::create(path)?),
+			duration,
+		)
+	}
+
+	fn seconds_to_frames(&self, duration: f64) -> usize {
+		(duration * self.sample_rate as f64).round() as usize
+	}
+}
@@ -0,0 +1,81 @@
+use std::{
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	thread::JoinHandle,
+	time::{Duration, Instant},
+};
+
+use crate::manager::backend::Backend;
+
+/// Runs a backend on a thread at the speed audio would be
+/// played back at, passing each buffer of interleaved samples
+/// to a callback.
+///
+/// This is used by outputs that don't have an audio device
+/// to keep time for them.
+pub(super) struct ClockedThread {
+	quit: Arc<AtomicBool>,
+	suspended: Arc<AtomicBool>,
+	join_handle: Option<JoinHandle<()>>,
+}
+
+impl ClockedThread {
+	pub fn start(
+		mut backend: Backend,
+		sample_rate: u32,
+		buffer_size: usize,
+		mut callback: impl FnMut(&[f32]) + Send + 'static,
+	) -> Self {
+		let quit = Arc::new(AtomicBool::new(false));
+		let thread_quit = quit.clone();
+		let suspended = Arc::new(AtomicBool::new(false));
+		let thread_suspended = suspended.clone();
+		backend.set_sample_rate(sample_rate);
+		let join_handle = std::thread::spawn(move || {
+			let channels = backend.channel_layout().num_channels();
+			let mut buffer = vec![0.0; buffer_size * channels];
+			let buffer_duration = Duration::from_secs_f64(buffer_size as f64 / sample_rate as f64);
+			let mut next_buffer_time = Instant::now();
+			while !thread_quit.load(Ordering::Relaxed) {
+				if thread_suspended.load(Ordering::Relaxed) {
+					std::thread::sleep(buffer_duration);
+					next_buffer_time = Instant::now();
+					continue;
+				}
+				backend.process_into(&mut buffer, channels);
+				callback(&buffer);
+				next_buffer_time += buffer_duration;
+				let now = Instant::now();
+				if next_buffer_time > now {
+					std::thread::sleep(next_buffer_time - now);
+				} else if now - next_buffer_time > buffer_duration {
+					// if we've fallen more than a buffer behind (for example,
+					// because the process was suspended), don't try to
+					// catch up
+					next_buffer_time = now;
+				}
+			}
+		});
+		Self {
+			quit,
+			suspended,
+			join_handle: Some(join_handle),
+		}
+	}
+
+	/// Stops or starts producing audio.
+	pub fn set_suspended(&self, suspended: bool) {
+		self.suspended.store(suspended, Ordering::Relaxed);
+	}
+}
+
+impl Drop for ClockedThread {
+	fn drop(&mut self) {
+		self.quit.store(true, Ordering::Relaxed);
+		if let Some(join_handle) = self.join_handle.take() {
+			join_handle.join().ok();
+		}
+	}
+}
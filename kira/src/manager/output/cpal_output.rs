@@ -0,0 +1,189 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::{
+	atomic::{AtomicBool, Ordering},
+	Arc,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use ringbuf::Producer;
+use ringbuf::{Consumer, RingBuffer};
+
+use crate::manager::{
+	backend::Backend, error::SetupError, stream_manager::StreamManager, AudioManagerSettings,
+	DeviceEvent,
+};
+
+use super::OutputBackend;
+
+const DEVICE_EVENT_QUEUE_CAPACITY: usize = 10;
+#[cfg(not(target_arch = "wasm32"))]
+const WRAPPER_THREAD_SLEEP_DURATION: f64 = 1.0 / 60.0;
+
+/// Sends audio to an audio output device using cpal.
+///
+/// If the output device stops working, the output will move
+/// to another device and report a [`DeviceEvent`].
+#[derive(Default)]
+pub struct CpalOutput {
+	device: Option<String>,
+	sample_rate: Option<u32>,
+	buffer_size: Option<u32>,
+	#[cfg(not(target_arch = "wasm32"))]
+	quit_signal_producer: Option<Producer<bool>>,
+	// whether the stream should be paused. the stream is owned
+	// by another thread, which checks this periodically
+	#[cfg(not(target_arch = "wasm32"))]
+	suspended: Arc<AtomicBool>,
+	device_event_consumer: Option<Consumer<DeviceEvent>>,
+	// on wasm, the stream is created on the main thread, so
+	// the stream manager is owned by the output directly
+	#[cfg(target_arch = "wasm32")]
+	stream_manager: Option<StreamManager>,
+}
+
+impl CpalOutput {
+	/// Creates a new `CpalOutput` that uses the system's
+	/// default output device.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Sets the name of the output device to use.
+	pub fn device(mut self, device: impl Into<String>) -> Self {
+		self.device = Some(device.into());
+		self
+	}
+
+	/// Sets the sample rate to request from the output device.
+	pub fn sample_rate(mut self, sample_rate: u32) -> Self {
+		self.sample_rate = Some(sample_rate);
+		self
+	}
+
+	/// Sets the number of frames the output device should
+	/// request at a time.
+	pub fn buffer_size(mut self, buffer_size: u32) -> Self {
+		self.buffer_size = Some(buffer_size);
+		self
+	}
+
+	pub(crate) fn from_settings(settings: &AudioManagerSettings) -> Self {
+		let mut output = Self::new();
+		output.device = settings.output_device.clone();
+		output.sample_rate = settings.sample_rate;
+		output.buffer_size = settings.buffer_size;
+		output
+	}
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl OutputBackend for CpalOutput {
+	fn start(&mut self, backend: Backend) -> Result<u32, SetupError> {
+		let (quit_signal_producer, mut quit_signal_consumer) = RingBuffer::new(1).split();
+		let (device_event_producer, device_event_consumer) =
+			RingBuffer::new(DEVICE_EVENT_QUEUE_CAPACITY).split();
+		let (mut setup_result_producer, mut setup_result_consumer) = RingBuffer::new(1).split();
+		let device = self.device.clone();
+		let sample_rate = self.sample_rate;
+		let buffer_size = self.buffer_size;
+		let suspended = self.suspended.clone();
+		// set up a cpal stream on a new thread. we could do this on the main thread,
+		// but that causes issues with LÖVE.
+		std::thread::spawn(move || {
+			match StreamManager::start(
+				backend,
+				device,
+				sample_rate,
+				buffer_size,
+				device_event_producer,
+			) {
+				Ok(mut stream_manager) => {
+					setup_result_producer
+						.push(Ok(stream_manager.sample_rate()))
+						.unwrap();
+					// wait for a quit message before ending the thread and dropping
+					// the stream. in the meantime, rebuild the stream if the
+					// output device stops working
+					while quit_signal_consumer.pop().is_none() {
+						stream_manager.set_suspended(suspended.load(Ordering::Relaxed));
+						stream_manager.update();
+						std::thread::sleep(std::time::Duration::from_secs_f64(
+							WRAPPER_THREAD_SLEEP_DURATION,
+						));
+					}
+				}
+				Err(error) => {
+					setup_result_producer.push(Err(error)).unwrap();
+				}
+			}
+		});
+		// wait for the audio thread to report back a result
+		let sample_rate = loop {
+			if let Some(result) = setup_result_consumer.pop() {
+				break result?;
+			}
+		};
+		self.quit_signal_producer = Some(quit_signal_producer);
+		self.device_event_consumer = Some(device_event_consumer);
+		Ok(sample_rate)
+	}
+
+	fn pop_device_event(&mut self) -> Option<DeviceEvent> {
+		self.device_event_consumer.as_mut()?.pop()
+	}
+
+	fn suspend(&mut self) {
+		self.suspended.store(true, Ordering::Relaxed);
+	}
+
+	fn resume(&mut self) {
+		self.suspended.store(false, Ordering::Relaxed);
+	}
+}
+
+#[cfg(target_arch = "wasm32")]
+impl OutputBackend for CpalOutput {
+	fn start(&mut self, backend: Backend) -> Result<u32, SetupError> {
+		let (device_event_producer, device_event_consumer) =
+			RingBuffer::new(DEVICE_EVENT_QUEUE_CAPACITY).split();
+		let stream_manager = StreamManager::start(
+			backend,
+			self.device.clone(),
+			self.sample_rate,
+			self.buffer_size,
+			device_event_producer,
+		)?;
+		let sample_rate = stream_manager.sample_rate();
+		self.stream_manager = Some(stream_manager);
+		self.device_event_consumer = Some(device_event_consumer);
+		Ok(sample_rate)
+	}
+
+	fn pop_device_event(&mut self) -> Option<DeviceEvent> {
+		if let Some(stream_manager) = &mut self.stream_manager {
+			stream_manager.update();
+		}
+		self.device_event_consumer.as_mut()?.pop()
+	}
+
+	fn suspend(&mut self) {
+		if let Some(stream_manager) = &mut self.stream_manager {
+			stream_manager.set_suspended(true);
+		}
+	}
+
+	fn resume(&mut self) {
+		if let Some(stream_manager) = &mut self.stream_manager {
+			stream_manager.set_suspended(false);
+		}
+	}
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for CpalOutput {
+	fn drop(&mut self) {
+		if let Some(quit_signal_producer) = &mut self.quit_signal_producer {
+			quit_signal_producer.push(true).ok();
+		}
+	}
+}
@@ -0,0 +1,136 @@
+use std::{
+	fs::File,
+	io::{stderr, BufWriter, Write},
+	path::PathBuf,
+};
+
+use hound::{SampleFormat, WavSpec, WavWriter};
+use ringbuf::{Consumer, Producer, RingBuffer};
+
+use crate::manager::{backend::Backend, error::SetupError, DeviceEvent};
+
+use super::{clocked::ClockedThread, OutputBackend};
+
+const DEFAULT_BUFFER_SIZE: usize = 512;
+
+/// Writes audio to a 32-bit float WAV file in real time.
+///
+/// The file has one channel for each speaker in the audio
+/// manager's [`ChannelLayout`](crate::channel_layout::ChannelLayout), and it's
+/// finalized when the audio manager is dropped. To render audio
+/// faster than real time, use an
+/// [`OfflineRenderer`](crate::manager::OfflineRenderer) instead.
+///
+/// If writing to the file fails, the output stops writing and
+/// reports a [`DeviceEvent::OutputFailed`].
+pub struct FileOutput {
+	path: PathBuf,
+	sample_rate: u32,
+	buffer_size: usize,
+	thread: Option<ClockedThread>,
+	device_event_consumer: Option<Consumer<DeviceEvent>>,
+}
+
+impl FileOutput {
+	/// Creates a new `FileOutput` that writes to the file at the
+	/// given path at the given sample rate.
+	pub fn new(path: impl Into<PathBuf>, sample_rate: u32) -> Self {
+		Self {
+			path: path.into(),
+			sample_rate,
+			buffer_size: DEFAULT_BUFFER_SIZE,
+			thread: None,
+			device_event_consumer: None,
+		}
+	}
+
+	/// Sets the number of frames to produce at a time.
+	pub fn buffer_size(self, buffer_size: usize) -> Self {
+		Self {
+			buffer_size,
+			..self
+		}
+	}
+}
+
+impl OutputBackend for FileOutput {
+	fn start(&mut self, backend: Backend) -> Result<u32, SetupError> {
+		let writer = WavWriter::create(
+			&self.path,
+			WavSpec {
+				channels: backend.channel_layout().num_channels() as u16,
+				sample_rate: self.sample_rate,
+				bits_per_sample: 32,
+				sample_format: SampleFormat::Float,
+			},
+		)?;
+		// the writer stops after the first error, so
+		// it only ever reports one event
+		let (device_event_producer, device_event_consumer) = RingBuffer::new(1).split();
+		let mut writer = FileWriter {
+			writer: Some(writer),
+			device_event_producer,
+		};
+		self.thread = Some(ClockedThread::start(
+			backend,
+			self.sample_rate,
+			self.buffer_size,
+			move |samples| writer.write(samples),
+		));
+		self.device_event_consumer = Some(device_event_consumer);
+		Ok(self.sample_rate)
+	}
+
+	fn pop_device_event(&mut self) -> Option<DeviceEvent> {
+		self.device_event_consumer.as_mut()?.pop()
+	}
+
+	fn suspend(&mut self) {
+		if let Some(thread) = &self.thread {
+			thread.set_suspended(true);
+		}
+	}
+
+	fn resume(&mut self) {
+		if let Some(thread) = &self.thread {
+			thread.set_suspended(false);
+		}
+	}
+}
+
+/// Writes samples to a WAV file and finalizes the file
+/// when dropped.
+struct FileWriter {
+	// set to `None` if writing fails
+	writer: Option<WavWriter<BufWriter<File>>>,
+	device_event_producer: Producer<DeviceEvent>,
+}
+
+impl FileWriter {
+	fn write(&mut self, samples: &[f32]) {
+		if let Some(writer) = &mut self.writer {
+			for sample in samples {
+				if let Err(error) = writer.write_sample(*sample) {
+					self.writer = None;
+					self.device_event_producer
+						.push(DeviceEvent::OutputFailed(error.to_string()))
+						.ok();
+					return;
+				}
+			}
+		}
+	}
+}
+
+impl Drop for FileWriter {
+	fn drop(&mut self) {
+		if let Some(writer) = self.writer.take() {
+			// the audio manager is being dropped, so there's
+			// no one left to report the error to
+			if let Err(error) = writer.finalize() {
+				// TODO: consider integrating with the log crate
+				writeln!(stderr(), "Kira failed to finalize the WAV file: {}", error).ok();
+			}
+		}
+	}
+}
@@ -0,0 +1,76 @@
+//! Destinations for the audio produced by an [`AudioManager`](super::AudioManager).
+//!
+//! By default, the audio manager sends audio to an audio device
+//! using [`CpalOutput`], but any [`OutputBackend`] can be passed to
+//! [`AudioManager::with_output`](super::AudioManager::with_output).
+
+#[cfg(not(target_arch = "wasm32"))]
+mod clocked;
+mod cpal_output;
+#[cfg(all(feature = "wav", not(target_arch = "wasm32")))]
+mod file;
+#[cfg(not(target_arch = "wasm32"))]
+mod null;
+
+pub use cpal_output::CpalOutput;
+#[cfg(all(feature = "wav", not(target_arch = "wasm32")))]
+pub use file::FileOutput;
+#[cfg(not(target_arch = "wasm32"))]
+pub use null::NullOutput;
+
+use super::{backend::Backend, error::SetupError, DeviceEvent};
+
+/// Implemented for every type that is `Send`.
+///
+/// On wasm, where audio streams can't be sent between
+/// threads, this is implemented for every type.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSend: Send {}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send> MaybeSend for T {}
+
+/// Implemented for every type that is `Send`.
+///
+/// On wasm, where audio streams can't be sent between
+/// threads, this is implemented for every type.
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSend {}
+
+#[cfg(target_arch = "wasm32")]
+impl<T> MaybeSend for T {}
+
+/// Something that pulls audio from a [`Backend`] and sends
+/// it somewhere, like an audio device or a file.
+///
+/// The output owns the backend (and usually an audio stream or
+/// thread that drives it) for as long as the audio manager is
+/// alive, and it should stop producing audio when it's dropped.
+pub trait OutputBackend: MaybeSend {
+	/// Takes ownership of the backend and starts producing audio.
+	///
+	/// Returns the sample rate the output runs at. The output is
+	/// responsible for calling [`Backend::set_sample_rate`] if this
+	/// differs from the sample rate the backend was created with.
+	fn start(&mut self, backend: Backend) -> Result<u32, SetupError>;
+
+	/// Returns the next event about the output device, if any.
+	///
+	/// This is called by [`AudioManager::pop_device_event`](super::AudioManager::pop_device_event).
+	fn pop_device_event(&mut self) -> Option<DeviceEvent> {
+		None
+	}
+
+	/// Stops sending audio to the output, for example by
+	/// pausing the audio stream.
+	///
+	/// This is called by [`AudioManager::suspend`](super::AudioManager::suspend)
+	/// after the backend has finished fading out. The backend
+	/// doesn't produce any audio while it's suspended, so it's fine
+	/// to keep pulling audio from it if the output can't be stopped.
+	fn suspend(&mut self) {}
+
+	/// Starts sending audio to the output again after
+	/// [`suspend`](Self::suspend).
+	fn resume(&mut self) {}
+}
@@ -0,0 +1,60 @@
+use crate::manager::{backend::Backend, error::SetupError};
+
+use super::{clocked::ClockedThread, OutputBackend};
+
+const DEFAULT_BUFFER_SIZE: usize = 512;
+
+/// Produces audio in real time without sending it anywhere.
+///
+/// This is useful for running audio logic, like sequences and
+/// metronomes, on machines with no sound card, such as
+/// dedicated game servers.
+pub struct NullOutput {
+	sample_rate: u32,
+	buffer_size: usize,
+	thread: Option<ClockedThread>,
+}
+
+impl NullOutput {
+	/// Creates a new `NullOutput` that runs at the given
+	/// sample rate.
+	pub fn new(sample_rate: u32) -> Self {
+		Self {
+			sample_rate,
+			buffer_size: DEFAULT_BUFFER_SIZE,
+			thread: None,
+		}
+	}
+
+	/// Sets the number of frames to produce at a time.
+	pub fn buffer_size(self, buffer_size: usize) -> Self {
+		Self {
+			buffer_size,
+			..self
+		}
+	}
+}
+
+impl OutputBackend for NullOutput {
+	fn start(&mut self, backend: Backend) -> Result<u32, SetupError> {
+		self.thread = Some(ClockedThread::start(
+			backend,
+			self.sample_rate,
+			self.buffer_size,
+			|_| {},
+		));
+		Ok(self.sample_rate)
+	}
+
+	fn suspend(&mut self) {
+		if let Some(thread) = &self.thread {
+			thread.set_suspended(true);
+		}
+	}
+
+	fn resume(&mut self) {
+		if let Some(thread) = &self.thread {
+			thread.set_suspended(false);
+		}
+	}
+}
@@ -0,0 +1,156 @@
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		mpsc::{channel, RecvTimeoutError, Sender},
+		Arc,
+	},
+	thread::JoinHandle,
+};
+
+use basedrop::{Collector, Handle};
+use instant::Instant;
+
+use crate::command::producer::CommandProducer;
+
+enum CollectorThread {
+	/// The collector only runs when the audio manager asks it to.
+	Manual(Collector),
+	/// The collector runs periodically on its own thread.
+	#[cfg(not(target_arch = "wasm32"))]
+	Background {
+		quit_sender: Sender<()>,
+		join_handle: JoinHandle<Collector>,
+		num_unfreed_resources: Arc<AtomicUsize>,
+	},
+}
+
+/// Frees resources that are no longer used by the audio thread,
+/// either when [`collect`](Self::collect) is called or on a
+/// background thread.
+///
+/// The background thread also sends commands that were held
+/// back because the command queue was full, so they don't wait
+/// for the next command to be sent.
+pub(crate) struct ResourceCollector {
+	handle: Handle,
+	thread: CollectorThread,
+}
+
+impl ResourceCollector {
+	/// Creates a resource collector. If `interval` is `Some`, the
+	/// resources are freed every `interval` seconds on a background
+	/// thread. On wasm, resources are always freed manually.
+	pub fn new(interval: Option<f64>, command_producer: CommandProducer) -> Self {
+		let collector = Collector::new();
+		let handle = collector.handle();
+		#[cfg(not(target_arch = "wasm32"))]
+		if let Some(interval) = interval {
+			return Self {
+				handle,
+				thread: Self::start_thread(
+					collector,
+					Duration::from_secs_f64(interval),
+					command_producer,
+				),
+			};
+		}
+		#[cfg(target_arch = "wasm32")]
+		let _ = (interval, command_producer);
+		Self {
+			handle,
+			thread: CollectorThread::Manual(collector),
+		}
+	}
+
+	#[cfg(not(target_arch = "wasm32"))]
+	fn start_thread(
+		mut collector: Collector,
+		interval: Duration,
+		mut command_producer: CommandProducer,
+	) -> CollectorThread {
+		let (quit_sender, quit_receiver) = channel();
+		let num_unfreed_resources = Arc::new(AtomicUsize::new(collector.alloc_count()));
+		let thread_num_unfreed_resources = num_unfreed_resources.clone();
+		let join_handle = std::thread::spawn(move || {
+			while let Err(RecvTimeoutError::Timeout) = quit_receiver.recv_timeout(interval) {
+				collector.collect();
+				command_producer.flush_spilled().ok();
+				thread_num_unfreed_resources.store(collector.alloc_count(), Ordering::Relaxed);
+			}
+			collector
+		});
+		CollectorThread::Background {
+			quit_sender,
+			join_handle,
+			num_unfreed_resources,
+		}
+	}
+
+	pub fn handle(&self) -> Handle {
+		self.handle.clone()
+	}
+
+	/// Frees unused resources now. This does nothing if the
+	/// resources are freed on a background thread.
+	pub fn collect(&mut self) {
+		if let CollectorThread::Manual(collector) = &mut self.thread {
+			collector.collect();
+		}
+	}
+
+	/// Returns the number of resources allocated through the
+	/// collector that haven't been freed yet, whether they're still
+	/// in use or waiting to be freed.
+	///
+	/// If the resources are freed on a background thread, this
+	/// is the number as of the last time they were freed.
+	pub fn num_unfreed_resources(&self) -> usize {
+		match &self.thread {
+			CollectorThread::Manual(collector) => collector.alloc_count(),
+			#[cfg(not(target_arch = "wasm32"))]
+			CollectorThread::Background {
+				num_unfreed_resources,
+				..
+			} => num_unfreed_resources.load(Ordering::Relaxed),
+		}
+	}
+
+	/// Stops the background thread (if there is one) and frees
+	/// every remaining resource.
+	///
+	/// If some resources are still in use after `timeout` has passed,
+	/// gives up and returns `Err` with the number of resources that
+	/// couldn't be freed.
+	pub fn cleanup(self, timeout: Duration) -> Result<(), usize> {
+		std::mem::drop(self.handle);
+		let collector = match self.thread {
+			CollectorThread::Manual(collector) => collector,
+			#[cfg(not(target_arch = "wasm32"))]
+			CollectorThread::Background {
+				quit_sender,
+				join_handle,
+				..
+			} => {
+				quit_sender.send(()).ok();
+				match join_handle.join() {
+					Ok(collector) => collector,
+					// the collector was lost with the thread, so
+					// there's nothing left to clean up
+					Err(_) => return Ok(()),
+				}
+			}
+		};
+		let mut collector = Err(collector);
+		let start_time = Instant::now();
+		while let Err(mut remaining) = collector {
+			if Instant::now() - start_time > timeout {
+				return Err(remaining.alloc_count());
+			}
+			remaining.collect();
+			collector = remaining.try_cleanup();
+		}
+		Ok(())
+	}
+}
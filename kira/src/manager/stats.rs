@@ -0,0 +1,193 @@
+//! Information about what the audio thread is doing.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+
+/// A snapshot of what the audio thread is doing, returned by
+/// [`AudioManager::stats`](super::AudioManager::stats).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AudioManagerStats {
+	/// How long the audio thread took to produce the most recent
+	/// buffer of audio, as a fraction of how long that audio
+	/// takes to play.
+	///
+	/// A value of 0.5 means the audio thread spent half of
+	/// the available time processing audio. Values above 1.0 mean
+	/// the audio thread couldn't keep up, which will cause
+	/// audio glitches.
+	pub cpu_load: f32,
+	/// The highest [`cpu_load`](Self::cpu_load) seen since the audio
+	/// manager was created.
+	pub peak_cpu_load: f32,
+	/// The number of times the audio thread took longer to produce
+	/// a buffer of audio than the audio takes to play.
+	///
+	/// This is measured by timing each buffer rather than reported
+	/// by the audio device, so it's only an estimate of how many
+	/// buffer underruns there were. The device may have enough audio
+	/// queued up to cover for a late buffer, and underruns caused by
+	/// something other than Kira taking too long aren't counted.
+	pub num_late_callbacks: u64,
+	/// The number of buffers of audio the audio thread has produced.
+	pub num_callbacks: u64,
+	/// The number of instances currently playing.
+	pub num_instances: usize,
+	/// The maximum number of instances that can be playing at a time.
+	pub instance_capacity: usize,
+	/// The number of sequence instances currently running.
+	pub num_sequences: usize,
+	/// The maximum number of sequence instances that can be running at a time.
+	pub sequence_capacity: usize,
+	/// The number of audio streams currently in use.
+	pub num_streams: usize,
+	/// The maximum number of audio streams that can be used at a time.
+	pub stream_capacity: usize,
+	/// The number of commands waiting to be received by the audio thread.
+	pub num_queued_commands: usize,
+	/// The number of commands being held until there's room
+	/// for them in the command queue.
+	///
+	/// This is only ever non-zero when using
+	/// [`CommandQueuePolicy::Spill`](crate::manager::CommandQueuePolicy::Spill).
+	pub num_spilled_commands: usize,
+	/// The maximum number of commands that can be waiting to be
+	/// received by the audio thread.
+	pub command_capacity: usize,
+	/// The number of commands the audio thread had to discard because
+	/// it had too many commands to process at once.
+	pub num_dropped_commands: u64,
+	/// The number of resources, such as sounds, instances, and
+	/// effects, that have been allocated and not freed yet.
+	///
+	/// This counts every resource that hasn't been freed: the ones
+	/// the audio thread is still using as well as the ones it's done
+	/// with that are waiting to be freed. It doesn't say how many are
+	/// waiting on their own, but if it keeps growing while the number
+	/// of resources in use stays the same, unused resources aren't
+	/// being freed often enough. See
+	/// [`AudioManagerSettings::resource_collection_interval`](super::AudioManagerSettings::resource_collection_interval).
+	///
+	/// If resources are freed on a background thread, this is the
+	/// number as of the last time the thread freed resources.
+	pub num_unfreed_resources: usize,
+}
+
+/// Statistics written by the audio thread and read by the
+/// audio manager.
+pub(crate) struct SharedStats {
+	cpu_load: AtomicU32,
+	peak_cpu_load: AtomicU32,
+	num_late_callbacks: AtomicU64,
+	num_callbacks: AtomicU64,
+	num_instances: AtomicUsize,
+	num_sequences: AtomicUsize,
+	num_streams: AtomicUsize,
+	num_dropped_commands: AtomicU64,
+	clock_time: AtomicU64,
+	suspended: AtomicBool,
+	sample_rate: AtomicU32,
+}
+
+impl SharedStats {
+	pub fn new(sample_rate: u32) -> Self {
+		Self {
+			cpu_load: AtomicU32::new(0.0f32.to_bits()),
+			peak_cpu_load: AtomicU32::new(0.0f32.to_bits()),
+			num_late_callbacks: AtomicU64::new(0),
+			num_callbacks: AtomicU64::new(0),
+			num_instances: AtomicUsize::new(0),
+			num_sequences: AtomicUsize::new(0),
+			num_streams: AtomicUsize::new(0),
+			num_dropped_commands: AtomicU64::new(0),
+			clock_time: AtomicU64::new(0),
+			suspended: AtomicBool::new(false),
+			sample_rate: AtomicU32::new(sample_rate),
+		}
+	}
+
+	/// Records how long it took to produce a buffer of audio,
+	/// as a fraction of how long the audio takes to play.
+	pub fn record_callback(&self, cpu_load: f32) {
+		self.cpu_load.store(cpu_load.to_bits(), Ordering::Relaxed);
+		let peak_cpu_load = f32::from_bits(self.peak_cpu_load.load(Ordering::Relaxed));
+		if cpu_load > peak_cpu_load {
+			self.peak_cpu_load
+				.store(cpu_load.to_bits(), Ordering::Relaxed);
+		}
+		// the buffer took longer to produce than it takes to play
+		if cpu_load > 1.0 {
+			self.num_late_callbacks.fetch_add(1, Ordering::Relaxed);
+		}
+		self.num_callbacks.fetch_add(1, Ordering::Relaxed);
+	}
+
+	pub fn set_resource_counts(
+		&self,
+		num_instances: usize,
+		num_sequences: usize,
+		num_streams: usize,
+	) {
+		self.num_instances.store(num_instances, Ordering::Relaxed);
+		self.num_sequences.store(num_sequences, Ordering::Relaxed);
+		self.num_streams.store(num_streams, Ordering::Relaxed);
+	}
+
+	pub fn record_dropped_command(&self) {
+		self.num_dropped_commands.fetch_add(1, Ordering::Relaxed);
+	}
+
+	pub fn set_clock_time(&self, clock_time: u64) {
+		self.clock_time.store(clock_time, Ordering::Relaxed);
+	}
+
+	pub fn set_suspended(&self, suspended: bool) {
+		self.suspended.store(suspended, Ordering::Relaxed);
+	}
+
+	pub fn set_sample_rate(&self, sample_rate: u32) {
+		self.sample_rate.store(sample_rate, Ordering::Relaxed);
+	}
+
+	pub fn cpu_load(&self) -> f32 {
+		f32::from_bits(self.cpu_load.load(Ordering::Relaxed))
+	}
+
+	pub fn peak_cpu_load(&self) -> f32 {
+		f32::from_bits(self.peak_cpu_load.load(Ordering::Relaxed))
+	}
+
+	pub fn num_late_callbacks(&self) -> u64 {
+		self.num_late_callbacks.load(Ordering::Relaxed)
+	}
+
+	pub fn num_callbacks(&self) -> u64 {
+		self.num_callbacks.load(Ordering::Relaxed)
+	}
+
+	pub fn num_instances(&self) -> usize {
+		self.num_instances.load(Ordering::Relaxed)
+	}
+
+	pub fn num_sequences(&self) -> usize {
+		self.num_sequences.load(Ordering::Relaxed)
+	}
+
+	pub fn num_streams(&self) -> usize {
+		self.num_streams.load(Ordering::Relaxed)
+	}
+
+	pub fn num_dropped_commands(&self) -> u64 {
+		self.num_dropped_commands.load(Ordering::Relaxed)
+	}
+
+	pub fn clock_time(&self) -> u64 {
+		self.clock_time.load(Ordering::Relaxed)
+	}
+
+	pub fn suspended(&self) -> bool {
+		self.suspended.load(Ordering::Relaxed)
+	}
+
+	pub fn sample_rate(&self) -> u32 {
+		self.sample_rate.load(Ordering::Relaxed)
+	}
+}
@@ -0,0 +1,192 @@
+use std::sync::{Arc, Mutex};
+
+use cpal::{
+	traits::{DeviceTrait, StreamTrait},
+	Device, Stream, StreamConfig, StreamError,
+};
+use ringbuf::{Consumer, Producer, RingBuffer};
+
+use super::{
+	backend::Backend,
+	device::{self, DeviceEvent},
+	error::SetupError,
+};
+
+/// How many stream errors can be queued up between
+/// calls to [`StreamManager::update`].
+const STREAM_ERROR_QUEUE_CAPACITY: usize = 10;
+
+/// Owns the cpal stream and rebuilds it if the audio
+/// output device stops working.
+///
+/// The backend is shared between the stream manager and
+/// the stream's data callback so that it can outlive any
+/// individual stream.
+pub(super) struct StreamManager {
+	backend: Arc<Mutex<Backend>>,
+	requested_device: Option<String>,
+	buffer_size: Option<u32>,
+	// the number of channels to request from the device
+	channels: u16,
+	sample_rate: u32,
+	stream: Option<Stream>,
+	// whether the stream should be paused
+	suspended: bool,
+	stream_error_consumer: Consumer<StreamError>,
+	device_event_producer: Producer<DeviceEvent>,
+}
+
+impl StreamManager {
+	/// Starts a stream on the requested output device (or the
+	/// default device if none was requested).
+	pub fn start(
+		mut backend: Backend,
+		requested_device: Option<String>,
+		sample_rate: Option<u32>,
+		buffer_size: Option<u32>,
+		device_event_producer: Producer<DeviceEvent>,
+	) -> Result<Self, SetupError> {
+		let device = device::find_output_device(requested_device.as_deref())?;
+		let channels = backend.channel_layout().num_channels() as u16;
+		let config = device::stream_config(&device, sample_rate, buffer_size, channels)?;
+		backend.set_sample_rate(config.sample_rate.0);
+		let backend = Arc::new(Mutex::new(backend));
+		let (stream, stream_error_consumer) = build_stream(&device, &config, backend.clone())?;
+		Ok(Self {
+			backend,
+			requested_device,
+			buffer_size,
+			channels,
+			sample_rate: config.sample_rate.0,
+			stream: Some(stream),
+			suspended: false,
+			stream_error_consumer,
+			device_event_producer,
+		})
+	}
+
+	/// Returns the sample rate of the current stream.
+	pub fn sample_rate(&self) -> u32 {
+		self.sample_rate
+	}
+
+	/// Pauses or resumes the stream.
+	pub fn set_suspended(&mut self, suspended: bool) {
+		if suspended == self.suspended {
+			return;
+		}
+		self.suspended = suspended;
+		if let Some(stream) = &self.stream {
+			// if the stream can't be paused, the backend will
+			// still output silence while it's suspended
+			if suspended {
+				stream.pause().ok();
+			} else {
+				stream.play().ok();
+			}
+		}
+	}
+
+	/// Checks if the stream has stopped working and tries to
+	/// create a new one if so.
+	pub fn update(&mut self) {
+		let stream_failed = drain_stream_errors(&mut self.stream_error_consumer);
+		if !stream_failed && self.stream.is_some() {
+			return;
+		}
+		let had_stream = self.stream.take().is_some();
+		let result = self.rebuild_stream().map(|name| (name, self.sample_rate));
+		if let Some(event) = rebuild_device_event(had_stream, result) {
+			self.device_event_producer.push(event).ok();
+		}
+	}
+
+	/// Builds a new stream on the requested device, falling back to
+	/// the default device, and returns the name of the device used.
+	///
+	/// The previous sample rate is kept if the new device supports it.
+	fn rebuild_stream(&mut self) -> Result<String, SetupError> {
+		let channels = self.channels;
+		let device = match self.requested_device.as_deref() {
+			Some(name) => device::find_output_device(Some(name))
+				.or_else(|_| device::find_output_device(None))?,
+			None => device::find_output_device(None)?,
+		};
+		let config =
+			device::stream_config(&device, Some(self.sample_rate), self.buffer_size, channels)
+				.or_else(|_| device::stream_config(&device, None, self.buffer_size, channels))?;
+		let (stream, stream_error_consumer) = build_stream(&device, &config, self.backend.clone())?;
+		if self.suspended {
+			stream.pause().ok();
+		}
+		if config.sample_rate.0 != self.sample_rate {
+			if let Ok(mut backend) = self.backend.lock() {
+				backend.set_sample_rate(config.sample_rate.0);
+			}
+			self.sample_rate = config.sample_rate.0;
+		}
+		self.stream = Some(stream);
+		self.stream_error_consumer = stream_error_consumer;
+		Ok(device.name().unwrap_or_default())
+	}
+}
+
+/// Pops all of the errors reported by a stream and returns
+/// `true` if there were any.
+///
+/// Any error is treated as a sign that the stream stopped
+/// working. Depending on the platform, a disconnected device
+/// may be reported as [`StreamError::DeviceNotAvailable`] or
+/// as a backend-specific error.
+pub(super) fn drain_stream_errors(stream_error_consumer: &mut Consumer<StreamError>) -> bool {
+	let mut stream_failed = false;
+	while stream_error_consumer.pop().is_some() {
+		stream_failed = true;
+	}
+	stream_failed
+}
+
+/// Returns the event to report after trying to rebuild the
+/// stream, given the name and sample rate of the new device
+/// if it succeeded.
+///
+/// A lost device is only reported once, when the old stream
+/// stops working. After that, the stream manager keeps trying
+/// to rebuild the stream quietly.
+pub(super) fn rebuild_device_event(
+	had_stream: bool,
+	result: Result<(String, u32), SetupError>,
+) -> Option<DeviceEvent> {
+	match result {
+		Ok((name, sample_rate)) => Some(DeviceEvent::DeviceChanged { name, sample_rate }),
+		Err(_) if had_stream => Some(DeviceEvent::DeviceLost),
+		Err(_) => None,
+	}
+}
+
+fn build_stream(
+	device: &Device,
+	config: &StreamConfig,
+	backend: Arc<Mutex<Backend>>,
+) -> Result<(Stream, Consumer<StreamError>), SetupError> {
+	let channels = config.channels as usize;
+	let (mut stream_error_producer, stream_error_consumer) =
+		RingBuffer::new(STREAM_ERROR_QUEUE_CAPACITY).split();
+	let stream = device.build_output_stream(
+		config,
+		move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+			// the backend is only locked elsewhere while the stream
+			// is being rebuilt, in which case we output silence
+			// rather than waiting
+			match backend.try_lock() {
+				Ok(mut backend) => backend.process_into(data, channels),
+				Err(_) => data.iter_mut().for_each(|sample| *sample = 0.0),
+			}
+		},
+		move |error| {
+			stream_error_producer.push(error).ok();
+		},
+	)?;
+	stream.play()?;
+	Ok((stream, stream_error_consumer))
+}
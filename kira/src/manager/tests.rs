@@ -1,11 +1,37 @@
-use crate::{arrangement::Arrangement, sound::Sound};
+use std::sync::{
+	atomic::{AtomicBool, Ordering},
+	Arc, Mutex,
+};
+
+use ringbuf::RingBuffer;
+
+use crate::{
+	arrangement::{Arrangement, SoundClip},
+	audio_stream::AudioStream,
+	channel_layout::ChannelLayout,
+	command::producer::CommandError,
+	group::{GroupId, GroupSet, GroupSettings},
+	instance::{InstanceSettings, StopInstanceSettings},
+	mixer::{effect::Effect, SubTrackSettings, TrackIndex},
+	parameter::{tween::Tween, Parameter, ParameterSettings, Parameters},
+	sequence::{Sequence, SequenceInstanceSettings, SequenceSettings},
+	sound::{InterpolationMode, Sound, SoundSettings},
+	static_container::slot_map::{SlotIndex, SlotKey},
+	Duration, Frame,
+};
 
 use super::{
+	backend::Backend,
+	device::{choose_output_config, ChosenOutputConfig},
 	error::{
 		AddArrangementError, AddGroupError, AddMetronomeError, AddParameterError,
-		AddSendTrackError, AddSoundError, AddSubTrackError,
+		AddSendTrackError, AddSoundError, AddSubTrackError, RemoveParameterError,
+		RemoveStreamError, SetupError, StartSequenceError,
 	},
-	AudioManager, AudioManagerSettings,
+	output::{NullOutput, OutputBackend},
+	stream_manager::{drain_stream_errors, rebuild_device_event},
+	AudioManager, AudioManagerSettings, CommandQueuePolicy, DeviceEvent, Diagnostic, ResourceId,
+	SupportedOutputConfig,
 };
 
 fn create_manager_with_limited_capacity() -> AudioManager {
@@ -102,3 +128,1260 @@ fn returns_error_on_exceeded_metronome_capacity() {
 }
 
 // TODO: write a test for exceeded stream capacity
+
+#[test]
+fn renders_audio_offline() {
+	let (mut manager, mut renderer) = AudioManager::new_offline(Default::default(), 100);
+	assert_eq!(renderer.render_seconds(0.5).len(), 50);
+	let mut sound_handle = manager
+		.add_sound(Sound::from_frames(
+			100,
+			vec![Frame::from_mono(1.0); 100],
+			SoundSettings::new(),
+		))
+		.unwrap();
+	sound_handle
+		.play(InstanceSettings::new().panning(0.0))
+		.unwrap();
+	let frames = renderer.render(200);
+	// the instance should be audible in the left channel until the
+	// sound finishes, and then the output should be silent
+	assert!(frames[1..99].iter().all(|frame| frame.left == 1.0));
+	assert!(frames[1..99].iter().all(|frame| frame.right == 0.0));
+	assert!(frames[101..]
+		.iter()
+		.all(|frame| *frame == Frame::from_mono(0.0)));
+}
+
+#[test]
+fn reports_stats() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		num_commands: 2,
+		num_instances: 10,
+		..Default::default()
+	});
+	let mut sound_handle = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			vec![Frame::from_mono(1.0); 100],
+			SoundSettings::new(),
+		))
+		.unwrap();
+	sound_handle.play(InstanceSettings::new()).unwrap();
+	let stats = manager.stats();
+	assert_eq!(stats.num_queued_commands, 2);
+	assert_eq!(stats.command_capacity, 2);
+	backend.process();
+	let stats = manager.stats();
+	assert_eq!(stats.num_callbacks, 1);
+	assert_eq!(stats.num_queued_commands, 0);
+	assert_eq!(stats.num_instances, 1);
+	assert_eq!(stats.instance_capacity, 10);
+	assert_eq!(stats.num_dropped_commands, 0);
+}
+
+#[test]
+fn reports_diagnostics() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		num_instances: 1,
+		..Default::default()
+	});
+	let mut sound_handle = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			vec![Frame::from_mono(1.0); 100],
+			SoundSettings::new().cooldown(0.0),
+		))
+		.unwrap();
+	let mut first_instance = sound_handle.play(InstanceSettings::new()).unwrap();
+	sound_handle.play(InstanceSettings::new()).unwrap();
+	backend.process();
+	assert_eq!(
+		manager.pop_diagnostic(),
+		Some(Diagnostic::InstanceLimitReached {
+			stopped: first_instance.id()
+		})
+	);
+	assert_eq!(manager.pop_diagnostic(), None);
+	first_instance.stop(StopInstanceSettings::new()).unwrap();
+	backend.process();
+	assert_eq!(
+		manager.pop_diagnostic(),
+		Some(Diagnostic::ResourceNotFound(ResourceId::Instance(
+			first_instance.id()
+		)))
+	);
+}
+
+#[test]
+fn reuses_instance_slots_with_new_ids() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		num_instances: 1,
+		..Default::default()
+	});
+	let mut sound_handle = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			vec![Frame::from_mono(1.0); 10],
+			SoundSettings::new().cooldown(0.0),
+		))
+		.unwrap();
+	let mut first_instance = sound_handle.play(InstanceSettings::new()).unwrap();
+	// let the first instance finish so its slot is freed
+	backend.process_block(&mut [Frame::from_mono(0.0); 20]);
+	assert_eq!(manager.stats().num_instances, 0);
+	let second_instance = sound_handle.play(InstanceSettings::new()).unwrap();
+	backend.process();
+	assert_eq!(
+		second_instance.id().slot_index().index,
+		first_instance.id().slot_index().index
+	);
+	assert_ne!(second_instance.id(), first_instance.id());
+	assert_eq!(manager.pop_diagnostic(), None);
+	// the old ID shouldn't refer to the new instance
+	first_instance.stop(StopInstanceSettings::new()).unwrap();
+	backend.process();
+	assert_eq!(
+		manager.pop_diagnostic(),
+		Some(Diagnostic::ResourceNotFound(ResourceId::Instance(
+			first_instance.id()
+		)))
+	);
+	assert_eq!(manager.stats().num_instances, 1);
+}
+
+#[test]
+fn looping_sequences_free_the_slots_of_their_instances() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		num_instances: 1,
+		..Default::default()
+	});
+	let sound_handle = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			vec![Frame::from_mono(1.0); 100],
+			SoundSettings::new().cooldown(0.0),
+		))
+		.unwrap();
+	let mut sequence = Sequence::<()>::new(SequenceSettings::new());
+	sequence.start_loop();
+	let id = sequence.play(&sound_handle, InstanceSettings::new());
+	sequence.wait(Duration::Seconds(5.0 / 48000.0));
+	sequence.stop_instance(id, StopInstanceSettings::new().fade_tween(None));
+	sequence.wait(Duration::Seconds(5.0 / 48000.0));
+	manager
+		.start_sequence(sequence, SequenceInstanceSettings::new())
+		.unwrap();
+	// each loop stops the instance it started, so the one slot
+	// should always be free by the time the next loop starts
+	let mut frames = [Frame::from_mono(0.0); 200];
+	backend.process_block(&mut frames);
+	assert_eq!(manager.pop_diagnostic(), None);
+	assert!(frames[150..].iter().any(|frame| frame.left > 0.0));
+	assert!(frames[150..].iter().any(|frame| frame.left == 0.0));
+}
+
+#[test]
+fn queues_sequence_commands_separately() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		num_commands: 1,
+		num_sequence_commands: 10,
+		num_instances: 10,
+		..Default::default()
+	});
+	let sound_handle = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			vec![Frame::from_mono(1.0); 100],
+			SoundSettings::new().cooldown(0.0),
+		))
+		.unwrap();
+	backend.process();
+	let mut sequence = Sequence::<()>::new(SequenceSettings::new());
+	for _ in 0..5 {
+		sequence.play(&sound_handle, InstanceSettings::new());
+	}
+	manager
+		.start_sequence(sequence, SequenceInstanceSettings::new())
+		.unwrap();
+	backend.process();
+	// the sequence's commands shouldn't take up room in the queue
+	// for commands sent from the audio manager
+	manager.add_parameter(Default::default()).unwrap();
+	backend.process();
+	assert_eq!(manager.pop_diagnostic(), None);
+	let stats = manager.stats();
+	assert_eq!(stats.num_instances, 5);
+	assert_eq!(stats.num_dropped_commands, 0);
+}
+
+#[test]
+fn runs_scheduled_commands_on_the_right_frame() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut sound_handle = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			vec![Frame::from_mono(1.0); 100],
+			SoundSettings::new(),
+		))
+		.unwrap();
+	backend.process();
+	assert_eq!(manager.clock_time(), 1);
+	let mut instance_handle = sound_handle.play_at(InstanceSettings::new(), 5).unwrap();
+	instance_handle
+		.stop_at(StopInstanceSettings::new().fade_tween(None), 8)
+		.unwrap();
+	let mut frames = [Frame::from_mono(0.0); 10];
+	backend.process_block(&mut frames);
+	assert_eq!(manager.clock_time(), 11);
+	for (i, frame) in frames.iter().enumerate() {
+		// the first frame of the block is frame 1 on the audio clock
+		let clock_time = i + 1;
+		if (5..8).contains(&clock_time) {
+			assert!(frame.left > 0.0);
+		} else {
+			assert_eq!(*frame, Frame::from_mono(0.0));
+		}
+	}
+}
+
+#[test]
+fn runs_scheduled_commands_in_order() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut parameter_handle = manager
+		.add_parameter(ParameterSettings::new().value(1.0))
+		.unwrap();
+	let mut sound_handle = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			vec![Frame::from_mono(1.0); 100],
+			SoundSettings::new(),
+		))
+		.unwrap();
+	sound_handle
+		.play(InstanceSettings::new().volume(parameter_handle.id()))
+		.unwrap();
+	let mut frames = [Frame::from_mono(0.0); 2];
+	backend.process_block(&mut frames);
+	assert_eq!(manager.clock_time(), 2);
+	let full_volume = frames[1].left;
+	assert!(full_volume > 0.0);
+	// commands scheduled for the same time should run in the
+	// order they were sent, even if they were sent after
+	// commands scheduled for earlier times
+	parameter_handle.set_at(0.25, None, 6).unwrap();
+	parameter_handle.set_at(0.0, None, 4).unwrap();
+	parameter_handle.set_at(0.5, None, 6).unwrap();
+	let mut frames = [Frame::from_mono(0.0); 6];
+	backend.process_block(&mut frames);
+	let volumes: Vec<f32> = frames
+		.iter()
+		.map(|frame| frame.left / full_volume)
+		.collect();
+	// the first frame of the block is frame 2 on the audio clock,
+	// and instances pick up parameter changes on the frame after
+	// they happen
+	assert_eq!(volumes, [1.0, 1.0, 1.0, 0.0, 0.0, 0.5]);
+}
+
+#[test]
+fn applies_batches_all_at_once() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		num_commands: 2,
+		..Default::default()
+	});
+	let mut sound_handle = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			vec![Frame::from_mono(1.0); 100],
+			SoundSettings::new().cooldown(0.0),
+		))
+		.unwrap();
+	backend.process();
+	// a batch that's too large for the command queue shouldn't
+	// be applied at all
+	let result = manager.batch(|| {
+		for _ in 0..3 {
+			sound_handle.play(InstanceSettings::new()).unwrap();
+		}
+	});
+	assert!(matches!(result, Err(CommandError::CommandQueueFull)));
+	backend.process();
+	assert_eq!(manager.stats().num_instances, 0);
+	// a batch only takes up one slot in the command queue
+	manager
+		.batch(|| {
+			for _ in 0..2 {
+				sound_handle.play(InstanceSettings::new()).unwrap();
+			}
+		})
+		.unwrap();
+	assert_eq!(manager.stats().num_queued_commands, 1);
+	backend.process();
+	assert_eq!(manager.stats().num_instances, 2);
+	assert_eq!(manager.pop_diagnostic(), None);
+}
+
+#[test]
+fn discards_unfinished_batches() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut sound_handle = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			vec![Frame::from_mono(1.0); 100],
+			SoundSettings::new().cooldown(0.0),
+		))
+		.unwrap();
+	backend.process();
+	// if the function recording a batch panics, the batch
+	// should be thrown away...
+	let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+		manager.batch(|| {
+			sound_handle.play(InstanceSettings::new()).unwrap();
+			panic!("something went wrong");
+		})
+	}));
+	assert!(result.is_err());
+	// ...and later commands should be sent as usual
+	sound_handle.play(InstanceSettings::new()).unwrap();
+	assert_eq!(manager.stats().num_queued_commands, 1);
+	backend.process();
+	assert_eq!(manager.stats().num_instances, 1);
+	// only one batch can be recorded at a time
+	let batch = manager.command_producer.start_batch().unwrap();
+	assert!(matches!(
+		manager.command_producer.start_batch(),
+		Err(CommandError::BatchInProgress)
+	));
+	drop(batch);
+	assert!(manager.command_producer.start_batch().is_ok());
+}
+
+#[test]
+fn grows_pools_when_enabled() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		num_sounds: 1,
+		num_instances: 1,
+		growable_pools: true,
+		..Default::default()
+	});
+	let sound = Sound::from_frames(
+		48000,
+		vec![Frame::from_mono(1.0); 100],
+		SoundSettings::new().cooldown(0.0),
+	);
+	let mut first_sound_handle = manager.add_sound(sound.clone()).unwrap();
+	let mut second_sound_handle = manager.add_sound(sound).unwrap();
+	backend.process();
+	first_sound_handle.play(InstanceSettings::new()).unwrap();
+	backend.process();
+	assert_eq!(manager.stats().instance_capacity, 1);
+	manager.free_unused_resources();
+	assert_eq!(manager.stats().instance_capacity, 2);
+	second_sound_handle.play(InstanceSettings::new()).unwrap();
+	backend.process();
+	// both sounds should still be loaded, and the first instance
+	// shouldn't have been stopped to make room for the second one
+	assert_eq!(manager.pop_diagnostic(), None);
+	assert_eq!(manager.stats().num_instances, 2);
+}
+
+#[test]
+fn grows_pools_to_fit_requested_ids() {
+	let id = GroupId::from_slot_index(SlotIndex {
+		index: 4,
+		generation: 0,
+	});
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		num_groups: 1,
+		growable_pools: true,
+		..Default::default()
+	});
+	manager.add_group(GroupSettings::new().id(id)).unwrap();
+	backend.process();
+	assert_eq!(manager.pop_diagnostic(), None);
+	// pools that can't grow still reject IDs that don't fit
+	let mut manager = create_manager_with_limited_capacity();
+	assert!(matches!(
+		manager.add_group(GroupSettings::new().id(id)),
+		Err(AddGroupError::GroupLimitReached)
+	));
+}
+
+#[test]
+fn runs_the_backend_on_a_custom_output() {
+	let mut manager = AudioManager::with_output(
+		AudioManagerSettings::default(),
+		NullOutput::new(44100).buffer_size(64),
+	)
+	.unwrap();
+	assert_eq!(manager.sample_rate(), 44100);
+	// the null output should produce audio in real time,
+	// so the audio clock should start advancing
+	let start_time = std::time::Instant::now();
+	while manager.clock_time() == 0 {
+		assert!(start_time.elapsed() < std::time::Duration::from_secs(1));
+		std::thread::sleep(std::time::Duration::from_millis(1));
+	}
+	assert_eq!(manager.pop_device_event(), None);
+	std::mem::drop(manager.output.take());
+}
+
+#[test]
+fn fills_interleaved_buffers_for_a_host_callback() {
+	let (mut manager, mut backend) =
+		AudioManager::new_with_backend(AudioManagerSettings::default(), 44100);
+	assert_eq!(manager.sample_rate(), 44100);
+	let mut sound_handle = manager
+		.add_sound(Sound::from_frames(
+			44100,
+			vec![Frame::from_mono(1.0); 100],
+			SoundSettings::new(),
+		))
+		.unwrap();
+	backend.process();
+	sound_handle.play(InstanceSettings::new()).unwrap();
+	// the buffer doesn't hold a whole number of stereo frames,
+	// so the last sample should be cleared
+	let mut buffer = [1.0; 7];
+	backend.process_into(&mut buffer, 2);
+	assert_eq!(manager.clock_time(), 4);
+	for sample in &buffer[..6] {
+		assert!(*sample > 0.0);
+	}
+	assert_eq!(buffer[6], 0.0);
+	// nothing should be produced for an output with no channels
+	backend.process_into(&mut buffer, 0);
+	assert_eq!(manager.clock_time(), 4);
+}
+
+#[test]
+fn places_instances_in_surround_layouts() {
+	let (mut manager, mut backend) = AudioManager::new_with_backend(
+		AudioManagerSettings {
+			channel_layout: ChannelLayout::Quad,
+			..Default::default()
+		},
+		44100,
+	);
+	let mut sound_handle = manager
+		.add_sound(Sound::from_frames(
+			44100,
+			vec![Frame::from_mono(1.0); 100],
+			SoundSettings::new(),
+		))
+		.unwrap();
+	backend.process();
+	// directly on the back left speaker
+	sound_handle
+		.play(InstanceSettings::new().surround_angle(-135.0))
+		.unwrap();
+	let mut buffer = [0.0; 8];
+	backend.process_into(&mut buffer, 4);
+	// by the second frame, the sound should only be coming
+	// out of the back left speaker
+	assert_eq!(&buffer[4..], &[0.0, 0.0, 1.0, 0.0]);
+}
+
+#[test]
+fn suspends_and_resumes_without_losing_state() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut sound_handle = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			vec![Frame::from_mono(1.0); 1000],
+			SoundSettings::new(),
+		))
+		.unwrap();
+	backend.process();
+	let instance_handle = sound_handle.play(InstanceSettings::new()).unwrap();
+	backend.process_block(&mut [Frame::from_mono(0.0); 10]);
+	let position = instance_handle.position();
+	manager.suspend(Tween::linear(4.0 / 48000.0)).unwrap();
+	// the output should fade out, and then the backend should
+	// stop producing audio and advancing the clock
+	let mut frames = [Frame::from_mono(0.0); 10];
+	backend.process_block(&mut frames);
+	assert!(frames[0].left > frames[2].left);
+	assert_eq!(frames[9], Frame::from_mono(0.0));
+	let clock_time = manager.clock_time();
+	backend.process_block(&mut frames);
+	assert_eq!(manager.clock_time(), clock_time);
+	assert_eq!(frames, [Frame::from_mono(0.0); 10]);
+	assert_eq!(manager.stats().num_instances, 1);
+	let suspended_position = instance_handle.position();
+	assert!(suspended_position > position);
+	manager.resume(None).unwrap();
+	backend.process_block(&mut frames);
+	assert!(frames.iter().all(|frame| frame.left > 0.0));
+	assert!(manager.clock_time() > clock_time);
+	assert!(instance_handle.position() > suspended_position);
+}
+
+#[test]
+fn pauses_everything_except_unpausable_groups_and_tracks() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let ui_group = manager
+		.add_group(GroupSettings::new().unpausable())
+		.unwrap();
+	let ui_track = manager
+		.add_sub_track(SubTrackSettings::new().unpausable())
+		.unwrap();
+	let frames = vec![Frame::from_mono(1.0); 1000];
+	let mut game_sound = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			frames.clone(),
+			SoundSettings::new(),
+		))
+		.unwrap();
+	let mut ui_group_sound = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			frames.clone(),
+			SoundSettings::new().groups(GroupSet::new().add(&ui_group)),
+		))
+		.unwrap();
+	let mut ui_track_sound = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			frames,
+			SoundSettings::new().default_track(&ui_track),
+		))
+		.unwrap();
+	backend.process();
+	let game_instance = game_sound.play(InstanceSettings::new()).unwrap();
+	let ui_group_instance = ui_group_sound.play(InstanceSettings::new()).unwrap();
+	let ui_track_instance = ui_track_sound.play(InstanceSettings::new()).unwrap();
+	backend.process();
+	manager.set_paused(true).unwrap();
+	assert!(manager.is_paused());
+	backend.process();
+	let game_position = game_instance.position();
+	let ui_group_position = ui_group_instance.position();
+	let ui_track_position = ui_track_instance.position();
+	backend.process_block(&mut [Frame::from_mono(0.0); 10]);
+	assert_eq!(game_instance.position(), game_position);
+	assert!(ui_group_instance.position() > ui_group_position);
+	assert!(ui_track_instance.position() > ui_track_position);
+	manager.set_paused(false).unwrap();
+	backend.process_block(&mut [Frame::from_mono(0.0); 10]);
+	assert!(game_instance.position() > game_position);
+}
+
+fn render_random_audio(seed: u64) -> Vec<Frame> {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		seed: Some(seed),
+		..Default::default()
+	});
+	let quiet_sound = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			vec![Frame::from_mono(0.25); 10],
+			SoundSettings::new().cooldown(0.0),
+		))
+		.unwrap();
+	let mut loud_sound = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			vec![Frame::from_mono(0.75); 10],
+			SoundSettings::new().cooldown(0.0),
+		))
+		.unwrap();
+	backend.process();
+	loud_sound
+		.play(InstanceSettings::new().volume(0.5..1.0))
+		.unwrap();
+	let mut sequence = Sequence::<()>::new(SequenceSettings::new());
+	sequence.wait(crate::Duration::Seconds(10.0 / 48000.0));
+	sequence.start_loop();
+	sequence.play_random(
+		vec![quiet_sound.id().into(), loud_sound.id().into()],
+		InstanceSettings::new(),
+	);
+	sequence.wait(crate::Duration::Seconds(10.0 / 48000.0));
+	manager
+		.start_sequence(sequence, SequenceInstanceSettings::new())
+		.unwrap();
+	let mut frames = vec![Frame::from_mono(0.0); 500];
+	backend.process_block(&mut frames);
+	frames
+}
+
+#[test]
+fn makes_the_same_random_decisions_with_the_same_seed() {
+	assert_eq!(render_random_audio(1), render_random_audio(1));
+	assert_ne!(render_random_audio(1), render_random_audio(2));
+}
+
+#[test]
+fn bypasses_panicking_effects_and_removes_panicking_streams() {
+	#[derive(Debug)]
+	struct PanickingEffect;
+
+	impl Effect for PanickingEffect {
+		fn process(&mut self, _dt: f64, _input: Frame, _parameters: &Parameters) -> Frame {
+			panic!("effect panicked");
+		}
+	}
+
+	#[derive(Debug)]
+	struct ConstantStream(Option<Frame>);
+
+	impl AudioStream for ConstantStream {
+		fn next(&mut self, _dt: f64) -> Frame {
+			self.0.expect("stream panicked")
+		}
+	}
+
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut sub_track = manager.add_sub_track(SubTrackSettings::new()).unwrap();
+	let effect = sub_track
+		.add_effect(PanickingEffect, Default::default())
+		.unwrap();
+	manager
+		.add_stream(
+			ConstantStream(Some(Frame::from_mono(1.0))),
+			sub_track.id().into(),
+		)
+		.unwrap();
+	let panicking_stream_id = manager
+		.add_stream(ConstantStream(None), TrackIndex::Main)
+		.unwrap();
+	backend.process();
+	let mut frames = [Frame::from_mono(0.0); 10];
+	backend.process_block(&mut frames);
+	// the healthy stream should still be heard through the bypassed effect
+	assert!(frames.iter().all(|frame| frame.left > 0.0));
+	backend.process_block(&mut frames);
+	assert!(frames.iter().all(|frame| frame.left > 0.0));
+	assert_eq!(manager.stats().num_streams, 1);
+	let mut diagnostics = vec![];
+	while let Some(diagnostic) = manager.pop_diagnostic() {
+		diagnostics.push(diagnostic);
+	}
+	assert_eq!(
+		diagnostics,
+		vec![
+			Diagnostic::StreamPanicked(panicking_stream_id),
+			Diagnostic::EffectPanicked(effect.id()),
+		]
+	);
+	// the panicked stream's ID has already been freed
+	assert!(matches!(
+		manager.remove_stream(panicking_stream_id),
+		Err(RemoveStreamError::NoStreamWithId(_))
+	));
+}
+
+#[test]
+fn frees_panicked_stream_ids_without_popping_diagnostics() {
+	#[derive(Debug)]
+	struct PanickingStream;
+
+	impl AudioStream for PanickingStream {
+		fn next(&mut self, _dt: f64) -> Frame {
+			panic!("stream panicked");
+		}
+	}
+
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		num_streams: 1,
+		growable_pools: false,
+		..Default::default()
+	});
+	manager
+		.add_stream(PanickingStream, TrackIndex::Main)
+		.unwrap();
+	backend.process();
+	backend.process();
+	assert_eq!(manager.stats().num_streams, 0);
+	// the only stream slot is free again even though the
+	// diagnostic is still waiting to be popped
+	manager
+		.add_stream(PanickingStream, TrackIndex::Main)
+		.unwrap();
+}
+
+#[test]
+fn frees_unused_resources_on_a_background_thread() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		resource_collection_interval: Some(0.001),
+		..Default::default()
+	});
+	let sound_handle = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			vec![Frame::from_mono(1.0); 100],
+			SoundSettings::new(),
+		))
+		.unwrap();
+	backend.process();
+	let wait_for_unfreed_resources = |manager: &AudioManager, condition: &dyn Fn(usize) -> bool| {
+		let start_time = std::time::Instant::now();
+		while !condition(manager.stats().num_unfreed_resources) {
+			assert!(start_time.elapsed() < std::time::Duration::from_secs(1));
+			std::thread::sleep(std::time::Duration::from_millis(1));
+		}
+		manager.stats().num_unfreed_resources
+	};
+	// the count is updated each time the collector runs
+	let num_unfreed_resources = wait_for_unfreed_resources(&manager, &|n| n > 0);
+	manager.remove_sound(sound_handle.id()).unwrap();
+	backend.process();
+	// the sound should be freed without calling free_unused_resources
+	wait_for_unfreed_resources(&manager, &|n| n < num_unfreed_resources);
+}
+
+#[test]
+fn reuses_slots_without_reusing_ids() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		num_parameters: 1,
+		..Default::default()
+	});
+	let old_parameter = manager.add_parameter(ParameterSettings::new()).unwrap();
+	let old_id = old_parameter.id();
+	manager.remove_parameter(old_id).unwrap();
+	// the only slot is free again, but the new parameter gets a new ID
+	let mut new_parameter = manager
+		.add_parameter(ParameterSettings::new().value(1.0))
+		.unwrap();
+	assert_ne!(new_parameter.id(), old_id);
+	assert!(matches!(
+		manager.remove_parameter(old_id),
+		Err(RemoveParameterError::NoParameterWithId(_))
+	));
+	// the old ID shouldn't refer to the new parameter on the audio thread
+	backend.process();
+	let mut old_parameter = old_parameter;
+	old_parameter.set(2.0, None).unwrap();
+	new_parameter.set(3.0, None).unwrap();
+	backend.process();
+	assert_eq!(
+		manager.pop_diagnostic(),
+		Some(Diagnostic::ResourceNotFound(ResourceId::Parameter(old_id)))
+	);
+	assert_eq!(manager.pop_diagnostic(), None);
+	// a specific ID can be requested, as long as its slot is free
+	let new_id = new_parameter.id();
+	assert!(matches!(
+		manager.add_parameter(ParameterSettings::new().id(old_id)),
+		Err(AddParameterError::IdInUse(_))
+	));
+	manager.remove_parameter(new_id).unwrap();
+	let parameter = manager
+		.add_parameter(ParameterSettings::new().id(old_id))
+		.unwrap();
+	assert_eq!(parameter.id(), old_id);
+	// requesting an old ID doesn't cause IDs to be handed out twice
+	manager.remove_parameter(old_id).unwrap();
+	let parameter = manager.add_parameter(ParameterSettings::new()).unwrap();
+	assert_ne!(parameter.id(), old_id);
+	assert_ne!(parameter.id(), new_id);
+}
+
+#[test]
+fn handles_full_command_queues_according_to_the_policy() {
+	let sound = || {
+		Sound::from_frames(
+			48000,
+			vec![Frame::from_mono(1.0); 100],
+			SoundSettings {
+				cooldown: None,
+				..Default::default()
+			},
+		)
+	};
+
+	// blocking gives up after the timeout
+	let (mut manager, _) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		num_commands: 1,
+		command_queue_policy: CommandQueuePolicy::Block { timeout: 0.01 },
+		..Default::default()
+	});
+	manager.add_sound(sound()).unwrap();
+	assert!(matches!(
+		manager.add_sound(sound()),
+		Err(AddSoundError::CommandProducerError(
+			CommandError::CommandQueueFull
+		))
+	));
+
+	// spilled commands are held and sent in order once there's room
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		num_commands: 2,
+		num_instances: 10,
+		command_queue_policy: CommandQueuePolicy::Spill,
+		..Default::default()
+	});
+	let mut sound_handle = manager.add_sound(sound()).unwrap();
+	for _ in 0..3 {
+		sound_handle.play(InstanceSettings::new()).unwrap();
+	}
+	let stats = manager.stats();
+	assert_eq!(stats.num_queued_commands, 2);
+	assert_eq!(stats.num_spilled_commands, 2);
+	backend.process();
+	assert_eq!(manager.stats().num_instances, 1);
+	manager.free_unused_resources();
+	let stats = manager.stats();
+	assert_eq!(stats.num_queued_commands, 2);
+	assert_eq!(stats.num_spilled_commands, 0);
+	backend.process();
+	let stats = manager.stats();
+	assert_eq!(stats.num_instances, 3);
+	assert_eq!(stats.num_dropped_commands, 0);
+}
+
+#[test]
+fn sends_spilled_commands_on_the_resource_collection_thread() {
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(AudioManagerSettings {
+		num_commands: 2,
+		num_instances: 10,
+		command_queue_policy: CommandQueuePolicy::Spill,
+		resource_collection_interval: Some(0.001),
+		..Default::default()
+	});
+	let mut sound_handle = manager
+		.add_sound(Sound::from_frames(
+			48000,
+			vec![Frame::from_mono(1.0); 100],
+			SoundSettings::new().cooldown(0.0),
+		))
+		.unwrap();
+	for _ in 0..3 {
+		sound_handle.play(InstanceSettings::new()).unwrap();
+	}
+	assert_eq!(manager.stats().num_spilled_commands, 2);
+	backend.process();
+	// no more commands are sent, so only the background
+	// thread can send the held ones
+	let start_time = std::time::Instant::now();
+	while manager.stats().num_spilled_commands > 0 {
+		assert!(
+			start_time.elapsed().as_secs_f64() < 5.0,
+			"spilled commands were never sent"
+		);
+		std::thread::sleep(std::time::Duration::from_millis(1));
+	}
+	backend.process();
+	assert_eq!(manager.stats().num_instances, 3);
+}
+
+#[cfg(feature = "wav")]
+#[test]
+fn streams_sounds_from_disk() {
+	// a short wav file with a different value for each frame
+	let path = std::env::temp_dir().join(format!("kira-stream-test-{}.wav", std::process::id()));
+	let mut writer = hound::WavWriter::create(
+		&path,
+		hound::WavSpec {
+			channels: 1,
+			sample_rate: 48000,
+			bits_per_sample: 16,
+			sample_format: hound::SampleFormat::Int,
+		},
+	)
+	.unwrap();
+	for i in 1..=1000 {
+		writer.write_sample(i as i16 * 10).unwrap();
+	}
+	writer.finalize().unwrap();
+
+	// plays the sound and collects the first `num_frames` frames
+	// that aren't silent. streamed instances output silence while
+	// they wait for their background thread, so those are skipped
+	let render = |sound: Sound, settings: InstanceSettings, num_frames: usize| {
+		let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+		let mut sound_handle = manager.add_sound(sound).unwrap();
+		sound_handle.play(settings).unwrap();
+		let start_time = std::time::Instant::now();
+		let mut frames = vec![];
+		while frames.len() < num_frames {
+			assert!(start_time.elapsed().as_secs() < 10);
+			let frame = backend.process();
+			if frame == Frame::from_mono(0.0) {
+				std::thread::sleep(std::time::Duration::from_micros(100));
+			} else {
+				frames.push(frame);
+			}
+		}
+		frames
+	};
+	let loop_end_settings = SoundSettings::new().default_loop_end(0.015);
+	for (sound_settings, settings, num_frames) in [
+		(
+			SoundSettings::new(),
+			InstanceSettings::new().loop_start(0.01),
+			2500,
+		),
+		(
+			SoundSettings::new(),
+			InstanceSettings::new().start_position(0.005).reverse(),
+			700,
+		),
+		(
+			SoundSettings::new(),
+			InstanceSettings::new()
+				.playback_rate(0.37)
+				.interpolation_mode(InterpolationMode::Sinc),
+			2000,
+		),
+		(
+			loop_end_settings.clone(),
+			InstanceSettings::new().loop_start(0.01),
+			2500,
+		),
+		(
+			loop_end_settings,
+			InstanceSettings::new()
+				.loop_start(0.005)
+				.start_position(0.005)
+				.reverse(),
+			2500,
+		),
+	] {
+		let streamed_sound = Sound::stream_from_file(&path, sound_settings.clone()).unwrap();
+		assert!(streamed_sound.is_streamed());
+		assert_eq!(streamed_sound.duration(), 1000.0 / 48000.0);
+		let expected = render(
+			Sound::from_wav_file(&path, sound_settings).unwrap(),
+			settings,
+			num_frames,
+		);
+		let streamed = render(streamed_sound, settings, num_frames);
+		for (expected, streamed) in expected.iter().zip(&streamed) {
+			assert!((expected.left - streamed.left).abs() < 0.0001);
+			assert!((expected.right - streamed.right).abs() < 0.0001);
+		}
+	}
+
+	// arrangements can't use streamed sounds
+	let (mut manager, _) = AudioManager::new_without_audio_thread(Default::default());
+	let sound_handle = manager
+		.add_sound(Sound::stream_from_file(&path, SoundSettings::default()).unwrap())
+		.unwrap();
+	let mut arrangement = Arrangement::new(Default::default());
+	arrangement.add_clip(SoundClip::new(&sound_handle, 0.0));
+	assert!(matches!(
+		manager.add_arrangement(arrangement),
+		Err(AddArrangementError::StreamedSound(id)) if id == sound_handle.id()
+	));
+
+	// and sequences can't play them
+	let mut sequence = Sequence::<()>::new(SequenceSettings::new());
+	sequence.play_random(vec![sound_handle.id().into()], InstanceSettings::new());
+	assert!(matches!(
+		manager.start_sequence(sequence, SequenceInstanceSettings::new()),
+		Err(StartSequenceError::StreamedSound(id)) if id == sound_handle.id()
+	));
+	std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn uses_the_instance_interpolation_mode_over_the_sounds() {
+	// a sound at half the output sample rate, so every other
+	// output frame is between two samples
+	let sound = Sound::from_frames(
+		24000,
+		(0..100).map(|i| Frame::from_mono((i % 2) as f32)).collect(),
+		SoundSettings::new().interpolation_mode(InterpolationMode::Nearest),
+	);
+	let render = |settings: InstanceSettings| {
+		let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+		let mut sound_handle = manager.add_sound(sound.clone()).unwrap();
+		sound_handle.play(settings).unwrap();
+		(0..100)
+			.map(|_| backend.process().left / 0.5f32.sqrt())
+			.collect::<Vec<_>>()
+	};
+	let is_in_between = |value: &f32| (value - 0.5).abs() < 0.01;
+	assert!(!render(InstanceSettings::new()).iter().any(is_in_between));
+	assert!(
+		render(InstanceSettings::new().interpolation_mode(InterpolationMode::Linear))
+			.iter()
+			.any(is_in_between)
+	);
+}
+
+#[test]
+fn rebuilds_the_stream_after_any_stream_error() {
+	let (mut stream_error_producer, mut stream_error_consumer) = RingBuffer::new(10).split();
+	// no errors means the stream is still working
+	assert!(!drain_stream_errors(&mut stream_error_consumer));
+	// errors other than a missing device also mean the stream
+	// should be rebuilt
+	stream_error_producer
+		.push(cpal::StreamError::BackendSpecific {
+			err: cpal::BackendSpecificError {
+				description: "the device was disconnected".into(),
+			},
+		})
+		.unwrap();
+	stream_error_producer
+		.push(cpal::StreamError::DeviceNotAvailable)
+		.unwrap();
+	assert!(drain_stream_errors(&mut stream_error_consumer));
+	// the errors are used up
+	assert!(!drain_stream_errors(&mut stream_error_consumer));
+	// a successful rebuild reports the new device
+	assert_eq!(
+		rebuild_device_event(true, Ok(("speakers".into(), 48000))),
+		Some(DeviceEvent::DeviceChanged {
+			name: "speakers".into(),
+			sample_rate: 48000,
+		})
+	);
+	// a failed rebuild reports the lost device only once
+	assert_eq!(
+		rebuild_device_event(true, Err(SetupError::NoDefaultOutputDevice)),
+		Some(DeviceEvent::DeviceLost)
+	);
+	assert_eq!(
+		rebuild_device_event(false, Err(SetupError::NoDefaultOutputDevice)),
+		None
+	);
+}
+
+#[test]
+fn updates_effects_and_resampled_sounds_when_the_sample_rate_changes() {
+	#[derive(Debug)]
+	struct InitRecorder(Arc<Mutex<Vec<u32>>>);
+
+	impl Effect for InitRecorder {
+		fn init(&mut self, sample_rate: u32) {
+			self.0.lock().unwrap().push(sample_rate);
+		}
+
+		fn process(&mut self, _dt: f64, input: Frame, _parameters: &Parameters) -> Frame {
+			input
+		}
+	}
+
+	let (mut manager, mut backend) = AudioManager::new_with_backend(
+		AudioManagerSettings {
+			resample_sounds: true,
+			..Default::default()
+		},
+		44100,
+	);
+	let mut sub_track = manager.add_sub_track(SubTrackSettings::new()).unwrap();
+	let init_sample_rates = Arc::new(Mutex::new(vec![]));
+	sub_track
+		.add_effect(InitRecorder(init_sample_rates.clone()), Default::default())
+		.unwrap();
+	let sound = manager
+		.add_sound(Sound::from_frames(
+			44100,
+			vec![Frame::from_mono(0.0); 10],
+			Default::default(),
+		))
+		.unwrap();
+	backend.process();
+	assert_eq!(*init_sample_rates.lock().unwrap(), vec![44100]);
+	assert!(manager.outdated_resampled_sounds().is_empty());
+	// existing effects are initialized again, and sounds resampled
+	// to the old sample rate are reported
+	backend.set_sample_rate(48000);
+	assert_eq!(manager.sample_rate(), 48000);
+	assert_eq!(*init_sample_rates.lock().unwrap(), vec![44100, 48000]);
+	assert_eq!(manager.outdated_resampled_sounds(), vec![sound.id()]);
+	// effects added after the change use the new sample rate, even
+	// if the sample rate changes again before they reach the audio
+	// thread
+	let new_init_sample_rates = Arc::new(Mutex::new(vec![]));
+	sub_track
+		.add_effect(
+			InitRecorder(new_init_sample_rates.clone()),
+			Default::default(),
+		)
+		.unwrap();
+	assert_eq!(*new_init_sample_rates.lock().unwrap(), vec![48000]);
+	backend.set_sample_rate(96000);
+	backend.process();
+	assert_eq!(*new_init_sample_rates.lock().unwrap(), vec![48000, 96000]);
+	// removed sounds aren't reported
+	manager.remove_sound(sound.id()).unwrap();
+	assert!(manager.outdated_resampled_sounds().is_empty());
+}
+
+#[test]
+fn chooses_the_output_config() {
+	let default_config = ChosenOutputConfig {
+		channels: 2,
+		sample_rate: 48000,
+	};
+	let supported_config = |channels, min_sample_rate, max_sample_rate| SupportedOutputConfig {
+		channels,
+		min_sample_rate,
+		max_sample_rate,
+		buffer_size_range: None,
+	};
+	let supported_configs = [
+		supported_config(2, 44100, 96000),
+		supported_config(6, 44100, 48000),
+	];
+	let choose = |sample_rate, channels| {
+		choose_output_config(default_config, &supported_configs, sample_rate, channels).unwrap()
+	};
+	// nothing requested uses the default config, even if the device
+	// doesn't list it
+	assert_eq!(choose(None, 2), default_config);
+	assert_eq!(
+		choose_output_config(default_config, &[], None, 2).unwrap(),
+		default_config
+	);
+	// supported requests are used as is
+	assert_eq!(
+		choose(Some(96000), 2),
+		ChosenOutputConfig {
+			channels: 2,
+			sample_rate: 96000,
+		}
+	);
+	assert_eq!(
+		choose(None, 6),
+		ChosenOutputConfig {
+			channels: 6,
+			sample_rate: 48000,
+		}
+	);
+	// unsupported channel counts fall back to the default
+	// channel count
+	assert_eq!(
+		choose(None, 8),
+		ChosenOutputConfig {
+			channels: 2,
+			sample_rate: 48000,
+		}
+	);
+	assert_eq!(
+		choose(Some(96000), 6),
+		ChosenOutputConfig {
+			channels: 2,
+			sample_rate: 96000,
+		}
+	);
+	// unsupported sample rates are an error if they were requested...
+	assert!(matches!(
+		choose_output_config(default_config, &supported_configs, Some(192000), 2),
+		Err(SetupError::UnsupportedSampleRate(192000))
+	));
+	// if no sample rate was requested, the closest sample rate
+	// the requested channel count supports is used instead
+	assert_eq!(
+		choose_output_config(
+			default_config,
+			&[supported_config(6, 44100, 44100)],
+			None,
+			6
+		)
+		.unwrap(),
+		ChosenOutputConfig {
+			channels: 6,
+			sample_rate: 44100,
+		}
+	);
+}
+
+#[test]
+#[cfg(all(feature = "wav", target_os = "linux"))]
+fn reports_file_output_errors() {
+	// writing to /dev/full always fails because the device is full
+	let mut manager = AudioManager::with_output(
+		AudioManagerSettings::default(),
+		super::output::FileOutput::new("/dev/full", 48000).buffer_size(4096),
+	)
+	.unwrap();
+	let start_time = std::time::Instant::now();
+	let event = loop {
+		if let Some(event) = manager.pop_device_event() {
+			break event;
+		}
+		assert!(start_time.elapsed().as_secs() < 5);
+		std::thread::sleep(std::time::Duration::from_millis(10));
+	};
+	assert!(matches!(event, DeviceEvent::OutputFailed(_)));
+}
+
+#[test]
+fn setting_a_parameter_without_a_tween_cancels_the_current_tween() {
+	let mut parameter = Parameter::new(0.0);
+	parameter.set(1.0, Some(Tween::linear(1.0)));
+	parameter.update(0.5);
+	parameter.set(0.25, None);
+	assert_eq!(parameter.value(), 0.25);
+	// the old tween shouldn't keep moving the value
+	parameter.update(0.25);
+	assert_eq!(parameter.value(), 0.25);
+}
+
+#[test]
+fn suspends_the_output_without_blocking() {
+	/// Lets the test drive the backend and see when
+	/// the output is suspended.
+	#[derive(Clone, Default)]
+	struct TestOutput {
+		backend: Arc<Mutex<Option<Backend>>>,
+		suspended: Arc<AtomicBool>,
+	}
+
+	impl OutputBackend for TestOutput {
+		fn start(&mut self, backend: Backend) -> Result<u32, SetupError> {
+			*self.backend.lock().unwrap() = Some(backend);
+			Ok(48000)
+		}
+
+		fn suspend(&mut self) {
+			self.suspended.store(true, Ordering::SeqCst);
+		}
+
+		fn resume(&mut self) {
+			self.suspended.store(false, Ordering::SeqCst);
+		}
+	}
+
+	let output = TestOutput::default();
+	let mut manager = AudioManager::with_output(Default::default(), output.clone()).unwrap();
+	let process = |num_frames: usize| {
+		let mut backend = output.backend.lock().unwrap();
+		let backend = backend.as_mut().unwrap();
+		for _ in 0..num_frames {
+			backend.process();
+		}
+	};
+	manager.start_suspend(Tween::linear(4.0 / 48000.0)).unwrap();
+	// the output shouldn't be suspended until the audio
+	// thread has finished fading out
+	assert!(!manager.poll_suspend());
+	assert!(!output.suspended.load(Ordering::SeqCst));
+	process(2);
+	assert!(!manager.poll_suspend());
+	assert!(!output.suspended.load(Ordering::SeqCst));
+	process(10);
+	assert!(manager.poll_suspend());
+	assert!(output.suspended.load(Ordering::SeqCst));
+	// there's nothing left to do
+	assert!(manager.poll_suspend());
+	manager.resume(None).unwrap();
+	assert!(!output.suspended.load(Ordering::SeqCst));
+}
+
+#[test]
+fn group_settings_builder_methods_keep_other_settings() {
+	let id = GroupId::from_slot_index(SlotIndex {
+		index: 0,
+		generation: 0,
+	});
+	let parent_id = GroupId::from_slot_index(SlotIndex {
+		index: 1,
+		generation: 0,
+	});
+	let settings = GroupSettings::new()
+		.unpausable()
+		.id(id)
+		.groups(GroupSet::new().add(parent_id));
+	assert_eq!(settings.id, Some(id));
+	assert!(settings.groups.contains(parent_id));
+	assert!(settings.unpausable);
+}
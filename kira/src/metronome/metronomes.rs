@@ -2,30 +2,41 @@ use basedrop::Owned;
 
 use crate::{
 	command::MetronomeCommand,
+	manager::diagnostics::{Diagnostic, DiagnosticProducer, ResourceId},
 	metronome::{Metronome, MetronomeId},
 	parameter::Parameters,
-	static_container::index_map::StaticIndexMap,
+	static_container::slot_map::StaticSlotMap,
 };
 
 pub(crate) struct Metronomes {
-	metronomes: StaticIndexMap<MetronomeId, Owned<Metronome>>,
+	metronomes: StaticSlotMap<MetronomeId, Owned<Metronome>>,
 }
 
 impl Metronomes {
 	pub fn new(capacity: usize) -> Self {
 		Self {
-			metronomes: StaticIndexMap::new(capacity),
+			metronomes: StaticSlotMap::new(capacity),
 		}
 	}
 
+	/// Moves all of the metronomes into `other`, which has a larger
+	/// capacity, and swaps the two so that the old (now empty)
+	/// storage can be freed on another thread.
+	pub fn grow_into(&mut self, other: &mut Self) {
+		self.metronomes.move_into(&mut other.metronomes);
+		std::mem::swap(self, other);
+	}
+
 	pub fn get(&self, id: MetronomeId) -> Option<&Owned<Metronome>> {
 		self.metronomes.get(&id)
 	}
 
-	pub fn run_command(&mut self, command: MetronomeCommand) {
+	pub fn run_command(&mut self, command: MetronomeCommand, diagnostics: &mut DiagnosticProducer) {
 		match command {
 			MetronomeCommand::AddMetronome(id, metronome) => {
-				self.metronomes.try_insert(id, metronome).ok();
+				if self.metronomes.try_insert(id, metronome).is_err() {
+					diagnostics.report(Diagnostic::ResourceLimitReached(ResourceId::Metronome(id)));
+				}
 			}
 			MetronomeCommand::RemoveMetronome(id) => {
 				self.metronomes.remove(&id);
@@ -33,21 +44,29 @@ impl Metronomes {
 			MetronomeCommand::SetMetronomeTempo(id, tempo) => {
 				if let Some(metronome) = self.metronomes.get_mut(&id) {
 					metronome.set_tempo(tempo);
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(ResourceId::Metronome(id)));
 				}
 			}
 			MetronomeCommand::StartMetronome(id) => {
 				if let Some(metronome) = self.metronomes.get_mut(&id) {
 					metronome.start();
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(ResourceId::Metronome(id)));
 				}
 			}
 			MetronomeCommand::PauseMetronome(id) => {
 				if let Some(metronome) = self.metronomes.get_mut(&id) {
 					metronome.pause();
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(ResourceId::Metronome(id)));
 				}
 			}
 			MetronomeCommand::StopMetronome(id) => {
 				if let Some(metronome) = self.metronomes.get_mut(&id) {
 					metronome.stop();
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(ResourceId::Metronome(id)));
 				}
 			}
 		}
@@ -7,8 +7,8 @@ pub mod handle;
 mod metronomes;
 mod settings;
 
+use crate::static_container::slot_map::{SlotIndex, SlotKey};
 use ringbuf::Producer;
-use uuid::Uuid;
 
 use crate::{parameter::Parameters, tempo::Tempo, value::CachedValue, Value};
 use handle::MetronomeHandle;
@@ -23,14 +23,16 @@ pub use settings::MetronomeSettings;
 	serde(transparent)
 )]
 pub struct MetronomeId {
-	uuid: Uuid,
+	index: SlotIndex,
 }
 
-impl MetronomeId {
-	pub(crate) fn new() -> Self {
-		Self {
-			uuid: Uuid::new_v4(),
-		}
+impl SlotKey for MetronomeId {
+	fn from_slot_index(index: SlotIndex) -> Self {
+		Self { index }
+	}
+
+	fn slot_index(&self) -> SlotIndex {
+		self.index
 	}
 }
 
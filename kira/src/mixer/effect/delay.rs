@@ -80,9 +80,7 @@ impl Default for DelaySettings {
 
 #[derive(Debug, Clone)]
 enum DelayState {
-	Uninitialized {
-		buffer_length: f64,
-	},
+	Uninitialized,
 	Initialized {
 		buffer: Vec<Frame>,
 		write_position: usize,
@@ -95,6 +93,7 @@ enum DelayState {
 pub struct Delay {
 	delay_time: CachedValue<f64>,
 	feedback: CachedValue<f64>,
+	buffer_length: f64,
 	state: DelayState,
 	filter: Option<Filter>,
 }
@@ -105,9 +104,8 @@ impl Delay {
 		Self {
 			delay_time: CachedValue::new(settings.delay_time, 0.5).with_min(0.0),
 			feedback: CachedValue::new(settings.feedback, 0.5).with_valid_range(-1.0..1.0),
-			state: DelayState::Uninitialized {
-				buffer_length: settings.buffer_length,
-			},
+			buffer_length: settings.buffer_length,
+			state: DelayState::Uninitialized,
 			filter: settings
 				.filter_settings
 				.map(|settings| Filter::new(settings)),
@@ -117,13 +115,9 @@ impl Delay {
 
 impl Effect for Delay {
 	fn init(&mut self, sample_rate: u32) {
-		if let DelayState::Uninitialized { buffer_length } = &self.state {
-			self.state = DelayState::Initialized {
-				buffer: vec![Frame::from_mono(0.0); (buffer_length * sample_rate as f64) as usize],
-				write_position: 0,
-			}
-		} else {
-			panic!("The delay should be in the uninitialized state before init")
+		self.state = DelayState::Initialized {
+			buffer: vec![Frame::from_mono(0.0); (self.buffer_length * sample_rate as f64) as usize],
+			write_position: 0,
 		}
 	}
 
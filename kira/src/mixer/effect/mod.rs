@@ -10,7 +10,7 @@ use handle::EffectHandle;
 
 use std::fmt::Debug;
 
-use uuid::Uuid;
+use crate::id_counter::{IdCounter, IdTooLarge};
 
 use crate::{frame::Frame, parameter::Parameters, Value};
 
@@ -22,15 +22,24 @@ use crate::{frame::Frame, parameter::Parameters, Value};
 	serde(transparent)
 )]
 pub struct EffectId {
-	uuid: Uuid,
+	index: u64,
 }
 
+static NEXT_EFFECT_ID: IdCounter = IdCounter::new();
+
 impl EffectId {
 	pub(crate) fn new() -> Self {
 		Self {
-			uuid: Uuid::new_v4(),
+			index: NEXT_EFFECT_ID.next(),
 		}
 	}
+
+	/// Makes sure [`EffectId::new`] never returns this ID,
+	/// since it was chosen by the user.
+	pub(crate) fn claim(self) -> Result<Self, IdTooLarge> {
+		NEXT_EFFECT_ID.claim(self.index)?;
+		Ok(self)
+	}
 }
 
 impl From<&EffectHandle> for EffectId {
@@ -54,6 +63,9 @@ pub struct EffectSettings {
 	/// The balance between dry (unaffected) signal and wet
 	/// (affected) signal to output. 0.0 is fully dry,
 	/// 1.0 is fully wet.
+	///
+	/// If this is linked to a parameter, it's read once per block
+	/// of up to 128 frames and ramped smoothly over the block.
 	pub mix: Value<f64>,
 }
 
@@ -102,7 +114,11 @@ impl Default for EffectSettings {
 pub trait Effect: Send + Debug {
 	/// Performs any required setup for the effect.
 	///
-	/// This is called once when the effect is first added to a track.
+	/// This is called when the effect is first added to a track,
+	/// and again if the audio manager's sample rate changes (for
+	/// example, because audio output moved to a different device).
+	/// Effects that allocate buffers based on the sample rate should
+	/// replace them each time this is called.
 	fn init(&mut self, sample_rate: u32) {}
 
 	/// Transforms an input frame.
@@ -112,4 +128,19 @@ pub trait Effect: Send + Debug {
 	/// This is useful in conjunction with [`CachedValue`](crate::CachedValue)s,
 	/// which can respond to parameter changes and update their value accordingly.
 	fn process(&mut self, dt: f64, input: Frame, parameters: &Parameters) -> Frame;
+
+	/// Transforms a block of frames in place.
+	/// - `dt` is the time that elapses between each frame (in seconds)
+	/// - `frames` is the input audio, which should be replaced with the output audio
+	/// - `parameters` is a set of all parameter IDs and their corresponding values
+	///   as of the end of the block.
+	///
+	/// The mixer always calls this function rather than [`process`](Effect::process).
+	/// By default, it calls `process` for each frame, so effects only need
+	/// to implement this if they can process a whole block more efficiently.
+	fn process_block(&mut self, dt: f64, frames: &mut [Frame], parameters: &Parameters) {
+		for frame in frames {
+			*frame = self.process(dt, *frame, parameters);
+		}
+	}
 }
@@ -1,31 +1,96 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
 use basedrop::Owned;
 
 use crate::{frame::Frame, parameter::Parameters, CachedValue};
 
-use super::effect::{Effect, EffectSettings};
+use super::{
+	effect::{Effect, EffectSettings},
+	ramped_value::RampedValue,
+};
 
 pub(crate) struct EffectSlot {
 	effect: Owned<Box<dyn Effect>>,
+	// the sample rate the effect was last initialized with
+	sample_rate: u32,
 	pub enabled: bool,
-	pub mix: CachedValue<f64>,
+	pub mix: RampedValue,
+	panicked: bool,
+	panic_reported: bool,
 }
 
 impl EffectSlot {
-	pub fn new(effect: Owned<Box<dyn Effect>>, settings: EffectSettings) -> Self {
+	pub fn new(effect: Owned<Box<dyn Effect>>, settings: EffectSettings, sample_rate: u32) -> Self {
 		Self {
 			effect,
+			sample_rate,
 			enabled: settings.enabled,
-			mix: CachedValue::new(settings.mix, 1.0).with_valid_range(0.0..1.0),
+			mix: RampedValue::new(CachedValue::new(settings.mix, 1.0).with_valid_range(0.0..1.0)),
+			panicked: false,
+			panic_reported: false,
+		}
+	}
+
+	/// Initializes the effect again if it was initialized with
+	/// a different sample rate.
+	///
+	/// If the effect panics, it's bypassed from then on.
+	pub fn set_sample_rate(&mut self, sample_rate: u32) {
+		if sample_rate == self.sample_rate {
+			return;
+		}
+		self.sample_rate = sample_rate;
+		let effect = &mut self.effect;
+		if catch_unwind(AssertUnwindSafe(|| effect.init(sample_rate))).is_err() {
+			self.panicked = true;
 		}
 	}
 
-	pub(super) fn process(&mut self, dt: f64, input: Frame, parameters: &Parameters) -> Frame {
+	/// Applies the effect to a block of frames in place.
+	///
+	/// `dry` is scratch space used to hold the unaffected signal, and it
+	/// must be the same length as `frames`.
+	///
+	/// If the effect panics, the block is left unaffected and
+	/// the effect is bypassed from then on.
+	pub(super) fn process_block(
+		&mut self,
+		dt: f64,
+		frames: &mut [Frame],
+		dry: &mut [Frame],
+		parameters: &Parameters,
+	) {
 		self.mix.update(parameters);
-		if self.enabled {
-			let wet = self.effect.process(dt, input, parameters);
-			input + (wet - input) * self.mix.value() as f32
-		} else {
-			input
+		if !self.enabled || self.panicked {
+			return;
+		}
+		dry.copy_from_slice(frames);
+		let effect = &mut self.effect;
+		let result = catch_unwind(AssertUnwindSafe(|| {
+			effect.process_block(dt, &mut *frames, parameters);
+		}));
+		if result.is_err() {
+			self.panicked = true;
+			frames.copy_from_slice(dry);
+			return;
+		}
+		let num_frames = frames.len();
+		for ((frame, dry), mix) in frames
+			.iter_mut()
+			.zip(dry.iter())
+			.zip(self.mix.ramp(num_frames))
+		{
+			*frame = *dry + (*frame - *dry) * mix;
+		}
+	}
+
+	/// Returns `true` the first time this is called after
+	/// the effect panics.
+	pub(super) fn take_panic(&mut self) -> bool {
+		if self.panicked && !self.panic_reported {
+			self.panic_reported = true;
+			return true;
 		}
+		false
 	}
 }
@@ -70,6 +70,7 @@
 
 pub mod effect;
 pub(crate) mod effect_slot;
+pub(crate) mod ramped_value;
 mod track;
 
 pub use track::{
@@ -78,3 +79,14 @@ pub use track::{
 	SendTrackId, SendTrackSettings, SubTrackId, SubTrackSettings, TrackIndex,
 };
 pub(crate) use track::{Track, TrackKind};
+
+/// The maximum number of frames the mixer processes at a time.
+///
+/// Larger buffers are split up into blocks of this size. Track
+/// volumes, send volumes, and effect mixes are read once at the
+/// end of each block and ramped smoothly to their new values over
+/// the course of the block, so if they're linked to a parameter,
+/// they lag behind it by up to this many frames. Commands,
+/// parameters, sequences, and instances are still updated
+/// every frame.
+pub(crate) const MAX_BLOCK_SIZE: usize = 128;
@@ -0,0 +1,59 @@
+use crate::{parameter::Parameters, CachedValue, Value};
+
+/// A gain that's applied to blocks of audio.
+///
+/// The mixer only reads parameters once per block, so if a gain
+/// jumped to its new value at the start of each block, a gain
+/// following a tweened parameter would change in steps, which
+/// sounds like a buzz ("zipper noise"). Instead, the gain is
+/// ramped from its value at the end of the previous block to
+/// its new value over the course of each block.
+#[derive(Debug, Clone)]
+pub(crate) struct RampedValue {
+	value: CachedValue<f64>,
+	// the gain at the start and end of the current block
+	start: f64,
+	end: f64,
+	// whether the value has been updated for at least one block.
+	// the first block starts at the first value read from the
+	// parameter instead of ramping from the default value.
+	updated: bool,
+}
+
+impl RampedValue {
+	pub fn new(value: CachedValue<f64>) -> Self {
+		let initial_value = value.value();
+		Self {
+			value,
+			start: initial_value,
+			end: initial_value,
+			updated: false,
+		}
+	}
+
+	pub fn set(&mut self, value: Value<f64>) {
+		self.value.set(value);
+	}
+
+	/// Reads the new value for the next block.
+	pub fn update(&mut self, parameters: &Parameters) {
+		self.value.update(parameters);
+		let value = self.value.value();
+		self.start = if self.updated { self.end } else { value };
+		self.end = value;
+		self.updated = true;
+	}
+
+	/// Returns the gain at the end of the current block.
+	pub fn value(&self) -> f64 {
+		self.end
+	}
+
+	/// Returns the gain to apply to each frame of a block with
+	/// the given number of frames.
+	pub fn ramp(&self, num_frames: usize) -> impl Iterator<Item = f32> {
+		let start = self.start as f32;
+		let step = (self.end - self.start) as f32 / num_frames as f32;
+		(1..=num_frames).map(move |i| start + step * i as f32)
+	}
+}
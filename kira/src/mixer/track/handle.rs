@@ -1,6 +1,6 @@
 //! An interface for controlling mixer tracks.
 
-use std::sync::Weak;
+use std::sync::{Arc, Weak};
 
 use basedrop::{Handle, Owned};
 use indexmap::IndexSet;
@@ -11,6 +11,7 @@ use crate::{
 		producer::{CommandError, CommandProducer},
 		MixerCommand,
 	},
+	manager::SharedStats,
 	mixer::effect::{handle::EffectHandle, Effect, EffectId, EffectSettings},
 	Value,
 };
@@ -32,6 +33,10 @@ pub enum AddEffectError {
 	/// No effect with the specified ID exists on this track.
 	#[error("No effect with the specified ID exists on this track")]
 	NoEffectWithId(EffectId),
+	/// The effect was given the largest possible ID, which can't
+	/// be used because there would be no IDs left for other effects.
+	#[error("The effect's ID is too large to be used")]
+	IdTooLarge(EffectId),
 	/// A command could not be sent to the audio thread.
 	#[error("Could not send the command to the audio thread.")]
 	CommandProducerError(#[from] CommandError),
@@ -53,20 +58,20 @@ pub enum RemoveEffectError {
 pub struct MainTrackHandle {
 	command_producer: CommandProducer,
 	active_effect_ids: IndexSet<EffectId>,
-	sample_rate: u32,
+	stats: Arc<SharedStats>,
 	resource_collector_handle: Weak<Handle>,
 }
 
 impl MainTrackHandle {
 	pub(crate) fn new(
 		command_producer: CommandProducer,
-		sample_rate: u32,
+		stats: Arc<SharedStats>,
 		resource_collector_handle: Weak<Handle>,
 	) -> Self {
 		Self {
 			command_producer,
 			active_effect_ids: IndexSet::with_capacity(MAIN_TRACK_NUM_EFFECTS),
-			sample_rate,
+			stats,
 			resource_collector_handle,
 		}
 	}
@@ -88,14 +93,18 @@ impl MainTrackHandle {
 		if self.active_effect_ids.len() >= self.active_effect_ids.capacity() {
 			return Err(AddEffectError::EffectLimitReached);
 		}
-		let effect_id = settings.id.unwrap_or(EffectId::new());
+		let effect_id = match settings.id {
+			Some(id) => id.claim().map_err(|_| AddEffectError::IdTooLarge(id))?,
+			None => EffectId::new(),
+		};
 		let handle = EffectHandle::new(
 			effect_id,
 			TrackIndex::Main,
 			&settings,
 			self.command_producer.clone(),
 		);
-		effect.init(self.sample_rate);
+		let sample_rate = self.stats.sample_rate();
+		effect.init(sample_rate);
 		if let Some(handle) = self.resource_collector_handle.upgrade() {
 			self.command_producer.push(
 				MixerCommand::AddEffect(
@@ -103,6 +112,7 @@ impl MainTrackHandle {
 					effect_id,
 					Owned::new(&handle, Box::new(effect)),
 					settings,
+					sample_rate,
 				)
 				.into(),
 			)?;
@@ -128,7 +138,7 @@ pub struct SubTrackHandle {
 	id: SubTrackId,
 	command_producer: CommandProducer,
 	active_effect_ids: IndexSet<EffectId>,
-	sample_rate: u32,
+	stats: Arc<SharedStats>,
 	resource_collector_handle: Weak<Handle>,
 }
 
@@ -137,14 +147,14 @@ impl SubTrackHandle {
 		id: SubTrackId,
 		settings: &SubTrackSettings,
 		command_producer: CommandProducer,
-		sample_rate: u32,
+		stats: Arc<SharedStats>,
 		resource_collector_handle: Weak<Handle>,
 	) -> Self {
 		Self {
 			id,
 			command_producer,
 			active_effect_ids: IndexSet::with_capacity(settings.num_effects),
-			sample_rate,
+			stats,
 			resource_collector_handle,
 		}
 	}
@@ -160,6 +170,19 @@ impl SubTrackHandle {
 			.push(MixerCommand::SetTrackVolume(self.id.into(), volume.into()).into())
 	}
 
+	/// Sets where to place the track's output around the listener
+	/// (in degrees) when using a surround channel layout.
+	///
+	/// If `None`, the track's output is sent to the main track
+	/// in stereo.
+	pub fn set_surround_angle(
+		&mut self,
+		surround_angle: Option<Value<f64>>,
+	) -> Result<(), CommandError> {
+		self.command_producer
+			.push(MixerCommand::SetTrackSurroundAngle(self.id.into(), surround_angle).into())
+	}
+
 	/// Adds an effect to the track.
 	pub fn add_effect(
 		&mut self,
@@ -169,14 +192,18 @@ impl SubTrackHandle {
 		if self.active_effect_ids.len() >= self.active_effect_ids.capacity() {
 			return Err(AddEffectError::EffectLimitReached);
 		}
-		let effect_id = settings.id.unwrap_or(EffectId::new());
+		let effect_id = match settings.id {
+			Some(id) => id.claim().map_err(|_| AddEffectError::IdTooLarge(id))?,
+			None => EffectId::new(),
+		};
 		let handle = EffectHandle::new(
 			effect_id,
 			self.id.into(),
 			&settings,
 			self.command_producer.clone(),
 		);
-		effect.init(self.sample_rate);
+		let sample_rate = self.stats.sample_rate();
+		effect.init(sample_rate);
 		if let Some(handle) = self.resource_collector_handle.upgrade() {
 			self.command_producer.push(
 				MixerCommand::AddEffect(
@@ -184,6 +211,7 @@ impl SubTrackHandle {
 					effect_id,
 					Owned::new(&handle, Box::new(effect)),
 					settings,
+					sample_rate,
 				)
 				.into(),
 			)?;
@@ -209,7 +237,7 @@ pub struct SendTrackHandle {
 	id: SendTrackId,
 	command_producer: CommandProducer,
 	active_effect_ids: IndexSet<EffectId>,
-	sample_rate: u32,
+	stats: Arc<SharedStats>,
 	resource_collector_handle: Weak<Handle>,
 }
 
@@ -218,14 +246,14 @@ impl SendTrackHandle {
 		id: SendTrackId,
 		settings: &SendTrackSettings,
 		command_producer: CommandProducer,
-		sample_rate: u32,
+		stats: Arc<SharedStats>,
 		resource_collector_handle: Weak<Handle>,
 	) -> Self {
 		Self {
 			id,
 			command_producer,
 			active_effect_ids: IndexSet::with_capacity(settings.num_effects),
-			sample_rate,
+			stats,
 			resource_collector_handle,
 		}
 	}
@@ -241,6 +269,19 @@ impl SendTrackHandle {
 			.push(MixerCommand::SetTrackVolume(self.id.into(), volume.into()).into())
 	}
 
+	/// Sets where to place the track's output around the listener
+	/// (in degrees) when using a surround channel layout.
+	///
+	/// If `None`, the track's output is sent to the main track
+	/// in stereo.
+	pub fn set_surround_angle(
+		&mut self,
+		surround_angle: Option<Value<f64>>,
+	) -> Result<(), CommandError> {
+		self.command_producer
+			.push(MixerCommand::SetTrackSurroundAngle(self.id.into(), surround_angle).into())
+	}
+
 	/// Adds an effect to the track.
 	pub fn add_effect(
 		&mut self,
@@ -250,14 +291,18 @@ impl SendTrackHandle {
 		if self.active_effect_ids.len() >= self.active_effect_ids.capacity() {
 			return Err(AddEffectError::EffectLimitReached);
 		}
-		let effect_id = settings.id.unwrap_or(EffectId::new());
+		let effect_id = match settings.id {
+			Some(id) => id.claim().map_err(|_| AddEffectError::IdTooLarge(id))?,
+			None => EffectId::new(),
+		};
 		let handle = EffectHandle::new(
 			effect_id,
 			self.id.into(),
 			&settings,
 			self.command_producer.clone(),
 		);
-		effect.init(self.sample_rate);
+		let sample_rate = self.stats.sample_rate();
+		effect.init(sample_rate);
 		if let Some(handle) = self.resource_collector_handle.upgrade() {
 			self.command_producer.push(
 				MixerCommand::AddEffect(
@@ -265,6 +310,7 @@ impl SendTrackHandle {
 					effect_id,
 					Owned::new(&handle, Box::new(effect)),
 					settings,
+					sample_rate,
 				)
 				.into(),
 			)?;
@@ -5,18 +5,23 @@ pub mod settings;
 use indexmap::IndexMap;
 pub use settings::*;
 
+use crate::static_container::slot_map::{SlotIndex, SlotKey};
 use basedrop::Owned;
 use handle::{SendTrackHandle, SubTrackHandle};
-use uuid::Uuid;
 
 use crate::{
-	frame::Frame, parameter::Parameters, static_container::index_map::StaticIndexMap, CachedValue,
-	Value,
+	frame::Frame,
+	manager::diagnostics::{Diagnostic, DiagnosticProducer},
+	parameter::Parameters,
+	static_container::index_map::StaticIndexMap,
+	CachedValue, Value,
 };
 
 use super::{
 	effect::{Effect, EffectId, EffectSettings},
 	effect_slot::EffectSlot,
+	ramped_value::RampedValue,
+	MAX_BLOCK_SIZE,
 };
 
 pub(crate) const MAIN_TRACK_NUM_EFFECTS: usize = 8;
@@ -29,14 +34,16 @@ pub(crate) const MAIN_TRACK_NUM_EFFECTS: usize = 8;
 	serde(transparent)
 )]
 pub struct SubTrackId {
-	uuid: Uuid,
+	index: SlotIndex,
 }
 
-impl SubTrackId {
-	pub(crate) fn new() -> Self {
-		Self {
-			uuid: Uuid::new_v4(),
-		}
+impl SlotKey for SubTrackId {
+	fn from_slot_index(index: SlotIndex) -> Self {
+		Self { index }
+	}
+
+	fn slot_index(&self) -> SlotIndex {
+		self.index
 	}
 }
 
@@ -54,14 +61,16 @@ impl From<&SubTrackHandle> for SubTrackId {
 	serde(transparent)
 )]
 pub struct SendTrackId {
-	uuid: Uuid,
+	index: SlotIndex,
 }
 
-impl SendTrackId {
-	pub(crate) fn new() -> Self {
-		Self {
-			uuid: Uuid::new_v4(),
-		}
+impl SlotKey for SendTrackId {
+	fn from_slot_index(index: SlotIndex) -> Self {
+		Self { index }
+	}
+
+	fn slot_index(&self) -> SlotIndex {
+		self.index
 	}
 }
 
@@ -133,7 +142,7 @@ pub(crate) enum TrackKind {
 	Sub {
 		id: SubTrackId,
 		parent_track: TrackIndex,
-		sends: IndexMap<SendTrackId, CachedValue<f64>>,
+		sends: IndexMap<SendTrackId, RampedValue>,
 	},
 	Send {
 		id: SendTrackId,
@@ -142,18 +151,25 @@ pub(crate) enum TrackKind {
 
 pub(crate) struct Track {
 	kind: TrackKind,
-	volume: CachedValue<f64>,
+	volume: RampedValue,
+	surround_angle: Option<CachedValue<f64>>,
+	unpausable: bool,
 	effect_slots: StaticIndexMap<EffectId, EffectSlot>,
-	input: Frame,
+	input: Vec<Frame>,
+	// scratch space for effects to store their unaffected input
+	dry: Vec<Frame>,
 }
 
 impl Track {
 	pub fn new_main_track() -> Self {
 		Self {
 			kind: TrackKind::Main,
-			volume: CachedValue::new(Value::Fixed(1.0), 1.0),
+			volume: RampedValue::new(CachedValue::new(Value::Fixed(1.0), 1.0)),
+			surround_angle: None,
+			unpausable: false,
 			effect_slots: StaticIndexMap::new(MAIN_TRACK_NUM_EFFECTS),
-			input: Frame::from_mono(0.0),
+			input: vec![Frame::from_mono(0.0); MAX_BLOCK_SIZE],
+			dry: vec![Frame::from_mono(0.0); MAX_BLOCK_SIZE],
 		}
 	}
 
@@ -164,18 +180,28 @@ impl Track {
 				parent_track: settings.parent_track,
 				sends: settings.sends.to_map(),
 			},
-			volume: CachedValue::new(settings.volume, 1.0),
+			volume: RampedValue::new(CachedValue::new(settings.volume, 1.0)),
+			surround_angle: settings
+				.surround_angle
+				.map(|angle| CachedValue::new(angle, 0.0)),
+			unpausable: settings.unpausable,
 			effect_slots: StaticIndexMap::new(settings.num_effects),
-			input: Frame::from_mono(0.0),
+			input: vec![Frame::from_mono(0.0); MAX_BLOCK_SIZE],
+			dry: vec![Frame::from_mono(0.0); MAX_BLOCK_SIZE],
 		}
 	}
 
 	pub fn new_send_track(id: SendTrackId, settings: SendTrackSettings) -> Self {
 		Self {
 			kind: TrackKind::Send { id },
-			volume: CachedValue::new(settings.volume, 1.0),
+			volume: RampedValue::new(CachedValue::new(settings.volume, 1.0)),
+			surround_angle: settings
+				.surround_angle
+				.map(|angle| CachedValue::new(angle, 0.0)),
+			unpausable: false,
 			effect_slots: StaticIndexMap::new(settings.num_effects),
-			input: Frame::from_mono(0.0),
+			input: vec![Frame::from_mono(0.0); MAX_BLOCK_SIZE],
+			dry: vec![Frame::from_mono(0.0); MAX_BLOCK_SIZE],
 		}
 	}
 
@@ -187,6 +213,10 @@ impl Track {
 		}
 	}
 
+	pub fn unpausable(&self) -> bool {
+		self.unpausable
+	}
+
 	pub fn kind(&self) -> &TrackKind {
 		&self.kind
 	}
@@ -195,40 +225,119 @@ impl Track {
 		self.volume.set(volume);
 	}
 
+	/// Returns the volume of the track as of the end of the
+	/// last block it processed.
+	pub fn volume(&self) -> f64 {
+		self.volume.value()
+	}
+
+	/// Returns the volume of the track for each frame of the
+	/// last block it processed.
+	pub fn volume_ramp(&self, num_frames: usize) -> impl Iterator<Item = f32> {
+		self.volume.ramp(num_frames)
+	}
+
+	pub fn set_surround_angle(&mut self, surround_angle: Option<Value<f64>>) {
+		self.surround_angle = surround_angle.map(|angle| CachedValue::new(angle, 0.0));
+	}
+
+	/// Returns the angle to place the track's output at in a
+	/// surround channel layout, if it has one.
+	pub fn surround_angle(&self) -> Option<f64> {
+		self.surround_angle.as_ref().map(|angle| angle.value())
+	}
+
+	/// Adds an effect to the track. Returns `false` if the track
+	/// already has the maximum number of effects.
 	pub fn add_effect(
 		&mut self,
 		id: EffectId,
 		effect: Owned<Box<dyn Effect>>,
 		settings: EffectSettings,
-	) {
-		let effect_slot = EffectSlot::new(effect, settings);
-		self.effect_slots.try_insert(id, effect_slot).ok();
+		sample_rate: u32,
+	) -> bool {
+		let effect_slot = EffectSlot::new(effect, settings, sample_rate);
+		self.effect_slots.try_insert(id, effect_slot).is_ok()
+	}
+
+	/// Reinitializes the track's effects if they were initialized
+	/// with a different sample rate.
+	pub fn set_sample_rate(&mut self, sample_rate: u32) {
+		for (_, effect_slot) in &mut self.effect_slots {
+			effect_slot.set_sample_rate(sample_rate);
+		}
 	}
 
 	pub fn effect_mut(&mut self, id: EffectId) -> Option<&mut EffectSlot> {
 		self.effect_slots.get_mut(&id)
 	}
 
-	pub fn remove_effect(&mut self, id: EffectId) {
-		self.effect_slots.remove(&id);
+	pub fn remove_effect(&mut self, id: EffectId) -> Option<EffectSlot> {
+		self.effect_slots.remove(&id)
 	}
 
-	pub fn add_input(&mut self, input: Frame) {
-		self.input += input;
+	/// Adds audio to the frame at `frame_index` in the current block.
+	pub fn add_input(&mut self, frame_index: usize, input: Frame) {
+		self.input[frame_index] += input;
 	}
 
-	pub fn process(&mut self, dt: f64, parameters: &Parameters) -> Frame {
+	/// Adds a block of audio to the current block.
+	pub fn add_input_block(&mut self, input: &[Frame]) {
+		for (frame, input) in self.input.iter_mut().zip(input.iter()) {
+			*frame += *input;
+		}
+	}
+
+	/// Adds a block of audio to the current block, scaled by
+	/// a volume that's ramped over the block.
+	pub fn add_input_block_with_volume(&mut self, input: &[Frame], volume: &RampedValue) {
+		for ((frame, input), volume) in self
+			.input
+			.iter_mut()
+			.zip(input.iter())
+			.zip(volume.ramp(input.len()))
+		{
+			*frame += *input * volume;
+		}
+	}
+
+	/// Runs the input through the track's effects and writes
+	/// the result to `out`.
+	///
+	/// The number of frames processed is the length of `out`,
+	/// which can be at most [`MAX_BLOCK_SIZE`].
+	pub fn process(&mut self, dt: f64, parameters: &Parameters, out: &mut [Frame]) {
 		self.volume.update(parameters);
+		if let Some(surround_angle) = &mut self.surround_angle {
+			surround_angle.update(parameters);
+		}
 		if let TrackKind::Sub { sends, .. } = &mut self.kind {
 			for (_, volume) in sends {
 				volume.update(parameters);
 			}
 		}
-		let mut input = self.input;
-		self.input = Frame::from_mono(0.0);
+		let num_frames = out.len();
+		let input = &mut self.input[..num_frames];
 		for (_, effect_slot) in &mut self.effect_slots {
-			input = effect_slot.process(dt, input, parameters);
+			effect_slot.process_block(dt, input, &mut self.dry[..num_frames], parameters);
+		}
+		for ((out, input), volume) in out
+			.iter_mut()
+			.zip(input.iter_mut())
+			.zip(self.volume.ramp(num_frames))
+		{
+			*out = *input * volume;
+			*input = Frame::from_mono(0.0);
+		}
+	}
+
+	/// Reports any effects on this track that have panicked
+	/// since the last time this was called.
+	pub fn report_panicked_effects(&mut self, diagnostics: &mut DiagnosticProducer) {
+		for (id, effect_slot) in &mut self.effect_slots {
+			if effect_slot.take_panic() {
+				diagnostics.report(Diagnostic::EffectPanicked(*id));
+			}
 		}
-		input * (self.volume.value() as f32)
 	}
 }
@@ -1,10 +1,14 @@
 use indexmap::IndexMap;
 
-use crate::{CachedValue, Value};
+use crate::{mixer::ramped_value::RampedValue, CachedValue, Value};
 
 use super::SendTrackId;
 
 /// A mapping of send tracks to volume levels.
+///
+/// Like track volumes, send volumes linked to a parameter are
+/// read once per block of up to 128 frames and ramped smoothly
+/// to their new value over the block.
 #[derive(Debug, Clone)]
 #[cfg_attr(
 	feature = "serde_support",
@@ -40,10 +44,10 @@ impl TrackSends {
 		self
 	}
 
-	pub(crate) fn to_map(&self) -> IndexMap<SendTrackId, CachedValue<f64>> {
+	pub(crate) fn to_map(&self) -> IndexMap<SendTrackId, RampedValue> {
 		let mut map = IndexMap::new();
 		for (id, volume) in self.iter() {
-			map.insert(*id, CachedValue::new(*volume, 1.0));
+			map.insert(*id, RampedValue::new(CachedValue::new(*volume, 1.0)));
 		}
 		map
 	}
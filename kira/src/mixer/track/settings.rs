@@ -18,9 +18,32 @@ pub struct SubTrackSettings {
 	/// addition to the parent track).
 	pub sends: TrackSends,
 	/// The volume of the track.
+	///
+	/// If this is linked to a parameter, it's read once per block
+	/// of up to 128 frames and ramped smoothly over the block.
 	pub volume: Value<f64>,
+	/// Where to place the track's output around the listener
+	/// (in degrees) when using a surround
+	/// [`ChannelLayout`](crate::channel_layout::ChannelLayout).
+	///
+	/// 0 degrees is directly in front of the listener, and positive
+	/// angles are to the right. The track's output is mixed down to
+	/// mono and panned between the nearest speakers. Positioned
+	/// tracks are mixed into the output after the main track's
+	/// effects, but the main track's volume still applies to them.
+	///
+	/// This only affects tracks that output directly to the main
+	/// track. If `None`, the track's output is sent to the main
+	/// track in stereo.
+	pub surround_angle: Option<Value<f64>>,
 	/// The maximum number of effects this track can hold.
 	pub num_effects: usize,
+	/// Whether instances and streams on this track (or any of
+	/// its child tracks) should keep playing while the audio
+	/// manager is paused.
+	///
+	/// See [`AudioManager::set_paused`](crate::manager::AudioManager::set_paused).
+	pub unpausable: bool,
 }
 
 impl SubTrackSettings {
@@ -59,6 +82,15 @@ impl SubTrackSettings {
 		}
 	}
 
+	/// Sets where to place the track's output around the listener
+	/// (in degrees) when using a surround channel layout.
+	pub fn surround_angle(self, surround_angle: impl Into<Value<f64>>) -> Self {
+		Self {
+			surround_angle: Some(surround_angle.into()),
+			..self
+		}
+	}
+
 	/// Sets the maximum number of effects this track can hold.
 	pub fn num_effects(self, num_effects: usize) -> Self {
 		Self {
@@ -66,6 +98,15 @@ impl SubTrackSettings {
 			..self
 		}
 	}
+
+	/// Makes instances and streams on this track keep playing
+	/// while the audio manager is paused.
+	pub fn unpausable(self) -> Self {
+		Self {
+			unpausable: true,
+			..self
+		}
+	}
 }
 
 impl Default for SubTrackSettings {
@@ -75,7 +116,9 @@ impl Default for SubTrackSettings {
 			parent_track: TrackIndex::Main,
 			sends: TrackSends::new(),
 			volume: Value::Fixed(1.0),
+			surround_angle: None,
 			num_effects: 10,
+			unpausable: false,
 		}
 	}
 }
@@ -91,7 +134,24 @@ pub struct SendTrackSettings {
 	/// The unique identifier for the track.
 	pub id: Option<SendTrackId>,
 	/// The volume of the track.
+	///
+	/// If this is linked to a parameter, it's read once per block
+	/// of up to 128 frames and ramped smoothly over the block.
 	pub volume: Value<f64>,
+	/// Where to place the track's output around the listener
+	/// (in degrees) when using a surround
+	/// [`ChannelLayout`](crate::channel_layout::ChannelLayout).
+	///
+	/// 0 degrees is directly in front of the listener, and positive
+	/// angles are to the right. The track's output is mixed down to
+	/// mono and panned between the nearest speakers. Positioned
+	/// tracks are mixed into the output after the main track's
+	/// effects, but the main track's volume still applies to them.
+	///
+	/// This only affects tracks that output directly to the main
+	/// track. If `None`, the track's output is sent to the main
+	/// track in stereo.
+	pub surround_angle: Option<Value<f64>>,
 	/// The maximum number of effects this track can hold.
 	pub num_effects: usize,
 }
@@ -118,6 +178,15 @@ impl SendTrackSettings {
 		}
 	}
 
+	/// Sets where to place the track's output around the listener
+	/// (in degrees) when using a surround channel layout.
+	pub fn surround_angle(self, surround_angle: impl Into<Value<f64>>) -> Self {
+		Self {
+			surround_angle: Some(surround_angle.into()),
+			..self
+		}
+	}
+
 	/// Sets the maximum number of effects this track can hold.
 	pub fn num_effects(self, num_effects: usize) -> Self {
 		Self {
@@ -132,6 +201,7 @@ impl Default for SendTrackSettings {
 		Self {
 			id: None,
 			volume: Value::Fixed(1.0),
+			surround_angle: None,
 			num_effects: 10,
 		}
 	}
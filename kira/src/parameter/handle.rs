@@ -32,4 +32,20 @@ impl ParameterHandle {
 		self.command_producer
 			.push(ParameterCommand::SetParameter(self.id, value, tween.into()).into())
 	}
+
+	/// Sets the parameter to a value with an optional tween
+	/// when the audio clock reaches the given time.
+	///
+	/// See [`AudioManager::clock_time`](crate::manager::AudioManager::clock_time).
+	pub fn set_at(
+		&mut self,
+		value: f64,
+		tween: impl Into<Option<Tween>>,
+		clock_time: u64,
+	) -> Result<(), CommandError> {
+		self.command_producer.push_scheduled(
+			ParameterCommand::SetParameter(self.id, value, tween.into()),
+			clock_time,
+		)
+	}
 }
@@ -1,4 +1,4 @@
-use uuid::Uuid;
+use crate::static_container::slot_map::{SlotIndex, SlotKey};
 
 use super::{handle::ParameterHandle, tween::Tween};
 
@@ -10,14 +10,16 @@ use super::{handle::ParameterHandle, tween::Tween};
 	serde(transparent)
 )]
 pub struct ParameterId {
-	uuid: Uuid,
+	index: SlotIndex,
 }
 
-impl ParameterId {
-	pub(crate) fn new() -> Self {
-		Self {
-			uuid: Uuid::new_v4(),
-		}
+impl SlotKey for ParameterId {
+	fn from_slot_index(index: SlotIndex) -> Self {
+		Self { index }
+	}
+
+	fn slot_index(&self) -> SlotIndex {
+		self.index
 	}
 }
 
@@ -109,6 +111,7 @@ impl Parameter {
 			});
 		} else {
 			self.value = target;
+			self.tween_state = None;
 		}
 	}
 
@@ -1,7 +1,8 @@
 use crate::{
 	command::ParameterCommand,
+	manager::diagnostics::{Diagnostic, DiagnosticProducer, ResourceId},
 	parameter::{Parameter, ParameterId},
-	static_container::index_map::StaticIndexMap,
+	static_container::slot_map::StaticSlotMap,
 };
 
 /// A collection of all of the currently active parameters.
@@ -10,28 +11,48 @@ use crate::{
 /// if you're writing your own effects.
 #[derive(Debug, Clone)]
 pub struct Parameters {
-	parameters: StaticIndexMap<ParameterId, Parameter>,
+	parameters: StaticSlotMap<ParameterId, Parameter>,
 }
 
 impl Parameters {
 	pub(crate) fn new(capacity: usize) -> Self {
 		Self {
-			parameters: StaticIndexMap::new(capacity),
+			parameters: StaticSlotMap::new(capacity),
 		}
 	}
 
+	/// Moves all of the parameters into `other`, which has a larger
+	/// capacity, and swaps the two so that the old (now empty)
+	/// storage can be freed on another thread.
+	pub(crate) fn grow_into(&mut self, other: &mut Self) {
+		self.parameters.move_into(&mut other.parameters);
+		std::mem::swap(self, other);
+	}
+
 	pub(crate) fn get(&self, id: ParameterId) -> Option<&Parameter> {
 		self.parameters.get(&id)
 	}
 
-	pub(crate) fn run_command(&mut self, command: ParameterCommand) {
+	pub(crate) fn run_command(
+		&mut self,
+		command: ParameterCommand,
+		diagnostics: &mut DiagnosticProducer,
+	) {
 		match command {
 			ParameterCommand::AddParameter(id, value) => {
-				self.parameters.try_insert(id, Parameter::new(value)).ok();
+				if self
+					.parameters
+					.try_insert(id, Parameter::new(value))
+					.is_err()
+				{
+					diagnostics.report(Diagnostic::ResourceLimitReached(ResourceId::Parameter(id)));
+				}
 			}
 			ParameterCommand::SetParameter(id, value, tween) => {
 				if let Some(parameter) = self.parameters.get_mut(&id) {
 					parameter.set(value, tween);
+				} else {
+					diagnostics.report(Diagnostic::ResourceNotFound(ResourceId::Parameter(id)));
 				}
 			}
 			ParameterCommand::RemoveParameter(id) => {
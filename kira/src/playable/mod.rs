@@ -77,12 +77,33 @@ impl<'a> Playable<'a> {
 		}
 	}
 
+	pub fn default_loop_end(&self) -> Option<f64> {
+		match self {
+			Playable::Sound(sound) => sound.default_loop_end(),
+			Playable::Arrangement(_) => None,
+		}
+	}
+
+	pub fn is_streamed(&self) -> bool {
+		match self {
+			Playable::Sound(sound) => sound.is_streamed(),
+			Playable::Arrangement(_) => false,
+		}
+	}
+
 	pub fn is_in_group(&self, id: GroupId, all_groups: &Groups) -> bool {
 		match self {
 			Playable::Sound(sound) => sound.is_in_group(id, all_groups),
 			Playable::Arrangement(arrangement) => arrangement.is_in_group(id, all_groups),
 		}
 	}
+
+	pub fn is_unpausable(&self, all_groups: &Groups) -> bool {
+		match self {
+			Playable::Sound(sound) => sound.is_unpausable(all_groups),
+			Playable::Arrangement(arrangement) => arrangement.is_unpausable(all_groups),
+		}
+	}
 }
 
 pub(crate) enum PlayableMut<'a> {
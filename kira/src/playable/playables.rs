@@ -3,7 +3,12 @@ use basedrop::Owned;
 use crate::{
 	arrangement::{Arrangement, ArrangementId},
 	command::ResourceCommand,
-	sound::{Sound, SoundId},
+	group::groups::Groups,
+	manager::{
+		backend::mixer::Mixer,
+		diagnostics::{Diagnostic, DiagnosticProducer, ResourceId},
+	},
+	sound::{InterpolationMode, SampleCursor, Sound, SoundId},
 	static_container::index_map::StaticIndexMap,
 	Frame,
 };
@@ -23,6 +28,15 @@ impl Playables {
 		}
 	}
 
+	/// Moves all of the sounds and arrangements into `other`, which has a larger
+	/// capacity, and swaps the two so that the old (now empty)
+	/// storage can be freed on another thread.
+	pub fn grow_into(&mut self, other: &mut Self) {
+		self.sounds.move_into(&mut other.sounds);
+		self.arrangements.move_into(&mut other.arrangements);
+		std::mem::swap(self, other);
+	}
+
 	pub fn sound(&self, id: SoundId) -> Option<&Owned<Sound>> {
 		self.sounds.get(&id)
 	}
@@ -53,29 +67,52 @@ impl Playables {
 		}
 	}
 
-	pub fn frame_at_position(&self, id: PlayableId, position: f64) -> Option<Frame> {
+	/// Gets the frame at the given position of a sound or
+	/// arrangement. If `interpolation_mode` is `None`, each
+	/// sound's own interpolation mode is used.
+	///
+	/// Sounds continue decoding from where the last read using
+	/// `cursor` left off when possible. Arrangements read from
+	/// several sounds, so they don't use the cursor.
+	pub fn frame_at_position(
+		&self,
+		id: PlayableId,
+		position: f64,
+		interpolation_mode: Option<InterpolationMode>,
+		cursor: &mut SampleCursor,
+	) -> Option<Frame> {
 		match id {
-			PlayableId::Sound(id) => self
-				.sound(id)
-				.map(|sound| sound.get_frame_at_position(position)),
-			PlayableId::Arrangement(id) => self
-				.arrangement(id)
-				.map(|arrangement| arrangement.get_frame_at_position(position, &self.sounds)),
+			PlayableId::Sound(id) => self.sound(id).map(|sound| {
+				sound.frame_at_position_with_cursor(
+					position,
+					interpolation_mode.unwrap_or_else(|| sound.interpolation_mode()),
+					cursor,
+				)
+			}),
+			PlayableId::Arrangement(id) => self.arrangement(id).map(|arrangement| {
+				arrangement.get_frame_at_position(position, &self.sounds, interpolation_mode)
+			}),
 		}
 	}
 
-	pub fn run_command(&mut self, command: ResourceCommand) {
+	pub fn run_command(&mut self, command: ResourceCommand, diagnostics: &mut DiagnosticProducer) {
 		match command {
 			ResourceCommand::AddSound(sound) => {
-				self.sounds.try_insert(sound.id(), sound).ok();
+				let id = sound.id();
+				if self.sounds.try_insert(id, sound).is_err() {
+					diagnostics.report(Diagnostic::ResourceLimitReached(ResourceId::Sound(id)));
+				}
 			}
 			ResourceCommand::RemoveSound(id) => {
 				self.sounds.remove(&id);
 			}
 			ResourceCommand::AddArrangement(arrangement) => {
-				self.arrangements
-					.try_insert(arrangement.id(), arrangement)
-					.ok();
+				let id = arrangement.id();
+				if self.arrangements.try_insert(id, arrangement).is_err() {
+					diagnostics.report(Diagnostic::ResourceLimitReached(ResourceId::Arrangement(
+						id,
+					)));
+				}
 			}
 			ResourceCommand::RemoveArrangement(id) => {
 				self.arrangements.remove(&id);
@@ -83,12 +120,26 @@ impl Playables {
 		}
 	}
 
-	pub fn update(&mut self, dt: f64) {
+	/// Updates the cooldown timers of each sound and arrangement.
+	///
+	/// While the audio manager is paused, only the timers of
+	/// unpausable sounds and arrangements are updated.
+	pub fn update(&mut self, dt: f64, paused: bool, groups: &Groups, mixer: &Mixer) {
 		for (_, sound) in &mut self.sounds {
-			sound.update_cooldown(dt);
+			if !paused
+				|| sound.is_unpausable(groups)
+				|| mixer.is_track_unpausable(sound.default_track())
+			{
+				sound.update_cooldown(dt);
+			}
 		}
 		for (_, arrangement) in &mut self.arrangements {
-			arrangement.update_cooldown(dt);
+			if !paused
+				|| arrangement.is_unpausable(groups)
+				|| mixer.is_track_unpausable(arrangement.default_track())
+			{
+				arrangement.update_cooldown(dt);
+			}
 		}
 	}
 }
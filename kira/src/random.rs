@@ -0,0 +1,39 @@
+//! Random number generation that can be made deterministic.
+//!
+//! While the backend is producing audio, it lends its seeded random
+//! number generator to the current thread, so every random decision
+//! made on the audio thread comes from that generator. Outside of
+//! that (for example, on the main thread), `thread_rng` is used.
+
+use std::{cell::RefCell, ops::Range};
+
+use rand::{rngs::StdRng, thread_rng, Rng};
+
+thread_local! {
+	static AUDIO_THREAD_RNG: RefCell<Option<StdRng>> = const { RefCell::new(None) };
+}
+
+/// Swaps the given random number generator with the one
+/// used by the current thread.
+///
+/// The backend calls this before and after producing audio,
+/// so calling it twice puts everything back the way it was.
+pub(crate) fn swap_rng(rng: &mut Option<StdRng>) {
+	AUDIO_THREAD_RNG.with(|cell| std::mem::swap(&mut *cell.borrow_mut(), rng));
+}
+
+/// Picks a random number within the given range.
+pub(crate) fn gen_range(range: Range<f64>) -> f64 {
+	AUDIO_THREAD_RNG.with(|cell| match &mut *cell.borrow_mut() {
+		Some(rng) => rng.gen_range(range),
+		None => thread_rng().gen_range(range),
+	})
+}
+
+/// Generates a random seed for another random number generator.
+pub(crate) fn gen_seed() -> u64 {
+	AUDIO_THREAD_RNG.with(|cell| match &mut *cell.borrow_mut() {
+		Some(rng) => rng.gen(),
+		None => thread_rng().gen(),
+	})
+}
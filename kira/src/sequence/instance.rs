@@ -1,13 +1,15 @@
 use std::sync::{atomic::Ordering, Arc};
 
+use crate::id_counter::{IdCounter, IdTooLarge};
 use atomic::Atomic;
-use rand::{thread_rng, Rng};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use ringbuf::Producer;
-use uuid::Uuid;
 
 use crate::{
 	group::{groups::Groups, GroupId},
+	instance::{InstanceId, InstanceSlots},
 	metronome::{MetronomeId, Metronomes},
+	random,
 	static_container::vec::StaticVec,
 	Tempo,
 };
@@ -22,15 +24,24 @@ use super::{RawSequence, SequenceInstanceHandle, SequenceOutputCommand, Sequence
 	serde(transparent)
 )]
 pub struct SequenceInstanceId {
-	uuid: Uuid,
+	index: u64,
 }
 
+static NEXT_SEQUENCE_INSTANCE_ID: IdCounter = IdCounter::new();
+
 impl SequenceInstanceId {
 	pub(crate) fn new() -> Self {
 		Self {
-			uuid: Uuid::new_v4(),
+			index: NEXT_SEQUENCE_INSTANCE_ID.next(),
 		}
 	}
+
+	/// Makes sure [`SequenceInstanceId::new`] never returns this ID,
+	/// since it was chosen by the user.
+	pub(crate) fn claim(self) -> Result<Self, IdTooLarge> {
+		NEXT_SEQUENCE_INSTANCE_ID.claim(self.index)?;
+		Ok(self)
+	}
 }
 
 impl<CustomEvent> From<&SequenceInstanceHandle<CustomEvent>> for SequenceInstanceId {
@@ -62,6 +73,16 @@ pub struct SequenceInstance {
 	wait_timer: Option<f64>,
 	muted: bool,
 	event_producer: Producer<usize>,
+	seed: Option<u64>,
+	// picks sounds for `play_random` steps
+	rng: StdRng,
+	// the IDs the sequence uses to refer to the instances it
+	// plays, and the IDs of the instances that were actually
+	// started on the current loop
+	instance_ids: Vec<(InstanceId, Option<InstanceId>)>,
+	// whether the sequence is in an unpausable group. this is
+	// set when the sequence instance is started on the audio thread
+	in_unpausable_group: bool,
 }
 
 impl SequenceInstance {
@@ -69,7 +90,22 @@ impl SequenceInstance {
 		sequence: RawSequence,
 		event_producer: Producer<usize>,
 		metronome: Option<MetronomeId>,
+		seed: Option<u64>,
 	) -> Self {
+		let mut instance_ids: Vec<(InstanceId, Option<InstanceId>)> = vec![];
+		for step in &sequence.steps {
+			let id = match step {
+				SequenceStep::RunCommand(SequenceOutputCommand::PlaySound(_, id, _)) => *id,
+				SequenceStep::PlayRandom(_, id, _) => *id,
+				_ => continue,
+			};
+			if !instance_ids
+				.iter()
+				.any(|(sequence_id, _)| *sequence_id == id)
+			{
+				instance_ids.push((id, None));
+			}
+		}
 		Self {
 			sequence,
 			metronome,
@@ -79,6 +115,12 @@ impl SequenceInstance {
 			wait_timer: None,
 			muted: false,
 			event_producer,
+			seed,
+			// if there's no seed, the generator is seeded when
+			// the sequence instance starts on the audio thread
+			rng: StdRng::seed_from_u64(seed.unwrap_or_default()),
+			instance_ids,
+			in_unpausable_group: false,
 		}
 	}
 
@@ -100,7 +142,12 @@ impl SequenceInstance {
 				self.wait_timer = None;
 			}
 		} else if let Some(loop_point) = self.sequence.loop_point {
-			self.sequence.update_instance_ids();
+			// instances played on the next loop get new IDs, so
+			// instances from this loop aren't affected by commands
+			// on the next loop
+			for (_, instance_id) in &mut self.instance_ids {
+				*instance_id = None;
+			}
 			self.start_step(loop_point);
 		} else {
 			self.set_state(SequenceInstanceState::Finished);
@@ -108,6 +155,9 @@ impl SequenceInstance {
 	}
 
 	pub(crate) fn start(&mut self) {
+		if self.seed.is_none() {
+			self.rng = StdRng::seed_from_u64(random::gen_seed());
+		}
 		self.start_step(0);
 	}
 
@@ -131,11 +181,57 @@ impl SequenceInstance {
 		self.set_state(SequenceInstanceState::Finished);
 	}
 
+	/// Sends a command to the backend, replacing the ID the
+	/// sequence uses for an instance with the instance's real ID.
+	fn run_command(
+		&mut self,
+		mut command: SequenceOutputCommand,
+		output_command_queue: &mut StaticVec<SequenceOutputCommand>,
+		instance_slots: &InstanceSlots,
+	) {
+		if let SequenceOutputCommand::PlaySound(playable, id, settings) = command {
+			let slot = instance_slots.claim(None);
+			// if the command can't be sent, the slot is freed
+			// when the claim is dropped
+			if output_command_queue
+				.try_push(SequenceOutputCommand::PlaySound(
+					playable,
+					slot.id(),
+					settings,
+				))
+				.is_ok()
+			{
+				// the backend takes back the claim when it
+				// creates the instance
+				let instance_id = slot.into_id();
+				if let Some((_, current_id)) = self
+					.instance_ids
+					.iter_mut()
+					.find(|(sequence_id, _)| *sequence_id == id)
+				{
+					*current_id = Some(instance_id);
+				}
+			}
+			return;
+		}
+		if let Some(id) = command.instance_id_mut() {
+			if let Some((_, Some(instance_id))) = self
+				.instance_ids
+				.iter()
+				.find(|(sequence_id, _)| sequence_id == id)
+			{
+				*id = *instance_id;
+			}
+		}
+		output_command_queue.try_push(command).ok();
+	}
+
 	pub(crate) fn update(
 		&mut self,
 		dt: f64,
 		metronomes: &Metronomes,
 		output_command_queue: &mut StaticVec<SequenceOutputCommand>,
+		instance_slots: &InstanceSlots,
 	) {
 		let metronome = self.metronome.map(|id| metronomes.get(id)).flatten();
 		loop {
@@ -171,20 +267,20 @@ impl SequenceInstance {
 							}
 							SequenceStep::RunCommand(command) => {
 								if !self.muted {
-									output_command_queue.try_push(*command).ok();
+									let command = *command;
+									self.run_command(command, output_command_queue, instance_slots);
 								}
 								self.start_step(self.position + 1);
 							}
 							SequenceStep::PlayRandom(choices, id, settings) => {
 								if !self.muted {
-									let choice_index = thread_rng().gen_range(0..choices.len());
-									output_command_queue
-										.try_push(SequenceOutputCommand::PlaySound(
-											choices[choice_index],
-											*id,
-											*settings,
-										))
-										.ok();
+									let choice_index = self.rng.gen_range(0..choices.len());
+									let command = SequenceOutputCommand::PlaySound(
+										choices[choice_index],
+										*id,
+										*settings,
+									);
+									self.run_command(command, output_command_queue, instance_slots);
 								}
 								self.start_step(self.position + 1);
 							}
@@ -212,4 +308,12 @@ impl SequenceInstance {
 	pub(crate) fn is_in_group(&self, parent_id: GroupId, groups: &Groups) -> bool {
 		self.sequence.is_in_group(parent_id, groups)
 	}
+
+	pub(crate) fn in_unpausable_group(&self) -> bool {
+		self.in_unpausable_group
+	}
+
+	pub(crate) fn set_in_unpausable_group(&mut self, groups: &Groups) {
+		self.in_unpausable_group = self.sequence.is_unpausable(groups);
+	}
 }
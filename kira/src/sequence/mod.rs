@@ -184,6 +184,8 @@ use crate::{
 	metronome::MetronomeId,
 	parameter::{tween::Tween, ParameterId},
 	playable::PlayableId,
+	sound::SoundId,
+	static_container::slot_map::{SlotIndex, SlotKey},
 	Duration, Tempo, Value,
 };
 
@@ -201,6 +203,13 @@ pub struct SequenceInstanceSettings {
 	pub metronome: Option<MetronomeId>,
 	/// How many events can be queued at a time.
 	pub event_queue_capacity: usize,
+	/// The seed for the random number generator used to pick
+	/// sounds for [`play_random`](Sequence::play_random) steps.
+	///
+	/// If `None`, the seed is picked by the audio thread's random
+	/// number generator, which can be seeded with
+	/// [`AudioManagerSettings::seed`](crate::manager::AudioManagerSettings::seed).
+	pub seed: Option<u64>,
 }
 
 impl SequenceInstanceSettings {
@@ -232,6 +241,15 @@ impl SequenceInstanceSettings {
 			..self
 		}
 	}
+
+	/// Sets the seed for the random number generator used to
+	/// pick sounds for [`play_random`](Sequence::play_random) steps.
+	pub fn seed(self, seed: u64) -> Self {
+		Self {
+			seed: Some(seed),
+			..self
+		}
+	}
 }
 
 impl Default for SequenceInstanceSettings {
@@ -240,6 +258,7 @@ impl Default for SequenceInstanceSettings {
 			id: None,
 			metronome: None,
 			event_queue_capacity: 10,
+			seed: None,
 		}
 	}
 }
@@ -290,6 +309,23 @@ pub(crate) enum SequenceStep<CustomEvent: Clone + Eq + Hash> {
 	EmitCustomEvent(CustomEvent),
 }
 
+impl SequenceOutputCommand {
+	/// Returns the ID of the instance this command plays or
+	/// controls, if any.
+	pub(crate) fn instance_id_mut(&mut self) -> Option<&mut InstanceId> {
+		match self {
+			Self::PlaySound(_, id, _)
+			| Self::SetInstanceVolume(id, _)
+			| Self::SetInstancePlaybackRate(id, _)
+			| Self::SetInstancePanning(id, _)
+			| Self::PauseInstance(id, _)
+			| Self::ResumeInstance(id, _)
+			| Self::StopInstance(id, _) => Some(id),
+			_ => None,
+		}
+	}
+}
+
 impl<CustomEvent: Clone + Eq + Hash> From<SequenceOutputCommand> for SequenceStep<CustomEvent> {
 	fn from(command: SequenceOutputCommand) -> Self {
 		Self::RunCommand(command)
@@ -393,13 +429,44 @@ impl<CustomEvent: Clone + Eq + Hash> Sequence<CustomEvent> {
 		self.loop_point = Some(self.steps.len())
 	}
 
+	/// Returns an ID that later steps can use to refer to an
+	/// instance this sequence plays.
+	///
+	/// Instances are given a new ID each time the sequence plays
+	/// them, so this ID only refers to the instance within the
+	/// sequence. It uses a slot past the end of the instance pool,
+	/// so it's never mistaken for the ID of a playing instance.
+	fn new_instance_id(&self) -> InstanceId {
+		let num_instances = self
+			.steps
+			.iter()
+			.filter(|step| {
+				matches!(
+					step,
+					SequenceStep::RunCommand(SequenceOutputCommand::PlaySound(..))
+						| SequenceStep::PlayRandom(..)
+				)
+			})
+			.count();
+		InstanceId::from_slot_index(SlotIndex {
+			index: u32::MAX,
+			generation: num_instances as u32,
+		})
+	}
+
 	/// Adds a step to play a sound or arrangement.
+	///
+	/// Returns an ID that later steps in the sequence can use to
+	/// control the instance. If the settings have an ID, that ID
+	/// is returned instead. Each time the step runs, the instance
+	/// is given a new ID, so instances started on earlier loops
+	/// keep playing and aren't affected by later steps.
 	pub fn play<P: Into<PlayableId>>(
 		&mut self,
 		playable: P,
 		settings: InstanceSettings,
 	) -> InstanceId {
-		let id = settings.id.unwrap_or(InstanceId::new());
+		let id = settings.id.unwrap_or_else(|| self.new_instance_id());
 		self.steps
 			.push(SequenceOutputCommand::PlaySound(playable.into(), id, settings).into());
 		id
@@ -407,12 +474,15 @@ impl<CustomEvent: Clone + Eq + Hash> Sequence<CustomEvent> {
 
 	/// Adds a step to play a random sound or arrangement from a
 	/// list of choices.
+	///
+	/// The returned ID works the same way as the one returned
+	/// by [`play`](Self::play).
 	pub fn play_random(
 		&mut self,
 		choices: Vec<PlayableId>,
 		settings: InstanceSettings,
 	) -> InstanceId {
-		let id = settings.id.unwrap_or(InstanceId::new());
+		let id = settings.id.unwrap_or_else(|| self.new_instance_id());
 		self.steps
 			.push(SequenceStep::PlayRandom(choices, id, settings).into());
 		id
@@ -606,6 +676,23 @@ impl<CustomEvent: Clone + Eq + Hash> Sequence<CustomEvent> {
 		}
 	}
 
+	/// Returns the IDs of the sounds this sequence can play.
+	pub(crate) fn sound_ids(&self) -> impl Iterator<Item = SoundId> + '_ {
+		self.steps
+			.iter()
+			.flat_map(|step| match step {
+				SequenceStep::RunCommand(SequenceOutputCommand::PlaySound(playable, ..)) => {
+					std::slice::from_ref(playable)
+				}
+				SequenceStep::PlayRandom(choices, ..) => choices.as_slice(),
+				_ => &[],
+			})
+			.filter_map(|playable| match playable {
+				PlayableId::Sound(id) => Some(*id),
+				PlayableId::Arrangement(_) => None,
+			})
+	}
+
 	/// Gets a set of all of the events this sequence can emit.
 	fn all_events(&self) -> IndexSet<CustomEvent> {
 		let mut events = IndexSet::new();
@@ -652,7 +739,12 @@ impl<CustomEvent: Clone + Eq + Hash> Sequence<CustomEvent> {
 		let (raw_sequence, events) = self.into_raw_sequence();
 		let (event_producer, event_consumer) =
 			RingBuffer::new(settings.event_queue_capacity).split();
-		let instance = SequenceInstance::new(raw_sequence, event_producer, settings.metronome);
+		let instance = SequenceInstance::new(
+			raw_sequence,
+			event_producer,
+			settings.metronome,
+			settings.seed,
+		);
 		let handle = SequenceInstanceHandle::new(
 			id,
 			instance.public_state(),
@@ -667,6 +759,11 @@ impl<CustomEvent: Clone + Eq + Hash> Sequence<CustomEvent> {
 	pub(crate) fn is_in_group(&self, id: GroupId, all_groups: &Groups) -> bool {
 		self.groups.has_ancestor(id, all_groups)
 	}
+
+	/// Returns if this sequence is in an unpausable group.
+	pub(crate) fn is_unpausable(&self, all_groups: &Groups) -> bool {
+		self.groups.is_unpausable(all_groups)
+	}
 }
 
 impl<CustomEvent: Clone + Eq + Hash> Default for Sequence<CustomEvent> {
@@ -680,80 +777,3 @@ impl<CustomEvent: Clone + Eq + Hash> Default for Sequence<CustomEvent> {
 }
 
 pub(crate) type RawSequence = Sequence<usize>;
-
-impl RawSequence {
-	fn convert_ids(steps: &mut Vec<SequenceStep<usize>>, old_id: InstanceId, new_id: InstanceId) {
-		for step in steps {
-			match step {
-				SequenceStep::RunCommand(command) => match command {
-					SequenceOutputCommand::PlaySound(_, id, _) => {
-						if *id == old_id {
-							*id = new_id;
-						}
-					}
-					SequenceOutputCommand::SetInstanceVolume(id, _) => {
-						if *id == old_id {
-							*id = new_id;
-						}
-					}
-					SequenceOutputCommand::SetInstancePlaybackRate(id, _) => {
-						if *id == old_id {
-							*id = new_id;
-						}
-					}
-					SequenceOutputCommand::SetInstancePanning(id, _) => {
-						if *id == old_id {
-							*id = new_id;
-						}
-					}
-					SequenceOutputCommand::PauseInstance(id, _) => {
-						if *id == old_id {
-							*id = new_id;
-						}
-					}
-					SequenceOutputCommand::ResumeInstance(id, _) => {
-						if *id == old_id {
-							*id = new_id;
-						}
-					}
-					SequenceOutputCommand::StopInstance(id, _) => {
-						if *id == old_id {
-							*id = new_id;
-						}
-					}
-					_ => {}
-				},
-				SequenceStep::PlayRandom(_, id, _) => {
-					if *id == old_id {
-						*id = new_id;
-					}
-				}
-				_ => {}
-			}
-		}
-	}
-
-	/// Assigns new instance IDs to each PlaySound command and updates
-	/// other sequence commands to use the new instance ID. This allows
-	/// the sequence to play sounds with fresh instance IDs on each loop
-	/// while still correctly pausing instances, setting their parameters,
-	/// etc.
-	fn update_instance_ids(&mut self) {
-		for i in 0..self.steps.len() {
-			match &self.steps[i] {
-				SequenceStep::RunCommand(command) => match command {
-					SequenceOutputCommand::PlaySound(_, id, _) => {
-						let old_id = *id;
-						Self::convert_ids(&mut self.steps, old_id, InstanceId::new());
-					}
-					_ => {}
-				},
-				SequenceStep::PlayRandom(_, id, _) => {
-					let old_id = *id;
-					Self::convert_ids(&mut self.steps, old_id, InstanceId::new());
-				}
-				_ => {}
-			}
-		}
-	}
-}
@@ -1,18 +1,25 @@
 //! An interface for controlling sounds.
 
+use std::sync::{Arc, Mutex, PoisonError};
+
+use basedrop::Handle;
+
 use crate::{
 	command::{
 		producer::{CommandError, CommandProducer},
 		InstanceCommand,
 	},
 	instance::{
-		handle::InstanceHandle, Instance, InstanceId, InstanceSettings, PauseInstanceSettings,
-		ResumeInstanceSettings, StopInstanceSettings,
+		handle::InstanceHandle, Instance, InstanceId, InstanceSettings, InstanceSlots,
+		PauseInstanceSettings, ResumeInstanceSettings, StopInstanceSettings,
 	},
 	mixer::TrackIndex,
 };
 
-use super::{Sound, SoundId};
+use super::{
+	streaming::{DecodingThread, StreamStarter},
+	Marker, Sound, SoundId,
+};
 
 /// Allows you to control a sound.
 #[derive(Debug, Clone)]
@@ -22,18 +29,39 @@ pub struct SoundHandle {
 	default_track: TrackIndex,
 	semantic_duration: Option<f64>,
 	default_loop_start: Option<f64>,
+	default_loop_end: Option<f64>,
+	markers: Vec<Marker>,
+	stream_starter: Option<StreamStarter>,
 	command_producer: CommandProducer,
+	instance_slots: Arc<Mutex<InstanceSlots>>,
 }
 
 impl SoundHandle {
-	pub(crate) fn new(sound: &Sound, command_producer: CommandProducer) -> Self {
+	pub(crate) fn new(
+		sound: &Sound,
+		command_producer: CommandProducer,
+		instance_slots: Arc<Mutex<InstanceSlots>>,
+		decoding_thread: DecodingThread,
+		collector_handle: Handle,
+	) -> Self {
 		Self {
 			id: sound.id(),
 			duration: sound.duration(),
 			default_track: sound.default_track(),
 			semantic_duration: sound.semantic_duration(),
 			default_loop_start: sound.default_loop_start(),
+			default_loop_end: sound.default_loop_end(),
+			markers: sound.markers().to_vec(),
+			stream_starter: sound.stream_source().map(|source| {
+				StreamStarter::new(
+					source.clone(),
+					sound.interpolation_mode(),
+					decoding_thread,
+					collector_handle,
+				)
+			}),
 			command_producer,
+			instance_slots,
 		}
 	}
 
@@ -66,26 +94,79 @@ impl SoundHandle {
 		self.default_loop_start
 	}
 
-	/// Plays the sound.
-	pub fn play(&mut self, settings: InstanceSettings) -> Result<InstanceHandle, CommandError> {
-		let id = settings.id.unwrap_or(InstanceId::new());
-		let instance = Instance::new(
-			self.id.into(),
+	/// Returns the default time (in seconds) looping instances
+	/// of this sound will jump back to the loop start point
+	/// from, if it isn't the end of the sound.
+	pub fn default_loop_end(&self) -> Option<f64> {
+		self.default_loop_end
+	}
+
+	/// Returns the markers in the sound, in order of position.
+	pub fn markers(&self) -> &[Marker] {
+		&self.markers
+	}
+
+	/// Returns the first marker with the given name, if there is one.
+	pub fn marker(&self, name: &str) -> Option<&Marker> {
+		self.markers
+			.iter()
+			.find(|marker| marker.name.as_deref() == Some(name))
+	}
+
+	fn create_instance(
+		&self,
+		settings: InstanceSettings,
+	) -> (InstanceId, Instance, InstanceHandle) {
+		let slot = self
+			.instance_slots
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.claim(settings.id);
+		let id = slot.id();
+		let settings = settings.into_internal(
 			self.duration,
-			None,
-			settings.into_internal(self.duration, self.default_loop_start, self.default_track),
+			self.default_loop_start,
+			self.default_loop_end,
+			self.default_track,
 		);
+		// instances of streamed sounds are decoded on the
+		// background thread shared by every streamed sound
+		let stream = self
+			.stream_starter
+			.as_ref()
+			.map(|stream_starter| stream_starter.start(&settings));
+		let instance = Instance::new(self.id.into(), self.duration, None, settings, stream, slot);
 		let handle = InstanceHandle::new(
 			id,
 			instance.public_state(),
 			instance.public_position(),
 			self.command_producer.clone(),
 		);
+		(id, instance, handle)
+	}
+
+	/// Plays the sound.
+	pub fn play(&mut self, settings: InstanceSettings) -> Result<InstanceHandle, CommandError> {
+		let (id, instance, handle) = self.create_instance(settings);
 		self.command_producer
 			.push(InstanceCommand::Play(id, instance).into())?;
 		Ok(handle)
 	}
 
+	/// Plays the sound when the audio clock reaches the given time.
+	///
+	/// See [`AudioManager::clock_time`](crate::manager::AudioManager::clock_time).
+	pub fn play_at(
+		&mut self,
+		settings: InstanceSettings,
+		clock_time: u64,
+	) -> Result<InstanceHandle, CommandError> {
+		let (id, instance, handle) = self.create_instance(settings);
+		self.command_producer
+			.push_scheduled(InstanceCommand::Play(id, instance), clock_time)?;
+		Ok(handle)
+	}
+
 	/// Pauses all instances of this sound.
 	pub fn pause(&mut self, settings: PauseInstanceSettings) -> Result<(), CommandError> {
 		self.command_producer
@@ -1,6 +1,6 @@
 use std::hash::Hash;
 
-use uuid::Uuid;
+use crate::id_counter::{IdCounter, IdTooLarge};
 
 use super::handle::SoundHandle;
 
@@ -12,15 +12,24 @@ use super::handle::SoundHandle;
 	serde(transparent)
 )]
 pub struct SoundId {
-	uuid: Uuid,
+	index: u64,
 }
 
+static NEXT_SOUND_ID: IdCounter = IdCounter::new();
+
 impl SoundId {
 	pub(crate) fn new() -> Self {
 		Self {
-			uuid: Uuid::new_v4(),
+			index: NEXT_SOUND_ID.next(),
 		}
 	}
+
+	/// Makes sure [`SoundId::new`] never returns this ID,
+	/// since it was chosen by the user.
+	pub(crate) fn claim(self) -> Result<Self, IdTooLarge> {
+		NEXT_SOUND_ID.claim(self.index)?;
+		Ok(self)
+	}
 }
 
 impl From<&SoundHandle> for SoundId {
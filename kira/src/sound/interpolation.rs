@@ -0,0 +1,125 @@
+//! Ways of estimating a sound's audio between samples.
+
+use std::f32::consts::PI;
+
+use crate::{util, Frame};
+
+/// The number of zero crossings on each side of the
+/// [`Sinc`](InterpolationMode::Sinc) kernel's center.
+const SINC_ZERO_CROSSINGS: usize = 8;
+
+/// The largest number of frames any interpolation mode uses.
+pub(crate) const MAX_FRAMES: usize = SINC_ZERO_CROSSINGS * 2;
+
+/// How a sound's audio is estimated between samples.
+///
+/// Whenever a sound plays at a different sample rate than it was
+/// recorded at, or at a playback rate other than 1, the playback
+/// position lands between samples, and the audio at that position
+/// has to be estimated from the samples around it. Modes that look
+/// at more samples sound smoother but take more CPU time.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub enum InterpolationMode {
+	/// Uses the sample at or before the playback position
+	/// without interpolating.
+	///
+	/// This gives a crunchy, lo-fi sound, which can be
+	/// nice for chiptune-style sound effects.
+	Nearest,
+	/// Blends linearly between the two samples around the
+	/// playback position.
+	///
+	/// This is cheap, so it's good for situations where
+	/// many sounds are playing at once, but it muffles high
+	/// frequencies a bit and can add some aliasing.
+	Linear,
+	/// Fits a cubic curve to the four samples around the
+	/// playback position.
+	///
+	/// This is a good balance between quality and speed.
+	Cubic,
+	/// Uses a windowed sinc filter over the sixteen samples around
+	/// the playback position.
+	///
+	/// This is the most expensive mode, but it reconstructs the
+	/// original audio most accurately, which makes a difference
+	/// when sounds with a lot of high frequency content (like
+	/// music) are played at a low playback rate.
+	Sinc,
+}
+
+// the crate supports Rust versions older than 1.62, which can't
+// derive `Default` for enums
+#[allow(clippy::derivable_impls)]
+impl Default for InterpolationMode {
+	fn default() -> Self {
+		Self::Cubic
+	}
+}
+
+impl InterpolationMode {
+	/// Returns the number of frames before the current one
+	/// that this mode uses.
+	pub(crate) fn num_frames_before(self) -> usize {
+		match self {
+			Self::Nearest | Self::Linear => 0,
+			Self::Cubic => 1,
+			Self::Sinc => SINC_ZERO_CROSSINGS - 1,
+		}
+	}
+
+	/// Returns the number of frames after the current one
+	/// that this mode uses.
+	pub(crate) fn num_frames_after(self) -> usize {
+		match self {
+			Self::Nearest => 0,
+			Self::Linear => 1,
+			Self::Cubic => 2,
+			Self::Sinc => SINC_ZERO_CROSSINGS,
+		}
+	}
+
+	/// Returns the total number of frames this mode uses.
+	pub(crate) fn num_frames(self) -> usize {
+		self.num_frames_before() + 1 + self.num_frames_after()
+	}
+
+	/// Estimates the audio at `fraction` of the way between the
+	/// current frame and the next one.
+	///
+	/// `frames` should contain [`num_frames`](Self::num_frames)
+	/// consecutive frames, starting [`num_frames_before`](Self::num_frames_before)
+	/// frames before the current one.
+	pub(crate) fn interpolate(self, frames: &[Frame], fraction: f32) -> Frame {
+		match self {
+			Self::Nearest => frames[0],
+			Self::Linear => frames[0] + (frames[1] - frames[0]) * fraction,
+			Self::Cubic => {
+				util::interpolate_frame(frames[0], frames[1], frames[2], frames[3], fraction)
+			}
+			Self::Sinc => {
+				// sin(pi * (fraction - offset)) only changes sign from one
+				// frame to the next, so it only has to be calculated once
+				let sin = (PI * fraction).sin();
+				let mut out = Frame::from_mono(0.0);
+				for (i, frame) in frames.iter().enumerate() {
+					let offset = i as i32 - (SINC_ZERO_CROSSINGS - 1) as i32;
+					let x = fraction - offset as f32;
+					let sign = if offset % 2 == 0 { 1.0 } else { -1.0 };
+					// x can be 0 when the fraction is 0 (or rounded up to 1)
+					let sinc = if x == 0.0 { 1.0 } else { sign * sin / (PI * x) };
+					let window_position = x / SINC_ZERO_CROSSINGS as f32;
+					let window = 0.42
+						+ 0.5 * (PI * window_position).cos()
+						+ 0.08 * (2.0 * PI * window_position).cos();
+					out += *frame * (sinc * window);
+				}
+				out
+			}
+		}
+	}
+}
@@ -0,0 +1,227 @@
+//! Loop points and markers stored in audio files.
+
+#[cfg(any(feature = "ogg", feature = "wav"))]
+use super::SoundSettings;
+#[cfg(feature = "wav")]
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+
+/// A named point in a sound, like a cue point in a wav file.
+///
+/// Markers are only read from wav files.
+///
+/// Markers can be used to start instances at specific points
+/// in a sound:
+///
+/// ```no_run
+/// # use kira::{manager::AudioManager, instance::InstanceSettings, sound::Sound};
+/// #
+/// # let mut audio_manager = AudioManager::new(Default::default())?;
+/// let mut sound = audio_manager.load_sound("dialogue.wav", Default::default())?;
+/// if let Some(position) = sound.marker("second line").map(|marker| marker.position) {
+///     sound.play(InstanceSettings::new().start_position(position))?;
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct Marker {
+	/// The name of the marker, if it has one.
+	pub name: Option<String>,
+	/// The position of the marker (in seconds).
+	pub position: f64,
+}
+
+/// Metadata read from an audio file. Positions are in frames.
+#[cfg(any(feature = "ogg", feature = "wav"))]
+#[derive(Debug, Default)]
+pub(super) struct Metadata {
+	loop_start: Option<u64>,
+	/// The frame after the end of the loop.
+	loop_end: Option<u64>,
+	markers: Vec<(Option<String>, u64)>,
+}
+
+#[cfg(feature = "wav")]
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+	let bytes = bytes.get(offset..offset + 4)?;
+	Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Iterates over the IDs and contents of a sequence of RIFF chunks,
+/// stopping at the first chunk that's cut off.
+#[cfg(feature = "wav")]
+fn riff_chunks(bytes: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> + '_ {
+	let mut bytes = bytes;
+	std::iter::from_fn(move || {
+		let id = bytes.get(0..4)?;
+		let size = u32_at(bytes, 4)? as usize;
+		let data = bytes.get(8..8usize.checked_add(size)?)?;
+		// chunks are padded to an even number of bytes
+		bytes = bytes.get(8 + size + size % 2..).unwrap_or(&[]);
+		Some((id, data))
+	})
+}
+
+#[cfg(any(feature = "ogg", feature = "wav"))]
+impl Metadata {
+	/// Reads the first loop from the `smpl` chunk and the cue
+	/// points from the `cue ` chunk of a wav file, along with their
+	/// labels from the `adtl` list.
+	///
+	/// Metadata that can't be read is ignored.
+	#[cfg(feature = "wav")]
+	pub fn from_wav(bytes: &[u8]) -> Self {
+		let mut metadata = Self::default();
+		if bytes.get(0..4) != Some(b"RIFF") || bytes.get(8..12) != Some(b"WAVE") {
+			return metadata;
+		}
+		let mut cue_points = vec![];
+		let mut labels = vec![];
+		for (id, data) in riff_chunks(&bytes[12..]) {
+			match id {
+				// the loops come after 36 bytes of other sampler
+				// information, and each one is 24 bytes long
+				b"smpl" if u32_at(data, 28).unwrap_or(0) > 0 => {
+					if let (Some(start), Some(end)) = (u32_at(data, 44), u32_at(data, 48)) {
+						metadata.loop_start = Some(start.into());
+						// the end point is the last frame of the loop
+						metadata.loop_end = Some(u64::from(end) + 1);
+					}
+				}
+				b"cue " => {
+					let num_cue_points = u32_at(data, 0).unwrap_or(0) as usize;
+					for i in 0..num_cue_points {
+						let offset = 4 + i * 24;
+						if let (Some(id), Some(position)) =
+							(u32_at(data, offset), u32_at(data, offset + 20))
+						{
+							cue_points.push((id, u64::from(position)));
+						}
+					}
+				}
+				b"LIST" if data.get(0..4) == Some(b"adtl") => {
+					for (id, data) in riff_chunks(&data[4..]) {
+						if id != b"labl" {
+							continue;
+						}
+						if let (Some(cue_point_id), Some(text)) = (u32_at(data, 0), data.get(4..)) {
+							let text = text.split(|byte| *byte == 0).next().unwrap_or(&[]);
+							labels.push((cue_point_id, String::from_utf8_lossy(text).into_owned()));
+						}
+					}
+				}
+				_ => {}
+			}
+		}
+		metadata.markers = cue_points
+			.into_iter()
+			.map(|(id, position)| {
+				let name = labels
+					.iter()
+					.find(|(cue_point_id, _)| *cue_point_id == id)
+					.map(|(_, label)| label.clone());
+				(name, position)
+			})
+			.collect();
+		metadata
+	}
+
+	/// Reads the same metadata as [`from_wav`](Self::from_wav)
+	/// without reading the audio data into memory.
+	#[cfg(feature = "wav")]
+	pub fn read_wav<R>(mut reader: R) -> std::io::Result<Self>
+	where
+		R: Read + Seek,
+	{
+		let mut bytes = vec![0; 12];
+		reader.read_exact(&mut bytes)?;
+		if &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+			return Ok(Self::default());
+		}
+		// the chunks the metadata is stored in are copied after
+		// the header, and the rest of the chunks are skipped
+		let mut chunk_header = [0; 8];
+		loop {
+			match reader.read_exact(&mut chunk_header) {
+				Ok(()) => {}
+				Err(error) if error.kind() == ErrorKind::UnexpectedEof => break,
+				Err(error) => return Err(error),
+			}
+			let size = u64::from(u32_at(&chunk_header, 4).unwrap_or(0));
+			// chunks are padded to an even number of bytes
+			let padded_size = size + size % 2;
+			match &chunk_header[0..4] {
+				b"smpl" | b"cue " | b"LIST" => {
+					bytes.extend_from_slice(&chunk_header);
+					reader.by_ref().take(padded_size).read_to_end(&mut bytes)?;
+				}
+				_ => {
+					reader.seek(SeekFrom::Current(padded_size as i64))?;
+				}
+			}
+		}
+		Ok(Self::from_wav(&bytes))
+	}
+
+	/// Reads the loop from the `LOOPSTART` and `LOOPLENGTH`
+	/// comments of an ogg file.
+	#[cfg(feature = "ogg")]
+	pub fn from_ogg_comments(comments: &[(String, String)]) -> Self {
+		let comment = |key: &str| {
+			comments
+				.iter()
+				.find(|(comment_key, _)| comment_key.eq_ignore_ascii_case(key))
+				.and_then(|(_, value)| value.trim().parse::<u64>().ok())
+		};
+		let loop_start = comment("LOOPSTART");
+		let loop_length = comment("LOOPLENGTH");
+		// a loop that ends past the largest possible position
+		// can't be right, so it's ignored
+		if let (Some(loop_start), Some(loop_length)) = (loop_start, loop_length) {
+			if loop_start.checked_add(loop_length).is_none() {
+				return Self::default();
+			}
+		}
+		Self {
+			loop_start,
+			loop_end: loop_start
+				.zip(loop_length)
+				.map(|(loop_start, length)| loop_start + length),
+			markers: vec![],
+		}
+	}
+
+	/// Uses the loop as the default loop start and end points of
+	/// the sound, unless the settings already set either of them.
+	///
+	/// The loop points are used together or not at all, since a
+	/// loop start point from the settings and a loop end point from
+	/// the file probably weren't meant to go together.
+	pub fn apply_to_settings(&self, settings: SoundSettings, sample_rate: u32) -> SoundSettings {
+		if settings.default_loop_start.is_some() || settings.default_loop_end.is_some() {
+			return settings;
+		}
+		let to_seconds = |position: u64| position as f64 / sample_rate as f64;
+		SoundSettings {
+			default_loop_start: self.loop_start.map(to_seconds),
+			default_loop_end: self.loop_end.map(to_seconds),
+			..settings
+		}
+	}
+
+	/// Returns the markers in order of position.
+	pub fn into_markers(self, sample_rate: u32) -> Vec<Marker> {
+		let mut markers = self.markers;
+		markers.sort_by_key(|(_, position)| *position);
+		markers
+			.into_iter()
+			.map(|(name, position)| Marker {
+				name,
+				position: position as f64 / sample_rate as f64,
+			})
+			.collect()
+	}
+}
@@ -3,59 +3,109 @@
 pub mod error;
 pub mod handle;
 mod id;
+pub(crate) mod interpolation;
+mod metadata;
+mod resample;
+mod samples;
 mod settings;
+pub(crate) mod streaming;
+#[cfg(test)]
+mod tests;
 
 pub use id::SoundId;
+pub use interpolation::InterpolationMode;
+pub use metadata::Marker;
+pub(crate) use samples::SampleCursor;
+pub use samples::SampleFormat;
 pub use settings::SoundSettings;
 
 use crate::{
 	frame::Frame,
 	group::{groups::Groups, GroupId, GroupSet},
 	mixer::TrackIndex,
-	util,
 };
 
 use std::{
 	fmt::{Debug, Formatter},
 	io::{Read, Seek},
+	sync::Arc,
 };
 
+#[cfg(any(feature = "ogg", feature = "wav"))]
+use metadata::Metadata;
+use samples::Samples;
+use streaming::StreamSource;
+
 #[cfg(any(feature = "mp3", feature = "ogg", feature = "flac", feature = "wav"))]
 use std::{fs::File, path::Path};
 
+#[derive(Clone)]
+enum SoundData {
+	/// The sound is fully decoded in memory.
+	Samples(Samples),
+	/// The sound is decoded from disk while it plays.
+	Streamed(Arc<StreamSource>),
+}
+
 /// A piece of audio that can be played by an [`AudioManager`](crate::manager::AudioManager).
 #[derive(Clone)]
 pub struct Sound {
 	id: SoundId,
 	sample_rate: u32,
-	frames: Vec<Frame>,
+	data: SoundData,
 	duration: f64,
 	default_track: TrackIndex,
 	cooldown: Option<f64>,
 	semantic_duration: Option<f64>,
 	default_loop_start: Option<f64>,
+	default_loop_end: Option<f64>,
+	interpolation_mode: InterpolationMode,
+	markers: Vec<Marker>,
 	groups: GroupSet,
 	cooldown_timer: f64,
 }
 
 impl Sound {
 	/// Creates a new sound from raw sample data.
+	///
+	/// The frames are converted to the storage format set
+	/// in the settings.
 	pub fn from_frames(sample_rate: u32, frames: Vec<Frame>, settings: SoundSettings) -> Self {
 		let duration = frames.len() as f64 / sample_rate as f64;
+		let samples = Samples::new(frames, settings.sample_format, settings.keep_mono);
 		Self {
-			id: settings.id.unwrap_or(SoundId::new()),
+			id: settings.id.unwrap_or_else(SoundId::new),
 			sample_rate,
-			frames,
+			data: SoundData::Samples(samples),
 			duration,
 			default_track: settings.default_track,
 			cooldown: settings.cooldown,
 			semantic_duration: settings.semantic_duration,
 			default_loop_start: settings.default_loop_start,
+			default_loop_end: settings.default_loop_end,
+			interpolation_mode: settings.interpolation_mode,
+			markers: vec![],
 			groups: settings.groups,
 			cooldown_timer: 0.0,
 		}
 	}
 
+	/// Creates a sound from raw sample data and the metadata
+	/// read from its file.
+	#[cfg(any(feature = "ogg", feature = "wav"))]
+	fn from_frames_with_metadata(
+		sample_rate: u32,
+		frames: Vec<Frame>,
+		settings: SoundSettings,
+		metadata: Metadata,
+	) -> Self {
+		let settings = metadata.apply_to_settings(settings, sample_rate);
+		Self {
+			markers: metadata.into_markers(sample_rate),
+			..Self::from_frames(sample_rate, frames, settings)
+		}
+	}
+
 	/// Decodes a sound from an mp3 reader.
 	#[cfg(feature = "mp3")]
 	pub fn from_mp3_reader<R>(
@@ -132,6 +182,14 @@ impl Sound {
 	}
 
 	/// Decodes a sound from an ogg reader.
+	///
+	/// If the file has `LOOPSTART` and `LOOPLENGTH` comments (in
+	/// frames), they're used as the sound's
+	/// [default loop start](SoundSettings::default_loop_start)
+	/// and [end](SoundSettings::default_loop_end) points,
+	/// unless the settings already set either of them. Ogg files
+	/// have no standard way of storing markers, so the sound won't
+	/// have any [markers](Sound::markers).
 	#[cfg(feature = "ogg")]
 	pub fn from_ogg_reader<R>(
 		reader: R,
@@ -160,10 +218,12 @@ impl Sound {
 				_ => return Err(error::SoundFromFileError::UnsupportedChannelConfiguration),
 			}
 		}
-		Ok(Self::from_frames(
+		let metadata = Metadata::from_ogg_comments(&reader.comment_hdr.comment_list);
+		Ok(Self::from_frames_with_metadata(
 			reader.ident_hdr.audio_sample_rate,
 			stereo_samples,
 			settings,
+			metadata,
 		))
 	}
 
@@ -230,14 +290,30 @@ impl Sound {
 	}
 
 	/// Decodes sound from a wav reader.
+	///
+	/// If the file has a `smpl` chunk, its first loop is used as the
+	/// sound's [default loop start](SoundSettings::default_loop_start)
+	/// and [end](SoundSettings::default_loop_end) points,
+	/// unless the settings already set either of them. Cue points are available
+	/// as [markers](Sound::markers), named by the labels in the
+	/// file's `adtl` list.
+	///
+	/// The reader has to be seekable, since the metadata is read
+	/// before the audio data.
 	#[cfg(feature = "wav")]
 	pub fn from_wav_reader<R>(
-		reader: R,
+		mut reader: R,
 		settings: SoundSettings,
 	) -> Result<Self, error::SoundFromFileError>
 	where
-		R: Read,
+		R: Read + Seek,
 	{
+		// hound skips the chunks the metadata is stored in, so they're
+		// read first, and then the reader goes back to the start of
+		// the file for hound
+		let start = reader.stream_position()?;
+		let metadata = Metadata::read_wav(&mut reader)?;
+		reader.seek(std::io::SeekFrom::Start(start))?;
 		let mut reader = hound::WavReader::new(reader)?;
 		let spec = reader.spec();
 		let mut stereo_samples = vec![];
@@ -279,10 +355,11 @@ impl Sound {
 			},
 			_ => return Err(error::SoundFromFileError::UnsupportedChannelConfiguration),
 		}
-		Ok(Self::from_frames(
+		Ok(Self::from_frames_with_metadata(
 			reader.spec().sample_rate,
 			stereo_samples,
 			settings,
+			metadata,
 		))
 	}
 
@@ -295,7 +372,7 @@ impl Sound {
 	where
 		P: AsRef<Path>,
 	{
-		Self::from_wav_reader(File::open(path)?, settings)
+		Self::from_wav_reader(std::io::BufReader::new(File::open(path)?), settings)
 	}
 
 	/// Decodes a sound from a file.
@@ -324,6 +401,114 @@ impl Sound {
 		Err(error::SoundFromFileError::UnsupportedAudioFileFormat)
 	}
 
+	/// Creates a sound that's decoded from a file while it plays
+	/// instead of all at once.
+	///
+	/// Long sounds, like music, can take up hundreds of megabytes once
+	/// they're decoded, so streaming them keeps memory usage low. Every
+	/// instance of a streamed sound is decoded on one background thread,
+	/// which stays about half a second ahead of each instance's playback
+	/// position. If an instance gets ahead of the thread, it waits for
+	/// the thread to catch up.
+	///
+	/// Instances of streamed sounds support the same settings as
+	/// instances of other sounds, but there are a few limitations:
+	/// - Streamed sounds can only be played using a
+	///   [`SoundHandle`](handle::SoundHandle). Sequences can't play
+	///   them, and arrangements can't use them in clips (starting a
+	///   sequence or adding an arrangement that does returns an
+	///   error).
+	/// - Seeking and looping are fast for wav files, but other
+	///   formats have to be decoded from the beginning of the file
+	///   to move backward, so seeking backward can be slow for long
+	///   files. For the same reason, only wav files can be played
+	///   backward. Instances of other formats stop as soon as they
+	///   would play backward.
+	/// - Files that don't say how long they are (mp3 and ogg files,
+	///   and some flac files) are decoded once when the sound is
+	///   created to find out their duration.
+	///
+	/// Loop points are read from wav and ogg files, and markers from
+	/// wav files, the same way as when they're loaded with
+	/// [`Sound::from_file`].
+	///
+	/// The audio format will be automatically determined from the file extension.
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn stream_from_file<P>(
+		path: P,
+		settings: SoundSettings,
+	) -> Result<Self, error::SoundFromFileError>
+	where
+		P: AsRef<std::path::Path>,
+	{
+		let source = StreamSource::new(path.as_ref().into())?;
+		let sample_rate = source.sample_rate();
+		let duration = source.num_frames() as f64 / sample_rate as f64;
+		#[cfg(any(feature = "ogg", feature = "wav"))]
+		let (settings, markers) = {
+			let metadata = source.read_metadata()?;
+			(
+				metadata.apply_to_settings(settings, sample_rate),
+				metadata.into_markers(sample_rate),
+			)
+		};
+		#[cfg(not(any(feature = "ogg", feature = "wav")))]
+		let markers = vec![];
+		Ok(Self {
+			id: settings.id.unwrap_or_else(SoundId::new),
+			sample_rate,
+			data: SoundData::Streamed(Arc::new(source)),
+			duration,
+			default_track: settings.default_track,
+			cooldown: settings.cooldown,
+			semantic_duration: settings.semantic_duration,
+			default_loop_start: settings.default_loop_start,
+			default_loop_end: settings.default_loop_end,
+			interpolation_mode: settings.interpolation_mode,
+			markers,
+			groups: settings.groups,
+			cooldown_timer: 0.0,
+		})
+	}
+
+	/// Converts the sound to a different sample rate.
+	///
+	/// Sounds are resampled on the fly when they're played at a
+	/// different sample rate than the one they were recorded at,
+	/// but that's done with simple interpolation, which can add
+	/// audible artifacts (especially to sounds with a lot of high
+	/// frequency content). This uses a higher quality filter to
+	/// convert the sound ahead of time, which takes longer but
+	/// only has to be done once. Usually you'll want to resample
+	/// sounds to the [audio manager's sample rate](crate::manager::AudioManager::sample_rate),
+	/// which [`AudioManagerSettings::resample_sounds`](crate::manager::AudioManagerSettings::resample_sounds)
+	/// can do automatically.
+	///
+	/// The samples are stored in the same format as before.
+	/// Streamed sounds are decoded as they play, so they're
+	/// returned unchanged.
+	pub fn resampled(self, sample_rate: u32) -> Self {
+		let samples = match &self.data {
+			SoundData::Samples(samples) => samples,
+			SoundData::Streamed(_) => return self,
+		};
+		if sample_rate == self.sample_rate {
+			return self;
+		}
+		let frames = resample::resample(&samples.frames(), self.sample_rate, sample_rate);
+		let samples = Samples::new(frames, samples.format(), samples.is_mono());
+		Self {
+			sample_rate,
+			data: SoundData::Samples(samples),
+			..self
+		}
+	}
+
+	/// Returns the sample rate of the sound's audio data.
+	pub fn sample_rate(&self) -> u32 {
+		self.sample_rate
+	}
+
 	/// Gets the unique identifier for this sound.
 	pub fn id(&self) -> SoundId {
 		self.id
@@ -356,33 +541,109 @@ impl Sound {
 		self.default_loop_start
 	}
 
+	/// Returns the default time (in seconds) looping instances
+	/// of this sound will jump back to the loop start point
+	/// from, if it isn't the end of the sound.
+	pub fn default_loop_end(&self) -> Option<f64> {
+		self.default_loop_end
+	}
+
+	/// Returns the way instances of this sound interpolate
+	/// between samples by default.
+	pub fn interpolation_mode(&self) -> InterpolationMode {
+		self.interpolation_mode
+	}
+
+	/// Returns the markers in the sound, in order of position.
+	///
+	/// Markers are only read from the cue points of wav files.
+	/// Other formats have no standard way of storing markers,
+	/// so sounds loaded from them have no markers.
+	pub fn markers(&self) -> &[Marker] {
+		&self.markers
+	}
+
+	/// Returns the first marker with the given name, if there is one.
+	pub fn marker(&self, name: &str) -> Option<&Marker> {
+		self.markers
+			.iter()
+			.find(|marker| marker.name.as_deref() == Some(name))
+	}
+
+	/// Returns `true` if the sound is decoded from a file
+	/// while it plays.
+	///
+	/// See [`Sound::stream_from_file`].
+	pub fn is_streamed(&self) -> bool {
+		matches!(self.data, SoundData::Streamed(_))
+	}
+
+	pub(crate) fn stream_source(&self) -> Option<&Arc<StreamSource>> {
+		match &self.data {
+			SoundData::Samples(_) => None,
+			SoundData::Streamed(source) => Some(source),
+		}
+	}
+
 	/// Gets the frame of this sound at an arbitrary time
 	/// in seconds, interpolating between samples if necessary.
+	///
+	/// Streamed sounds aren't kept in memory, so this always
+	/// returns silence for them.
 	pub fn get_frame_at_position(&self, position: f64) -> Frame {
+		self.get_frame_at_position_with_interpolation(position, self.interpolation_mode)
+	}
+
+	/// Gets the frame of this sound at an arbitrary time in
+	/// seconds, using the given interpolation mode instead of
+	/// the sound's default one.
+	///
+	/// Streamed sounds aren't kept in memory, so this always
+	/// returns silence for them.
+	pub fn get_frame_at_position_with_interpolation(
+		&self,
+		position: f64,
+		interpolation_mode: InterpolationMode,
+	) -> Frame {
+		self.frame_at_position_with_cursor(position, interpolation_mode, &mut Default::default())
+	}
+
+	/// Gets the frame of this sound at an arbitrary time in seconds,
+	/// continuing to decode from where the last read using the same
+	/// `cursor` left off when possible.
+	pub(crate) fn frame_at_position_with_cursor(
+		&self,
+		position: f64,
+		interpolation_mode: InterpolationMode,
+		cursor: &mut SampleCursor,
+	) -> Frame {
+		let samples = match &self.data {
+			SoundData::Samples(samples) => samples,
+			SoundData::Streamed(_) => return Frame::from_mono(0.0),
+		};
 		let sample_position = self.sample_rate as f64 * position;
 		let fraction = (sample_position % 1.0) as f32;
-		let current_sample_index = sample_position as usize;
-		let previous = if current_sample_index == 0 {
-			Frame::from_mono(0.0)
-		} else {
-			*self
-				.frames
-				.get(current_sample_index - 1)
-				.unwrap_or(&Frame::from_mono(0.0))
-		};
-		let current = *self
-			.frames
-			.get(current_sample_index)
-			.unwrap_or(&Frame::from_mono(0.0));
-		let next_1 = *self
-			.frames
-			.get(current_sample_index + 1)
-			.unwrap_or(&Frame::from_mono(0.0));
-		let next_2 = *self
-			.frames
-			.get(current_sample_index + 2)
-			.unwrap_or(&Frame::from_mono(0.0));
-		util::interpolate_frame(previous, current, next_1, next_2, fraction)
+		let current_sample_index = sample_position as i64;
+		let mut frames = [Frame::from_mono(0.0); interpolation::MAX_FRAMES];
+		let frames = &mut frames[..interpolation_mode.num_frames()];
+		samples.read_with_cursor(
+			current_sample_index - interpolation_mode.num_frames_before() as i64,
+			frames,
+			cursor,
+		);
+		interpolation_mode.interpolate(frames, fraction)
+	}
+
+	/// Returns the number of bytes used to store the sound's
+	/// samples in memory.
+	///
+	/// Streamed sounds aren't kept in memory, so this always
+	/// returns 0 for them.
+	pub fn memory_size(&self) -> usize {
+		match &self.data {
+			SoundData::Samples(samples) => samples.memory_size(),
+			SoundData::Streamed(_) => 0,
+		}
 	}
 
 	/// Starts the cooldown timer for the sound.
@@ -411,17 +672,31 @@ impl Sound {
 	pub(crate) fn is_in_group(&self, id: GroupId, all_groups: &Groups) -> bool {
 		self.groups.has_ancestor(id, all_groups)
 	}
+
+	/// Returns if this sound is in an unpausable group.
+	pub(crate) fn is_unpausable(&self, all_groups: &Groups) -> bool {
+		self.groups.is_unpausable(all_groups)
+	}
 }
 
 impl Debug for Sound {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-		f.debug_struct(&format!("Sound ({} frames)", self.frames.len()))
+		let name = match &self.data {
+			SoundData::Samples(samples) => format!("Sound ({} frames)", samples.len()),
+			SoundData::Streamed(source) => {
+				format!("Sound (streamed, {} frames)", source.num_frames())
+			}
+		};
+		f.debug_struct(&name)
 			.field("sample_rate", &self.sample_rate)
 			.field("duration", &self.duration)
 			.field("default_track", &self.default_track)
 			.field("cooldown", &self.cooldown)
 			.field("semantic_duration", &self.semantic_duration)
 			.field("default_loop_start", &self.default_loop_start)
+			.field("default_loop_end", &self.default_loop_end)
+			.field("interpolation_mode", &self.interpolation_mode)
+			.field("markers", &self.markers)
 			.field("groups", &self.groups)
 			.field("cooldown_timer", &self.cooldown_timer)
 			.finish()
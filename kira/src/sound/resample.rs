@@ -0,0 +1,83 @@
+//! Windowed-sinc resampling for converting sounds to
+//! a different sample rate.
+
+use std::f64::consts::PI;
+
+use crate::Frame;
+
+/// The number of zero crossings of the sinc function on each
+/// side of the kernel's center. More zero crossings give a
+/// sharper cutoff at the cost of more computation.
+const ZERO_CROSSINGS: usize = 16;
+
+/// The number of kernel values stored per zero crossing.
+/// Values in between are linearly interpolated.
+const KERNEL_RESOLUTION: usize = 512;
+
+/// How close to the Nyquist frequency the cutoff is, leaving
+/// room for the filter's transition band.
+const ROLLOFF: f64 = 0.95;
+
+/// Returns a Blackman-windowed sinc kernel sampled at
+/// [`KERNEL_RESOLUTION`] points per zero crossing, from the
+/// center to the edge.
+fn kernel() -> Vec<f64> {
+	(0..=ZERO_CROSSINGS * KERNEL_RESOLUTION)
+		.map(|i| {
+			let x = i as f64 / KERNEL_RESOLUTION as f64;
+			let sinc = if i == 0 {
+				1.0
+			} else {
+				(PI * x).sin() / (PI * x)
+			};
+			let window_position = x / ZERO_CROSSINGS as f64;
+			let window = 0.42
+				+ 0.5 * (PI * window_position).cos()
+				+ 0.08 * (2.0 * PI * window_position).cos();
+			sinc * window
+		})
+		.collect()
+}
+
+/// Converts frames from one sample rate to another.
+///
+/// The frames are filtered to remove frequencies that can't be
+/// represented at the lower of the two sample rates, so sounds
+/// don't alias when they're played at a higher or lower rate.
+pub(crate) fn resample(frames: &[Frame], from: u32, to: u32) -> Vec<Frame> {
+	if from == to {
+		return frames.to_vec();
+	}
+	let kernel = kernel();
+	let ratio = from as f64 / to as f64;
+	// when downsampling, the cutoff is lowered to the output's
+	// Nyquist frequency, which widens the kernel
+	let cutoff = ROLLOFF * (1.0 / ratio).min(1.0);
+	let half_width = ZERO_CROSSINGS as f64 / cutoff;
+	let num_output_frames = (frames.len() as f64 / ratio).ceil() as usize;
+	(0..num_output_frames)
+		.map(|i| {
+			let position = i as f64 * ratio;
+			let first_index = ((position - half_width).ceil() as i64).max(0);
+			let last_index = ((position + half_width).floor() as i64).min(frames.len() as i64 - 1);
+			let mut left = 0.0;
+			let mut right = 0.0;
+			for index in first_index..=last_index {
+				let kernel_position =
+					(position - index as f64).abs() * cutoff * KERNEL_RESOLUTION as f64;
+				let kernel_index = kernel_position as usize;
+				if kernel_index + 1 >= kernel.len() {
+					continue;
+				}
+				let fraction = kernel_position - kernel_index as f64;
+				let weight = cutoff
+					* (kernel[kernel_index]
+						+ (kernel[kernel_index + 1] - kernel[kernel_index]) * fraction);
+				let frame = frames[index as usize];
+				left += frame.left as f64 * weight;
+				right += frame.right as f64 * weight;
+			}
+			Frame::new(left as f32, right as f32)
+		})
+		.collect()
+}
@@ -0,0 +1,396 @@
+//! Compact storage for the samples of in-memory sounds.
+
+use crate::Frame;
+
+/// How the samples of a [`Sound`](super::Sound) are stored in memory.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub enum SampleFormat {
+	/// Each sample is stored as a 32-bit float.
+	///
+	/// This keeps the samples exactly as they were decoded.
+	F32,
+	/// Each sample is stored as a 16-bit integer, which
+	/// takes half as much memory as [`F32`](SampleFormat::F32).
+	///
+	/// This is lossless for 16-bit audio files, but lowers the
+	/// precision of audio with a higher bit depth.
+	I16,
+	/// Samples are compressed to 4 bits each using IMA ADPCM,
+	/// which takes an eighth as much memory as
+	/// [`F32`](SampleFormat::F32).
+	///
+	/// The original file's compressed data (like mp3 frames or
+	/// ogg packets) isn't kept. Instead, the decoded samples are
+	/// compressed again with ADPCM, which is lossy, so any quality
+	/// lost by the file's own compression is lost again on top of
+	/// that. This is best suited for sound effects rather than
+	/// music.
+	///
+	/// The samples are decoded on the audio thread when they're
+	/// played. Each instance remembers where it left off, so
+	/// playing forward only decodes each sample about once, but
+	/// seeking, playing backward, and arrangements decode from the
+	/// start of a block of 64 samples, which takes more CPU time.
+	Adpcm,
+}
+
+// can't be derived on the Rust versions the crate supports
+// (enum `#[default]` attributes need 1.62)
+#[allow(clippy::derivable_impls)]
+impl Default for SampleFormat {
+	fn default() -> Self {
+		Self::F32
+	}
+}
+
+fn f32_to_i16(sample: f32) -> i16 {
+	(sample * 32768.0).round().clamp(-32768.0, 32767.0) as i16
+}
+
+fn i16_to_f32(sample: i16) -> f32 {
+	sample as f32 / 32768.0
+}
+
+/// The number of samples in each independently decodable
+/// block of ADPCM data.
+const ADPCM_BLOCK_SIZE: usize = 64;
+
+const ADPCM_INDEX_TABLE: [i32; 8] = [-1, -1, -1, -1, 2, 4, 6, 8];
+
+const ADPCM_STEP_TABLE: [i32; 89] = [
+	7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+	73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+	494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+	2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+	10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+/// The state of an ADPCM decoder, which is all that's needed
+/// to start decoding from any point.
+#[derive(Debug, Copy, Clone, Default)]
+struct AdpcmState {
+	predictor: i16,
+	step_index: u8,
+}
+
+impl AdpcmState {
+	/// Updates the state with the next 4-bit code and
+	/// returns the decoded sample.
+	fn decode(&mut self, code: u8) -> i16 {
+		let step = ADPCM_STEP_TABLE[self.step_index as usize];
+		let mut difference = step >> 3;
+		if code & 4 != 0 {
+			difference += step;
+		}
+		if code & 2 != 0 {
+			difference += step >> 1;
+		}
+		if code & 1 != 0 {
+			difference += step >> 2;
+		}
+		let predictor = if code & 8 != 0 {
+			self.predictor as i32 - difference
+		} else {
+			self.predictor as i32 + difference
+		};
+		self.predictor = predictor.max(i16::MIN as i32).min(i16::MAX as i32) as i16;
+		self.step_index = (self.step_index as i32 + ADPCM_INDEX_TABLE[(code & 7) as usize])
+			.max(0)
+			.min(ADPCM_STEP_TABLE.len() as i32 - 1) as u8;
+		self.predictor
+	}
+
+	/// Returns the 4-bit code that gets closest to `sample`
+	/// and updates the state as if the code was decoded.
+	fn encode(&mut self, sample: i16) -> u8 {
+		let mut difference = sample as i32 - self.predictor as i32;
+		let mut code = 0;
+		if difference < 0 {
+			code = 8;
+			difference = -difference;
+		}
+		let mut step = ADPCM_STEP_TABLE[self.step_index as usize];
+		for bit in [4, 2, 1].iter() {
+			if difference >= step {
+				code |= bit;
+				difference -= step;
+			}
+			step >>= 1;
+		}
+		self.decode(code);
+		code
+	}
+}
+
+/// One channel of ADPCM-compressed samples.
+#[derive(Debug, Clone)]
+struct AdpcmChannel {
+	/// The decoder state at the start of each block.
+	block_states: Vec<AdpcmState>,
+	/// The 4-bit codes for each sample, two per byte.
+	codes: Vec<u8>,
+}
+
+impl AdpcmChannel {
+	fn new(samples: impl Iterator<Item = f32>) -> Self {
+		let samples: Vec<i16> = samples.map(f32_to_i16).collect();
+		let mut state = AdpcmState::default();
+		let mut block_states = vec![];
+		let mut codes = vec![];
+		for (i, sample) in samples.iter().copied().enumerate() {
+			// the first sample of each block is stored exactly in
+			// the block's header, along with a step size that fits
+			// the difference to the next sample, so the block starts
+			// off accurate
+			let code = if i % ADPCM_BLOCK_SIZE == 0 {
+				state.predictor = sample;
+				if let Some(next_sample) = samples.get(i + 1) {
+					let difference = (*next_sample as i32 - sample as i32).abs();
+					state.step_index = ADPCM_STEP_TABLE
+						.iter()
+						.position(|step| *step >= difference)
+						.unwrap_or(ADPCM_STEP_TABLE.len() - 1) as u8;
+				}
+				block_states.push(state);
+				0
+			} else {
+				state.encode(sample)
+			};
+			if i % 2 == 0 {
+				codes.push(code);
+			} else {
+				*codes.last_mut().unwrap() |= code << 4;
+			}
+		}
+		Self {
+			block_states,
+			codes,
+		}
+	}
+
+	fn memory_size(&self) -> usize {
+		self.block_states.len() * std::mem::size_of::<AdpcmState>() + self.codes.len()
+	}
+
+	/// Decodes the samples starting at `start_index`, calling
+	/// `f` with each one until it returns `false`.
+	///
+	/// If `resume_from` is the index of an earlier sample in the
+	/// same block along with the decoder state before that sample,
+	/// decoding picks up from there instead of from the start of
+	/// the block. Returns the decoder state before `start_index`,
+	/// so a later call can pick up from there.
+	fn decode(
+		&self,
+		start_index: usize,
+		resume_from: Option<(usize, AdpcmState)>,
+		mut f: impl FnMut(f32) -> bool,
+	) -> AdpcmState {
+		let block_start = start_index / ADPCM_BLOCK_SIZE * ADPCM_BLOCK_SIZE;
+		let (first_index, mut state) = match resume_from {
+			Some((index, state)) if (block_start..=start_index).contains(&index) => (index, state),
+			_ => (block_start, AdpcmState::default()),
+		};
+		let mut start_state = state;
+		for i in first_index..self.codes.len() * 2 {
+			if i == start_index {
+				start_state = state;
+			}
+			let sample = if i % ADPCM_BLOCK_SIZE == 0 {
+				state = self.block_states[i / ADPCM_BLOCK_SIZE];
+				state.predictor
+			} else {
+				state.decode((self.codes[i / 2] >> (4 * (i % 2))) & 0xf)
+			};
+			if i >= start_index && !f(i16_to_f32(sample)) {
+				break;
+			}
+		}
+		start_state
+	}
+}
+
+/// Remembers where a reader of ADPCM samples left off, so
+/// reading the next few frames doesn't have to start decoding
+/// from the beginning of the block again.
+///
+/// Other formats don't need to decode anything, so they
+/// ignore the cursor.
+#[derive(Debug, Copy, Clone, Default)]
+pub(crate) struct SampleCursor {
+	/// The index of the first frame of the last read and the
+	/// decoder state before that frame for each channel.
+	position: Option<(usize, [AdpcmState; 2])>,
+}
+
+#[derive(Debug, Clone)]
+enum SampleData {
+	/// Interleaved 32-bit float samples.
+	F32(Vec<f32>),
+	/// Interleaved 16-bit integer samples.
+	I16(Vec<i16>),
+	/// Compressed samples for each channel.
+	Adpcm(Vec<AdpcmChannel>),
+}
+
+/// The samples of an in-memory sound.
+#[derive(Debug, Clone)]
+pub(crate) struct Samples {
+	num_frames: usize,
+	/// 1 if both channels of every frame are the same and
+	/// they're being stored as one channel, 2 otherwise.
+	num_channels: usize,
+	data: SampleData,
+}
+
+impl Samples {
+	/// Converts frames to the given format. If `keep_mono` is `true`
+	/// and both channels of every frame are the same, only one channel
+	/// is stored.
+	pub fn new(frames: Vec<Frame>, format: SampleFormat, keep_mono: bool) -> Self {
+		let num_channels = if keep_mono && frames.iter().all(|frame| frame.left == frame.right) {
+			1
+		} else {
+			2
+		};
+		let channel = |channel: usize| {
+			frames.iter().map(move |frame| {
+				if channel == 0 {
+					frame.left
+				} else {
+					frame.right
+				}
+			})
+		};
+		let mut interleaved = Vec::with_capacity(frames.len() * num_channels);
+		for frame in &frames {
+			interleaved.push(frame.left);
+			if num_channels == 2 {
+				interleaved.push(frame.right);
+			}
+		}
+		let data = match format {
+			SampleFormat::F32 => SampleData::F32(interleaved),
+			SampleFormat::I16 => SampleData::I16(interleaved.into_iter().map(f32_to_i16).collect()),
+			SampleFormat::Adpcm => SampleData::Adpcm(
+				(0..num_channels)
+					.map(|i| AdpcmChannel::new(channel(i)))
+					.collect(),
+			),
+		};
+		Self {
+			num_frames: frames.len(),
+			num_channels,
+			data,
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.num_frames
+	}
+
+	/// Returns the format the samples are stored in.
+	pub fn format(&self) -> SampleFormat {
+		match &self.data {
+			SampleData::F32(_) => SampleFormat::F32,
+			SampleData::I16(_) => SampleFormat::I16,
+			SampleData::Adpcm(_) => SampleFormat::Adpcm,
+		}
+	}
+
+	/// Returns `true` if only one channel is stored.
+	pub fn is_mono(&self) -> bool {
+		self.num_channels == 1
+	}
+
+	/// Decodes all of the frames.
+	pub fn frames(&self) -> Vec<Frame> {
+		let mut frames = vec![Frame::from_mono(0.0); self.num_frames];
+		self.read(0, &mut frames);
+		frames
+	}
+
+	/// Returns the number of bytes used to store the samples.
+	pub fn memory_size(&self) -> usize {
+		match &self.data {
+			SampleData::F32(samples) => samples.len() * std::mem::size_of::<f32>(),
+			SampleData::I16(samples) => samples.len() * std::mem::size_of::<i16>(),
+			SampleData::Adpcm(channels) => channels.iter().map(AdpcmChannel::memory_size).sum(),
+		}
+	}
+
+	/// Fills `frames` with consecutive frames starting at
+	/// `first_index`. Frames outside of the sound are silent.
+	pub fn read(&self, first_index: i64, frames: &mut [Frame]) {
+		self.read_with_cursor(first_index, frames, &mut SampleCursor::default());
+	}
+
+	/// Like [`read`](Self::read), but continues decoding from
+	/// where the last read using the same `cursor` left off
+	/// when possible.
+	pub fn read_with_cursor(
+		&self,
+		first_index: i64,
+		frames: &mut [Frame],
+		cursor: &mut SampleCursor,
+	) {
+		for frame in frames.iter_mut() {
+			*frame = Frame::from_mono(0.0);
+		}
+		// the part of `frames` that's within the sound
+		let start_index = first_index.max(0) as usize;
+		let end_index = (first_index + frames.len() as i64).min(self.num_frames as i64);
+		if end_index <= start_index as i64 {
+			return;
+		}
+		let end_index = end_index as usize;
+		let frames = &mut frames[(start_index as i64 - first_index) as usize..];
+		let frames = &mut frames[..end_index - start_index];
+		let sample_range = start_index * self.num_channels..end_index * self.num_channels;
+		match &self.data {
+			SampleData::F32(samples) => {
+				for (frame, samples) in frames
+					.iter_mut()
+					.zip(samples[sample_range].chunks_exact(self.num_channels))
+				{
+					*frame = Frame::new(samples[0], samples[self.num_channels - 1]);
+				}
+			}
+			SampleData::I16(samples) => {
+				for (frame, samples) in frames
+					.iter_mut()
+					.zip(samples[sample_range].chunks_exact(self.num_channels))
+				{
+					*frame = Frame::new(
+						i16_to_f32(samples[0]),
+						i16_to_f32(samples[self.num_channels - 1]),
+					);
+				}
+			}
+			SampleData::Adpcm(channels) => {
+				let mut states = [AdpcmState::default(); 2];
+				for (i, channel) in channels.iter().enumerate() {
+					let resume_from = cursor.position.map(|(index, states)| (index, states[i]));
+					let mut frames = frames.iter_mut();
+					states[i] =
+						channel.decode(start_index, resume_from, |sample| match frames.next() {
+							Some(frame) => {
+								if i == 0 {
+									*frame = Frame::from_mono(sample);
+								} else {
+									frame.right = sample;
+								}
+								true
+							}
+							None => false,
+						});
+				}
+				cursor.position = Some((start_index, states));
+			}
+		}
+	}
+}
@@ -1,6 +1,6 @@
 use crate::{group::GroupSet, mixer::TrackIndex};
 
-use super::SoundId;
+use super::{InterpolationMode, SampleFormat, SoundId};
 
 /// Settings for a [`Sound`](crate::sound::Sound).
 #[derive(Debug, Clone)]
@@ -37,8 +37,24 @@ pub struct SoundSettings {
 	/// the point an instance should jump back to when it reaches
 	/// the end.
 	pub default_loop_start: Option<f64>,
+	/// The point looping instances of this sound should jump back
+	/// to the loop start point from, if it isn't the end of the sound.
+	pub default_loop_end: Option<f64>,
+	/// How instances of this sound interpolate between samples,
+	/// unless the instance settings say otherwise.
+	pub interpolation_mode: InterpolationMode,
 	/// The groups this sound belongs to.
 	pub groups: GroupSet,
+	/// How the sound's samples are stored in memory.
+	///
+	/// This has no effect on streamed sounds.
+	pub sample_format: SampleFormat,
+	/// Whether the sound should only store one channel if both
+	/// channels are the same, which halves the amount of memory
+	/// mono sounds take up.
+	///
+	/// This has no effect on streamed sounds.
+	pub keep_mono: bool,
 }
 
 impl SoundSettings {
@@ -87,6 +103,22 @@ impl SoundSettings {
 		}
 	}
 
+	/// Sets the default loop end point of the sound.
+	pub fn default_loop_end(self, default_loop_end: f64) -> Self {
+		Self {
+			default_loop_end: Some(default_loop_end),
+			..self
+		}
+	}
+
+	/// Sets how instances of this sound interpolate between samples.
+	pub fn interpolation_mode(self, interpolation_mode: InterpolationMode) -> Self {
+		Self {
+			interpolation_mode,
+			..self
+		}
+	}
+
 	/// Sets the group this sound belongs to.
 	pub fn groups(self, groups: impl Into<GroupSet>) -> Self {
 		Self {
@@ -94,6 +126,20 @@ impl SoundSettings {
 			..self
 		}
 	}
+
+	/// Sets how the sound's samples are stored in memory.
+	pub fn sample_format(self, sample_format: SampleFormat) -> Self {
+		Self {
+			sample_format,
+			..self
+		}
+	}
+
+	/// Sets whether the sound should only store one channel
+	/// if both channels are the same.
+	pub fn keep_mono(self, keep_mono: bool) -> Self {
+		Self { keep_mono, ..self }
+	}
 }
 
 impl Default for SoundSettings {
@@ -104,7 +150,11 @@ impl Default for SoundSettings {
 			cooldown: Some(0.0001),
 			semantic_duration: None,
 			default_loop_start: None,
+			default_loop_end: None,
+			interpolation_mode: InterpolationMode::default(),
 			groups: GroupSet::new(),
+			sample_format: SampleFormat::default(),
+			keep_mono: false,
 		}
 	}
 }
@@ -0,0 +1,372 @@
+//! Incremental decoders for each supported audio file format.
+
+use std::path::Path;
+#[cfg(any(feature = "mp3", feature = "ogg", feature = "flac", feature = "wav"))]
+use std::{fs::File, io::BufReader};
+
+#[cfg(any(feature = "ogg", feature = "wav"))]
+use crate::sound::metadata::Metadata;
+use crate::{sound::error::SoundFromFileError, Frame};
+
+/// The number of frames the wav decoder reads at a time.
+#[cfg(feature = "wav")]
+const WAV_CHUNK_SIZE: usize = 4096;
+
+/// Decodes an audio file a piece at a time.
+pub(super) trait Decoder: Send {
+	/// Returns the sample rate of the audio.
+	fn sample_rate(&self) -> u32;
+
+	/// Returns the number of frames in the file, if the file
+	/// says how long it is.
+	fn num_frames(&self) -> Option<u64>;
+
+	/// Decodes the next piece of the file. Returns an empty `Vec`
+	/// once the end of the file is reached.
+	fn decode(&mut self) -> Result<Vec<Frame>, SoundFromFileError>;
+
+	/// Moves to the frame at `index` or somewhere before it and
+	/// returns the index of the frame the decoder ended up at.
+	///
+	/// Formats that can't seek go back to the beginning of the file
+	/// (or stay where they are if `index` is ahead of them) and leave
+	/// it to the caller to decode its way to the right frame.
+	fn seek(&mut self, index: u64) -> Result<u64, SoundFromFileError>;
+
+	/// Returns `true` if [`seek`](Self::seek) can move backward
+	/// without decoding the file from the beginning.
+	fn can_seek_backward(&self) -> bool;
+}
+
+/// Reads the loop points and markers stored in the file at `path`.
+#[cfg(any(feature = "ogg", feature = "wav"))]
+pub(super) fn read_metadata(path: &Path) -> Result<Metadata, SoundFromFileError> {
+	match path.extension().and_then(|extension| extension.to_str()) {
+		#[cfg(feature = "ogg")]
+		Some("ogg") => {
+			// only the headers are read here, so this doesn't
+			// decode any audio
+			let reader =
+				lewton::inside_ogg::OggStreamReader::new(BufReader::new(File::open(path)?))?;
+			Ok(Metadata::from_ogg_comments(
+				&reader.comment_hdr.comment_list,
+			))
+		}
+		#[cfg(feature = "wav")]
+		Some("wav") => Ok(Metadata::read_wav(BufReader::new(File::open(path)?))?),
+		_ => Ok(Metadata::default()),
+	}
+}
+
+/// Opens a decoder for the file at `path`, choosing the format
+/// based on the file extension.
+pub(super) fn open(path: &Path) -> Result<Box<dyn Decoder>, SoundFromFileError> {
+	match path.extension().and_then(|extension| extension.to_str()) {
+		#[cfg(feature = "mp3")]
+		Some("mp3") => Ok(Box::new(Mp3Decoder::open(path)?)),
+		#[cfg(feature = "ogg")]
+		Some("ogg") => Ok(Box::new(OggDecoder::open(path)?)),
+		#[cfg(feature = "flac")]
+		Some("flac") => Ok(Box::new(FlacDecoder::open(path)?)),
+		#[cfg(feature = "wav")]
+		Some("wav") => Ok(Box::new(WavDecoder::open(path)?)),
+		_ => Err(SoundFromFileError::UnsupportedAudioFileFormat),
+	}
+}
+
+#[cfg(feature = "mp3")]
+struct Mp3Decoder {
+	path: std::path::PathBuf,
+	decoder: minimp3::Decoder<BufReader<File>>,
+	sample_rate: u32,
+	// the first frame has to be decoded to find out the
+	// sample rate, so it's kept here until it's asked for
+	first_frames: Option<Vec<Frame>>,
+	position: u64,
+}
+
+#[cfg(feature = "mp3")]
+impl Mp3Decoder {
+	fn open(path: &Path) -> Result<Self, SoundFromFileError> {
+		let mut decoder = minimp3::Decoder::new(BufReader::new(File::open(path)?));
+		let (sample_rate, first_frames) = match decoder.next_frame() {
+			Ok(frame) => (frame.sample_rate as u32, Self::convert_frame(frame)?),
+			Err(minimp3::Error::Eof) => return Err(SoundFromFileError::UnknownMp3SampleRate),
+			Err(error) => return Err(error.into()),
+		};
+		Ok(Self {
+			path: path.into(),
+			decoder,
+			sample_rate,
+			first_frames: Some(first_frames),
+			position: 0,
+		})
+	}
+
+	fn convert_frame(frame: minimp3::Frame) -> Result<Vec<Frame>, SoundFromFileError> {
+		match frame.channels {
+			1 => Ok(frame
+				.data
+				.iter()
+				.map(|sample| Frame::from_i32((*sample).into(), (*sample).into(), 16))
+				.collect()),
+			2 => Ok(frame
+				.data
+				.chunks_exact(2)
+				.map(|samples| Frame::from_i32(samples[0].into(), samples[1].into(), 16))
+				.collect()),
+			_ => Err(SoundFromFileError::UnsupportedChannelConfiguration),
+		}
+	}
+}
+
+#[cfg(feature = "mp3")]
+impl Decoder for Mp3Decoder {
+	fn sample_rate(&self) -> u32 {
+		self.sample_rate
+	}
+
+	fn num_frames(&self) -> Option<u64> {
+		None
+	}
+
+	fn decode(&mut self) -> Result<Vec<Frame>, SoundFromFileError> {
+		let frames = match self.first_frames.take() {
+			Some(frames) => frames,
+			None => match self.decoder.next_frame() {
+				Ok(frame) => {
+					if frame.sample_rate as u32 != self.sample_rate {
+						return Err(SoundFromFileError::VariableMp3SampleRate);
+					}
+					Self::convert_frame(frame)?
+				}
+				Err(minimp3::Error::Eof) => vec![],
+				Err(error) => return Err(error.into()),
+			},
+		};
+		self.position += frames.len() as u64;
+		Ok(frames)
+	}
+
+	fn seek(&mut self, index: u64) -> Result<u64, SoundFromFileError> {
+		if index < self.position {
+			*self = Self::open(&self.path)?;
+		}
+		Ok(self.position)
+	}
+
+	fn can_seek_backward(&self) -> bool {
+		false
+	}
+}
+
+#[cfg(feature = "ogg")]
+struct OggDecoder {
+	path: std::path::PathBuf,
+	reader: lewton::inside_ogg::OggStreamReader<BufReader<File>>,
+	position: u64,
+}
+
+#[cfg(feature = "ogg")]
+impl OggDecoder {
+	fn open(path: &Path) -> Result<Self, SoundFromFileError> {
+		let reader = lewton::inside_ogg::OggStreamReader::new(BufReader::new(File::open(path)?))?;
+		if !(1..=2).contains(&reader.ident_hdr.audio_channels) {
+			return Err(SoundFromFileError::UnsupportedChannelConfiguration);
+		}
+		Ok(Self {
+			path: path.into(),
+			reader,
+			position: 0,
+		})
+	}
+}
+
+#[cfg(feature = "ogg")]
+impl Decoder for OggDecoder {
+	fn sample_rate(&self) -> u32 {
+		self.reader.ident_hdr.audio_sample_rate
+	}
+
+	fn num_frames(&self) -> Option<u64> {
+		None
+	}
+
+	fn decode(&mut self) -> Result<Vec<Frame>, SoundFromFileError> {
+		use lewton::samples::Samples;
+		// some packets (like the first one) don't contain any
+		// samples, so keep going until one does
+		while let Some(packet) = self.reader.read_dec_packet_generic::<Vec<Vec<f32>>>()? {
+			let frames: Vec<Frame> = match packet.len() {
+				1 => packet[0]
+					.iter()
+					.map(|sample| Frame::from_mono(*sample))
+					.collect(),
+				2 => (0..packet.num_samples())
+					.map(|i| Frame::new(packet[0][i], packet[1][i]))
+					.collect(),
+				_ => return Err(SoundFromFileError::UnsupportedChannelConfiguration),
+			};
+			if !frames.is_empty() {
+				self.position += frames.len() as u64;
+				return Ok(frames);
+			}
+		}
+		Ok(vec![])
+	}
+
+	fn seek(&mut self, index: u64) -> Result<u64, SoundFromFileError> {
+		if index < self.position {
+			*self = Self::open(&self.path)?;
+		}
+		Ok(self.position)
+	}
+
+	fn can_seek_backward(&self) -> bool {
+		false
+	}
+}
+
+#[cfg(feature = "flac")]
+struct FlacDecoder {
+	path: std::path::PathBuf,
+	reader: claxon::FlacReader<BufReader<File>>,
+	buffer: Vec<i32>,
+	position: u64,
+}
+
+#[cfg(feature = "flac")]
+impl FlacDecoder {
+	fn open(path: &Path) -> Result<Self, SoundFromFileError> {
+		let reader = claxon::FlacReader::new(BufReader::new(File::open(path)?))?;
+		if !(1..=2).contains(&reader.streaminfo().channels) {
+			return Err(SoundFromFileError::UnsupportedChannelConfiguration);
+		}
+		Ok(Self {
+			path: path.into(),
+			reader,
+			buffer: vec![],
+			position: 0,
+		})
+	}
+}
+
+#[cfg(feature = "flac")]
+impl Decoder for FlacDecoder {
+	fn sample_rate(&self) -> u32 {
+		self.reader.streaminfo().sample_rate
+	}
+
+	fn num_frames(&self) -> Option<u64> {
+		self.reader.streaminfo().samples
+	}
+
+	fn decode(&mut self) -> Result<Vec<Frame>, SoundFromFileError> {
+		let bits_per_sample = self.reader.streaminfo().bits_per_sample;
+		let buffer = std::mem::take(&mut self.buffer);
+		let block = match self.reader.blocks().read_next_or_eof(buffer)? {
+			Some(block) => block,
+			None => return Ok(vec![]),
+		};
+		let frames: Vec<Frame> = match block.channels() {
+			1 => block
+				.channel(0)
+				.iter()
+				.map(|sample| Frame::from_i32(*sample, *sample, bits_per_sample))
+				.collect(),
+			2 => block
+				.channel(0)
+				.iter()
+				.zip(block.channel(1))
+				.map(|(left, right)| Frame::from_i32(*left, *right, bits_per_sample))
+				.collect(),
+			_ => return Err(SoundFromFileError::UnsupportedChannelConfiguration),
+		};
+		self.buffer = block.into_buffer();
+		self.position += frames.len() as u64;
+		Ok(frames)
+	}
+
+	fn seek(&mut self, index: u64) -> Result<u64, SoundFromFileError> {
+		if index < self.position {
+			*self = Self::open(&self.path)?;
+		}
+		Ok(self.position)
+	}
+
+	fn can_seek_backward(&self) -> bool {
+		false
+	}
+}
+
+#[cfg(feature = "wav")]
+struct WavDecoder {
+	reader: hound::WavReader<BufReader<File>>,
+}
+
+#[cfg(feature = "wav")]
+impl WavDecoder {
+	fn open(path: &Path) -> Result<Self, SoundFromFileError> {
+		let reader = hound::WavReader::new(BufReader::new(File::open(path)?))?;
+		if !(1..=2).contains(&reader.spec().channels) {
+			return Err(SoundFromFileError::UnsupportedChannelConfiguration);
+		}
+		Ok(Self { reader })
+	}
+}
+
+#[cfg(feature = "wav")]
+impl Decoder for WavDecoder {
+	fn sample_rate(&self) -> u32 {
+		self.reader.spec().sample_rate
+	}
+
+	fn num_frames(&self) -> Option<u64> {
+		Some(self.reader.duration().into())
+	}
+
+	fn decode(&mut self) -> Result<Vec<Frame>, SoundFromFileError> {
+		let spec = self.reader.spec();
+		let num_samples = WAV_CHUNK_SIZE * spec.channels as usize;
+		let mut frames = Vec::with_capacity(WAV_CHUNK_SIZE);
+		match spec.sample_format {
+			hound::SampleFormat::Float => {
+				let mut iter = self.reader.samples::<f32>().take(num_samples);
+				if spec.channels == 1 {
+					for sample in iter {
+						frames.push(Frame::from_mono(sample?));
+					}
+				} else {
+					while let (Some(left), Some(right)) = (iter.next(), iter.next()) {
+						frames.push(Frame::new(left?, right?));
+					}
+				}
+			}
+			hound::SampleFormat::Int => {
+				let bits_per_sample = spec.bits_per_sample.into();
+				let mut iter = self.reader.samples::<i32>().take(num_samples);
+				if spec.channels == 1 {
+					for sample in iter {
+						let sample = sample?;
+						frames.push(Frame::from_i32(sample, sample, bits_per_sample));
+					}
+				} else {
+					while let (Some(left), Some(right)) = (iter.next(), iter.next()) {
+						frames.push(Frame::from_i32(left?, right?, bits_per_sample));
+					}
+				}
+			}
+		}
+		Ok(frames)
+	}
+
+	fn seek(&mut self, index: u64) -> Result<u64, SoundFromFileError> {
+		let index = index.min(self.reader.duration().into());
+		self.reader.seek(index as u32)?;
+		Ok(index)
+	}
+
+	fn can_seek_backward(&self) -> bool {
+		true
+	}
+}
@@ -0,0 +1,572 @@
+//! Decoding sounds from disk while they play.
+//!
+//! Every instance of a streamed sound is decoded on one shared
+//! background thread, which decodes each file a piece at a time and
+//! sends the frames to the audio thread through a ring buffer for
+//! each instance. Every frame is sent along with its index in the
+//! file, so the audio thread can tell whether the frames it receives
+//! are the ones it needs, and if they aren't (for example, because
+//! the instance was seeked), it asks the background thread to seek.
+
+mod decoder;
+mod reader;
+#[cfg(all(test, feature = "wav"))]
+mod tests;
+
+pub(crate) use reader::StreamReader;
+
+use reader::following_index;
+
+use std::{
+	collections::VecDeque,
+	fmt::{Debug, Formatter},
+	panic::{catch_unwind, AssertUnwindSafe},
+	path::PathBuf,
+	sync::{
+		atomic::{AtomicBool, AtomicU64, Ordering},
+		Arc, Mutex, MutexGuard, PoisonError,
+	},
+	thread::Thread,
+	time::Duration,
+};
+
+use basedrop::{Handle, Owned};
+use ringbuf::{Producer, RingBuffer};
+
+use crate::{instance::InternalInstanceSettings, Frame};
+
+#[cfg(any(feature = "ogg", feature = "wav"))]
+use super::metadata::Metadata;
+use super::InterpolationMode;
+
+use self::decoder::Decoder;
+
+use super::error::SoundFromFileError;
+
+/// How much audio (in seconds) is decoded ahead of the
+/// playback position.
+const BUFFER_DURATION: f64 = 0.5;
+
+/// The maximum number of frames decoded at a time when
+/// playing backward.
+const REVERSE_CHUNK_SIZE: u64 = 4096;
+
+/// How long the background thread waits before checking
+/// whether there's room for more frames when none of the
+/// streams it's decoding have room.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Marks a seek request as being for backward playback.
+const BACKWARD_FLAG: u64 = 1 << 63;
+
+/// Means there's no seek request waiting to be handled.
+const NO_SEEK_REQUEST: u64 = u64::MAX;
+
+/// State shared between a [`StreamReader`] and the background thread.
+struct StreamShared {
+	/// The index of the frame the background thread should start
+	/// decoding from next, with [`BACKWARD_FLAG`] set if it should
+	/// decode backward, or [`NO_SEEK_REQUEST`].
+	seek_request: AtomicU64,
+	/// Set when the instance is done with the reader so the
+	/// thread knows to stop decoding it.
+	stopped: AtomicBool,
+	/// Set by the thread if the file couldn't be decoded.
+	failed: AtomicBool,
+}
+
+impl StreamShared {
+	fn request_seek(&self, index: u64, backward: bool) {
+		let request = if backward {
+			index | BACKWARD_FLAG
+		} else {
+			index
+		};
+		self.seek_request.store(request, Ordering::Relaxed);
+	}
+
+	fn take_seek_request(&self) -> Option<(u64, bool)> {
+		match self.seek_request.swap(NO_SEEK_REQUEST, Ordering::Relaxed) {
+			NO_SEEK_REQUEST => None,
+			request => Some((request & !BACKWARD_FLAG, request & BACKWARD_FLAG != 0)),
+		}
+	}
+}
+
+/// An audio file that's decoded as it plays.
+pub(crate) struct StreamSource {
+	path: PathBuf,
+	sample_rate: u32,
+	num_frames: u64,
+	/// Whether the file can be decoded backward without starting
+	/// over from the beginning for every chunk.
+	can_play_backward: bool,
+}
+
+impl StreamSource {
+	/// Opens the file at `path` to check that it can be decoded
+	/// and to find out its sample rate and length.
+	///
+	/// If the file doesn't say how long it is, it's decoded from
+	/// start to finish to count the frames.
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn new(path: PathBuf) -> Result<Self, SoundFromFileError> {
+		let mut decoder = decoder::open(&path)?;
+		let num_frames = match decoder.num_frames() {
+			Some(num_frames) => num_frames,
+			None => {
+				let mut num_frames = 0;
+				loop {
+					let frames = decoder.decode()?;
+					if frames.is_empty() {
+						break num_frames;
+					}
+					num_frames += frames.len() as u64;
+				}
+			}
+		};
+		Ok(Self {
+			path,
+			sample_rate: decoder.sample_rate(),
+			num_frames,
+			can_play_backward: decoder.can_seek_backward(),
+		})
+	}
+
+	pub fn sample_rate(&self) -> u32 {
+		self.sample_rate
+	}
+
+	/// Reads the loop points and markers stored in the file.
+	#[cfg(any(feature = "ogg", feature = "wav"))]
+	pub(super) fn read_metadata(&self) -> Result<Metadata, SoundFromFileError> {
+		decoder::read_metadata(&self.path)
+	}
+
+	pub fn num_frames(&self) -> u64 {
+		self.num_frames
+	}
+
+	pub fn can_play_backward(&self) -> bool {
+		self.can_play_backward
+	}
+}
+
+impl Debug for StreamSource {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("StreamSource")
+			.field("path", &self.path)
+			.field("sample_rate", &self.sample_rate)
+			.field("num_frames", &self.num_frames)
+			.field("can_play_backward", &self.can_play_backward)
+			.finish()
+	}
+}
+
+/// The background thread that decodes every streamed sound.
+///
+/// The thread is started when the first stream is added, and it
+/// exits once every stream it was decoding has been stopped, so
+/// it only runs while streamed sounds are playing.
+#[derive(Clone, Default)]
+pub(crate) struct DecodingThread {
+	state: Arc<Mutex<DecodingThreadState>>,
+}
+
+#[derive(Default)]
+struct DecodingThreadState {
+	/// Streams the thread hasn't picked up yet.
+	new_streams: Vec<Stream>,
+	/// The thread, if it's running.
+	thread: Option<Thread>,
+}
+
+impl DecodingThread {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	fn state(&self) -> MutexGuard<'_, DecodingThreadState> {
+		self.state.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	fn add_stream(&self, stream: Stream) {
+		let mut state = self.state();
+		state.new_streams.push(stream);
+		match &state.thread {
+			Some(thread) => thread.unpark(),
+			None => {
+				let thread_state = self.state.clone();
+				let thread = std::thread::spawn(move || Self::run(thread_state));
+				state.thread = Some(thread.thread().clone());
+			}
+		}
+	}
+
+	/// Returns `true` if the thread is running.
+	#[cfg(test)]
+	fn is_running(&self) -> bool {
+		self.state().thread.is_some()
+	}
+
+	fn run(state: Arc<Mutex<DecodingThreadState>>) {
+		let mut streams = vec![];
+		let mut frames = vec![];
+		loop {
+			{
+				let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+				streams.append(&mut state.new_streams);
+				// the thread is marked as stopped while the lock is
+				// held, so a stream can't be added in the meantime
+				if streams.is_empty() {
+					state.thread = None;
+					return;
+				}
+			}
+			// each stream decodes one piece at a time, so streams
+			// that need a lot of frames don't hold up the others
+			let mut decoded_frames = false;
+			let mut i = 0;
+			while i < streams.len() {
+				let stream = &mut streams[i];
+				let status = catch_unwind(AssertUnwindSafe(|| stream.decode_next(&mut frames)))
+					.unwrap_or_else(|_| {
+						stream.shared.failed.store(true, Ordering::Relaxed);
+						StreamStatus::Finished
+					});
+				match status {
+					StreamStatus::Decoded => {
+						decoded_frames = true;
+						i += 1;
+					}
+					StreamStatus::Waiting => i += 1,
+					StreamStatus::Finished => {
+						streams.swap_remove(i);
+					}
+				}
+			}
+			if !decoded_frames {
+				std::thread::park_timeout(POLL_INTERVAL);
+			}
+		}
+	}
+}
+
+/// Starts decoding a streamed sound for each of its instances.
+#[derive(Clone)]
+pub(crate) struct StreamStarter {
+	source: Arc<StreamSource>,
+	/// The sound's interpolation mode, which is used unless
+	/// an instance's settings override it.
+	interpolation_mode: InterpolationMode,
+	decoding_thread: DecodingThread,
+	collector_handle: Handle,
+}
+
+impl StreamStarter {
+	pub fn new(
+		source: Arc<StreamSource>,
+		interpolation_mode: InterpolationMode,
+		decoding_thread: DecodingThread,
+		collector_handle: Handle,
+	) -> Self {
+		Self {
+			source,
+			interpolation_mode,
+			decoding_thread,
+			collector_handle,
+		}
+	}
+
+	/// Starts decoding the sound on the background thread for an
+	/// instance with the given settings.
+	pub fn start(&self, settings: &InternalInstanceSettings) -> Owned<StreamReader> {
+		let sample_rate = self.source.sample_rate;
+		let num_frames = self.source.num_frames;
+		let loop_start = settings
+			.loop_start
+			.map(|loop_start| (loop_start * sample_rate as f64).max(0.0) as u64)
+			.filter(|loop_start| *loop_start < num_frames);
+		let interpolation_mode = settings
+			.interpolation_mode
+			.unwrap_or(self.interpolation_mode);
+		// the thread keeps going for a few frames past the end of
+		// the loop, since they're used to interpolate the positions
+		// right before it
+		let loop_end = settings
+			.loop_end
+			.map(|loop_end| {
+				((loop_end * sample_rate as f64).round() as u64
+					+ interpolation_mode.num_frames_after() as u64)
+					.min(num_frames)
+			})
+			.filter(|loop_end| matches!(loop_start, Some(loop_start) if loop_start < *loop_end))
+			.unwrap_or(num_frames);
+		let capacity = ((sample_rate as f64 * BUFFER_DURATION) as usize).max(1);
+		let (producer, consumer) = RingBuffer::new(capacity).split();
+		let shared = Arc::new(StreamShared {
+			seek_request: AtomicU64::new(NO_SEEK_REQUEST),
+			stopped: AtomicBool::new(false),
+			failed: AtomicBool::new(false),
+		});
+		let mut start_index = (settings.start_position * sample_rate as f64).max(0.0) as u64;
+		// when playing backward, the frames after the start position
+		// are needed to interpolate it, so they're sent first
+		if settings.reverse {
+			start_index += interpolation_mode.num_frames_after() as u64;
+		}
+		let start_index = start_index.min(num_frames.saturating_sub(1));
+		shared.request_seek(start_index, settings.reverse);
+		let stream = Stream {
+			path: self.source.path.clone(),
+			source: None,
+			num_frames,
+			loop_start,
+			loop_end,
+			producer,
+			shared,
+			next_index: 0,
+			backward: false,
+			pending: VecDeque::new(),
+		};
+		let reader = StreamReader::new(
+			consumer,
+			&stream,
+			&self.source,
+			start_index,
+			settings.reverse,
+			interpolation_mode,
+		);
+		self.decoding_thread.add_stream(stream);
+		Owned::new(&self.collector_handle, reader)
+	}
+}
+
+impl Debug for StreamStarter {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("StreamStarter")
+			.field("source", &self.source)
+			.field("interpolation_mode", &self.interpolation_mode)
+			.finish()
+	}
+}
+
+/// Reads frames from a decoder, keeping track of the index
+/// of each frame.
+struct Source {
+	decoder: Box<dyn Decoder>,
+	/// The index of the first frame in `buffer`.
+	position: u64,
+	/// Frames that have been decoded but not read yet.
+	buffer: VecDeque<Frame>,
+}
+
+impl Source {
+	/// Moves to the frame at `index`.
+	fn seek(&mut self, index: u64) -> Result<(), SoundFromFileError> {
+		if index >= self.position && index <= self.position + self.buffer.len() as u64 {
+			self.buffer.drain(..(index - self.position) as usize);
+			self.position = index;
+			return Ok(());
+		}
+		self.position = self.decoder.seek(index)?;
+		self.buffer.clear();
+		// decode the frames between where the decoder ended
+		// up and where we want to be
+		while self.position < index {
+			if self.buffer.is_empty() && !self.fill_buffer()? {
+				break;
+			}
+			let num_frames_to_skip = ((index - self.position) as usize).min(self.buffer.len());
+			self.buffer.drain(..num_frames_to_skip);
+			self.position += num_frames_to_skip as u64;
+		}
+		Ok(())
+	}
+
+	/// Decodes the next piece of the file into the buffer. Returns
+	/// `false` if the end of the file has been reached.
+	fn fill_buffer(&mut self) -> Result<bool, SoundFromFileError> {
+		let frames = self.decoder.decode()?;
+		self.buffer.extend(frames);
+		Ok(!self.buffer.is_empty())
+	}
+
+	/// Reads up to `num_frames` frames into `frames`, returning
+	/// the number of frames read. Fewer frames are read if the end
+	/// of the file is reached.
+	fn read(
+		&mut self,
+		num_frames: usize,
+		frames: &mut Vec<Frame>,
+	) -> Result<usize, SoundFromFileError> {
+		let mut num_frames_read = 0;
+		while num_frames_read < num_frames {
+			if self.buffer.is_empty() && !self.fill_buffer()? {
+				break;
+			}
+			let num_frames_to_read = (num_frames - num_frames_read).min(self.buffer.len());
+			frames.extend(self.buffer.drain(..num_frames_to_read));
+			self.position += num_frames_to_read as u64;
+			num_frames_read += num_frames_to_read;
+		}
+		Ok(num_frames_read)
+	}
+}
+
+/// What happened when the background thread tried to decode
+/// the next piece of a stream.
+enum StreamStatus {
+	/// New frames were decoded.
+	Decoded,
+	/// There's nothing to decode until the audio thread makes room
+	/// in the ring buffer or asks the thread to seek.
+	Waiting,
+	/// The instance is done with the stream, or the file couldn't
+	/// be decoded, so the thread can forget about it.
+	Finished,
+}
+
+/// Decodes an instance's sound on the background thread.
+struct Stream {
+	path: PathBuf,
+	/// The file is opened on the background thread the first
+	/// time the stream is decoded.
+	source: Option<Source>,
+	num_frames: u64,
+	loop_start: Option<u64>,
+	/// The frame after the last one that's sent before looping.
+	loop_end: u64,
+	producer: Producer<(u64, Frame)>,
+	shared: Arc<StreamShared>,
+	/// The index of the next frame to decode.
+	next_index: u64,
+	backward: bool,
+	/// Frames that have been decoded but didn't fit in the
+	/// ring buffer yet.
+	pending: VecDeque<(u64, Frame)>,
+}
+
+impl Stream {
+	/// Handles any seek request from the audio thread and decodes
+	/// the next piece of the sound if there's room for it.
+	fn decode_next(&mut self, frames: &mut Vec<Frame>) -> StreamStatus {
+		if self.shared.stopped.load(Ordering::Relaxed) {
+			return StreamStatus::Finished;
+		}
+		let mut source = match self.source.take() {
+			Some(source) => source,
+			None => match decoder::open(&self.path) {
+				Ok(decoder) => Source {
+					decoder,
+					position: 0,
+					buffer: VecDeque::new(),
+				},
+				Err(_) => {
+					self.shared.failed.store(true, Ordering::Relaxed);
+					return StreamStatus::Finished;
+				}
+			},
+		};
+		let status = self.decode_next_from(&mut source, frames);
+		self.source = Some(source);
+		status
+	}
+
+	fn decode_next_from(&mut self, source: &mut Source, frames: &mut Vec<Frame>) -> StreamStatus {
+		if let Some((index, backward)) = self.shared.take_seek_request() {
+			self.next_index = index;
+			self.backward = backward;
+			self.pending.clear();
+		}
+		while let Some(frame) = self.pending.pop_front() {
+			if let Err(frame) = self.producer.push(frame) {
+				self.pending.push_front(frame);
+				break;
+			}
+		}
+		if !self.pending.is_empty() {
+			return StreamStatus::Waiting;
+		}
+		frames.clear();
+		let decoded_frames = if self.backward {
+			self.decode_backward(source, frames)
+		} else {
+			self.decode_forward(source, frames)
+		};
+		match decoded_frames {
+			Ok(true) => StreamStatus::Decoded,
+			// we've reached the end of the sound, so we wait in case
+			// the audio thread wants to seek somewhere else
+			Ok(false) => StreamStatus::Waiting,
+			Err(_) => {
+				self.shared.failed.store(true, Ordering::Relaxed);
+				StreamStatus::Finished
+			}
+		}
+	}
+
+	/// Decodes the next piece of the sound. Returns `false` if
+	/// there's nothing left to decode.
+	fn decode_forward(
+		&mut self,
+		source: &mut Source,
+		frames: &mut Vec<Frame>,
+	) -> Result<bool, SoundFromFileError> {
+		if self.next_index >= self.num_frames {
+			return Ok(false);
+		}
+		source.seek(self.next_index)?;
+		if source.buffer.is_empty() && !source.fill_buffer()? {
+			// the file is shorter than it said it was
+			self.next_index = self.num_frames;
+			return Ok(false);
+		}
+		let end = if self.next_index < self.loop_end {
+			self.loop_end
+		} else {
+			self.num_frames
+		};
+		let num_frames = source.buffer.len().min((end - self.next_index) as usize);
+		source.read(num_frames, frames)?;
+		for (i, frame) in frames.drain(..).enumerate() {
+			self.pending.push_back((self.next_index + i as u64, frame));
+		}
+		let last_index = self.next_index + num_frames as u64 - 1;
+		self.next_index = following_index(last_index, false, self.loop_end, self.loop_start)
+			.unwrap_or(self.num_frames);
+		Ok(true)
+	}
+
+	/// Decodes the piece of the sound before the next frame and
+	/// queues it up in reverse order. Returns `false` if there's
+	/// nothing left to decode.
+	fn decode_backward(
+		&mut self,
+		source: &mut Source,
+		frames: &mut Vec<Frame>,
+	) -> Result<bool, SoundFromFileError> {
+		if self.next_index >= self.num_frames {
+			return Ok(false);
+		}
+		let first_index = match self.loop_start {
+			Some(loop_start) if self.next_index >= loop_start => loop_start,
+			_ => 0,
+		};
+		let chunk_start = (self.next_index + 1)
+			.saturating_sub(REVERSE_CHUNK_SIZE)
+			.max(first_index);
+		source.seek(chunk_start)?;
+		let num_frames = source.read((self.next_index + 1 - chunk_start) as usize, frames)?;
+		if num_frames == 0 {
+			self.next_index = self.num_frames;
+			return Ok(false);
+		}
+		for (i, frame) in frames.drain(..).enumerate().rev() {
+			self.pending.push_back((chunk_start + i as u64, frame));
+		}
+		self.next_index = following_index(chunk_start, true, self.loop_end, self.loop_start)
+			.unwrap_or(self.num_frames);
+		Ok(true)
+	}
+}
@@ -0,0 +1,247 @@
+use std::{
+	fmt::{Debug, Formatter},
+	sync::{atomic::Ordering, Arc},
+};
+
+use ringbuf::Consumer;
+
+use crate::{
+	sound::{interpolation::MAX_FRAMES, InterpolationMode},
+	Frame,
+};
+
+use super::{Stream, StreamShared, StreamSource};
+
+/// The number of recently received frames the reader keeps
+/// around for interpolation. Each frame is stored in the slot
+/// for its index modulo the cache size, so all of the frames
+/// used to interpolate one position fit at the same time.
+const CACHE_SIZE: usize = MAX_FRAMES;
+
+/// Returns the index of the frame the background thread sends
+/// after the frame at `index`, or `None` if it doesn't send
+/// any more frames.
+///
+/// `loop_end` is the frame after the last one that's sent before
+/// looping, which is the number of frames in the sound unless the
+/// loop ends early. When playing forward, the frame after the last
+/// one is the loop start point, and when playing backward, the frame
+/// after the loop start point (or the first frame) is the last one.
+pub(super) fn following_index(
+	index: u64,
+	backward: bool,
+	loop_end: u64,
+	loop_start: Option<u64>,
+) -> Option<u64> {
+	if backward {
+		match index.checked_sub(1) {
+			Some(previous_index) if loop_start != Some(index) => Some(previous_index),
+			_ => loop_start.and(loop_end.checked_sub(1)),
+		}
+	} else if index + 1 < loop_end {
+		Some(index + 1)
+	} else {
+		loop_start
+	}
+}
+
+/// The audio thread's end of a streamed sound.
+///
+/// Receives frames decoded on the background thread and asks the
+/// thread to seek when it needs frames the thread isn't sending.
+pub(crate) struct StreamReader {
+	consumer: Consumer<(u64, Frame)>,
+	pub(super) shared: Arc<StreamShared>,
+	sample_rate: u32,
+	num_frames: u64,
+	can_play_backward: bool,
+	loop_start: Option<u64>,
+	/// The frame after the last one that's sent before looping.
+	loop_end: u64,
+	/// The index of the frame the background thread is expected
+	/// to send next, or `None` if it's done sending frames.
+	next_index: Option<u64>,
+	/// Whether the background thread is decoding backward.
+	backward: bool,
+	interpolation_mode: InterpolationMode,
+	cache: [Option<(u64, Frame)>; CACHE_SIZE],
+}
+
+impl StreamReader {
+	/// Creates a reader for a stream that starts decoding
+	/// at `start_index`.
+	pub(super) fn new(
+		consumer: Consumer<(u64, Frame)>,
+		stream: &Stream,
+		source: &StreamSource,
+		start_index: u64,
+		backward: bool,
+		interpolation_mode: InterpolationMode,
+	) -> Self {
+		Self {
+			consumer,
+			shared: stream.shared.clone(),
+			sample_rate: source.sample_rate(),
+			num_frames: source.num_frames(),
+			can_play_backward: source.can_play_backward(),
+			loop_start: stream.loop_start,
+			loop_end: stream.loop_end,
+			next_index: Some(start_index),
+			backward,
+			interpolation_mode,
+			cache: [None; CACHE_SIZE],
+		}
+	}
+
+	/// Returns `true` if the file couldn't be decoded, in which case
+	/// no more frames will be received.
+	pub fn failed(&self) -> bool {
+		self.shared.failed.load(Ordering::Relaxed)
+	}
+
+	/// Returns `false` if the file's format can only be decoded
+	/// backward by starting over from the beginning of the file
+	/// for every chunk, which is too slow to keep up with playback.
+	pub fn can_play_backward(&self) -> bool {
+		self.can_play_backward
+	}
+
+	/// Tells the background thread to stop decoding.
+	///
+	/// The reader itself is freed later by the resource
+	/// collector, so this should be called as soon as the
+	/// instance is done with it.
+	pub fn stop(&self) {
+		self.shared.stopped.store(true, Ordering::Relaxed);
+	}
+
+	fn cached_frame(&self, index: i64) -> Option<Frame> {
+		if index < 0 || index as u64 >= self.num_frames {
+			return Some(Frame::from_mono(0.0));
+		}
+		match self.cache[index as usize % CACHE_SIZE] {
+			Some((cached_index, frame)) if cached_index == index as u64 => Some(frame),
+			_ => None,
+		}
+	}
+
+	/// Makes sure the frame at `index` is in the cache, receiving
+	/// frames from the background thread if necessary. Returns
+	/// `false` if the frame hasn't been decoded yet.
+	fn receive_frame(&mut self, index: u64, backward: bool) -> bool {
+		if self.cached_frame(index as i64).is_some() {
+			return true;
+		}
+		// if the background thread isn't going to send the frame
+		// any time soon, ask it to start from that frame instead
+		let reachable = match self.next_index {
+			Some(next_index) if self.backward == backward => {
+				let distance = if backward {
+					next_index.checked_sub(index)
+				} else {
+					index.checked_sub(next_index)
+				};
+				matches!(distance, Some(distance) if distance as usize <= self.consumer.capacity())
+			}
+			_ => false,
+		};
+		if !reachable {
+			self.shared.request_seek(index, backward);
+			self.next_index = Some(index);
+			self.backward = backward;
+		}
+		while let Some((received_index, frame)) = self.consumer.pop() {
+			// frames that were decoded before the last seek request
+			// are skipped
+			if Some(received_index) != self.next_index {
+				continue;
+			}
+			self.cache[received_index as usize % CACHE_SIZE] = Some((received_index, frame));
+			self.next_index =
+				following_index(received_index, backward, self.loop_end, self.loop_start);
+			if received_index == index {
+				return true;
+			}
+		}
+		false
+	}
+
+	/// Gets the frame at an arbitrary time in seconds,
+	/// interpolating between samples if necessary.
+	///
+	/// Returns `None` if the frames around that time haven't
+	/// been decoded yet.
+	pub fn frame_at_position(&mut self, position: f64, backward: bool) -> Option<Frame> {
+		let sample_position = self.sample_rate as f64 * position;
+		let fraction = sample_position.rem_euclid(1.0) as f32;
+		let current_index = sample_position.floor() as i64;
+		if current_index < 0 || current_index as u64 >= self.num_frames {
+			return Some(Frame::from_mono(0.0));
+		}
+		let current_index = current_index as u64;
+		// receive the frames needed for interpolation in the order
+		// the background thread sends them. the frames on the other
+		// side of the current frame are only used if they're
+		// already cached.
+		if backward {
+			let first_index = match self.loop_start {
+				Some(loop_start) if current_index >= loop_start => loop_start,
+				_ => 0,
+			};
+			let num_frames_before = self.interpolation_mode.num_frames_before() as u64;
+			for index in (current_index
+				.saturating_sub(num_frames_before)
+				.max(first_index)..=current_index)
+				.rev()
+			{
+				if !self.receive_frame(index, true) {
+					return None;
+				}
+			}
+		} else {
+			// the frames after the end of the loop aren't sent
+			// until the instance loops
+			let last_index = if current_index < self.loop_end {
+				self.loop_end - 1
+			} else {
+				self.num_frames - 1
+			};
+			let num_frames_after = self.interpolation_mode.num_frames_after() as u64;
+			for index in current_index..=(current_index + num_frames_after).min(last_index) {
+				if !self.receive_frame(index, false) {
+					return None;
+				}
+			}
+		}
+		let first_index = current_index as i64 - self.interpolation_mode.num_frames_before() as i64;
+		let mut frames = [Frame::from_mono(0.0); MAX_FRAMES];
+		let frames = &mut frames[..self.interpolation_mode.num_frames()];
+		for (i, frame) in frames.iter_mut().enumerate() {
+			*frame = self
+				.cached_frame(first_index + i as i64)
+				.unwrap_or(Frame::from_mono(0.0));
+		}
+		Some(self.interpolation_mode.interpolate(frames, fraction))
+	}
+}
+
+impl Drop for StreamReader {
+	fn drop(&mut self) {
+		self.stop();
+	}
+}
+
+impl Debug for StreamReader {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("StreamReader")
+			.field("sample_rate", &self.sample_rate)
+			.field("num_frames", &self.num_frames)
+			.field("can_play_backward", &self.can_play_backward)
+			.field("loop_start", &self.loop_start)
+			.field("loop_end", &self.loop_end)
+			.field("next_index", &self.next_index)
+			.field("backward", &self.backward)
+			.field("interpolation_mode", &self.interpolation_mode)
+			.finish()
+	}
+}
@@ -0,0 +1,155 @@
+use std::{path::PathBuf, sync::Arc, time::Instant};
+
+use basedrop::Collector;
+
+use crate::{
+	instance::{Instance, InstanceSettings, InstanceSlots},
+	mixer::TrackIndex,
+	parameter::Parameters,
+	playable::Playables,
+	sound::{InterpolationMode, SoundId},
+	Frame,
+};
+
+use super::{DecodingThread, StreamShared, StreamSource, StreamStarter};
+
+/// Writes a short mono wav file and returns its path.
+fn write_wav_file(name: &str) -> PathBuf {
+	let path = std::env::temp_dir().join(format!("kira-{}-{}.wav", name, std::process::id()));
+	let mut writer = hound::WavWriter::create(
+		&path,
+		hound::WavSpec {
+			channels: 1,
+			sample_rate: 48000,
+			bits_per_sample: 16,
+			sample_format: hound::SampleFormat::Int,
+		},
+	)
+	.unwrap();
+	for i in 1..=1000 {
+		writer.write_sample(i as i16 * 10).unwrap();
+	}
+	writer.finalize().unwrap();
+	path
+}
+
+/// Creates an instance of a streamed sound and returns it along
+/// with the state shared with the background thread.
+fn create_instance(
+	source: StreamSource,
+	settings: InstanceSettings,
+	decoding_thread: &DecodingThread,
+	collector: &Collector,
+) -> (Instance, Arc<StreamShared>) {
+	let duration = source.num_frames() as f64 / source.sample_rate() as f64;
+	let settings = settings.into_internal(duration, None, None, TrackIndex::Main);
+	let starter = StreamStarter::new(
+		Arc::new(source),
+		InterpolationMode::Linear,
+		decoding_thread.clone(),
+		collector.handle(),
+	);
+	let stream = starter.start(&settings);
+	let shared = stream.shared.clone();
+	let instance = Instance::new(
+		SoundId::new().into(),
+		duration,
+		None,
+		settings,
+		Some(stream),
+		InstanceSlots::new(1).claim(None),
+	);
+	(instance, shared)
+}
+
+#[test]
+fn stops_decoding_when_the_instance_is_removed() {
+	let path = write_wav_file("stream-stop-test");
+	let collector = Collector::new();
+	let (instance, shared) = create_instance(
+		StreamSource::new(path.clone()).unwrap(),
+		InstanceSettings::new(),
+		&DecodingThread::new(),
+		&collector,
+	);
+	drop(instance);
+	// the reader is still waiting to be collected, but the
+	// background thread should let go of its reference
+	let start_time = Instant::now();
+	while Arc::strong_count(&shared) > 2 {
+		assert!(
+			start_time.elapsed().as_secs() < 5,
+			"the background thread never stopped"
+		);
+		std::thread::sleep(std::time::Duration::from_millis(1));
+	}
+	assert_eq!(collector.alloc_count(), 1);
+	std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn stops_instances_that_cant_play_backward() {
+	let path = write_wav_file("stream-reverse-test");
+	let collector = Collector::new();
+	// pretend the file is in a format that can only seek by
+	// decoding from the beginning
+	let source = StreamSource {
+		can_play_backward: false,
+		..StreamSource::new(path.clone()).unwrap()
+	};
+	let (mut instance, _) = create_instance(
+		source,
+		InstanceSettings::new().reverse(),
+		&DecodingThread::new(),
+		&collector,
+	);
+	assert_eq!(
+		instance.get_sample(&Playables::new(0, 0)),
+		Frame::from_mono(0.0)
+	);
+	let parameters = Parameters::new(0);
+	instance.update(1.0 / 48000.0, &parameters);
+	assert!(instance.finished());
+	std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn decodes_every_stream_on_one_thread() {
+	let path = write_wav_file("stream-thread-test");
+	let collector = Collector::new();
+	let decoding_thread = DecodingThread::new();
+	assert!(!decoding_thread.is_running());
+	let mut instances = vec![];
+	for _ in 0..2 {
+		instances.push(create_instance(
+			StreamSource::new(path.clone()).unwrap(),
+			InstanceSettings::new(),
+			&decoding_thread,
+			&collector,
+		));
+	}
+	assert!(decoding_thread.is_running());
+	// both instances should get their frames from the thread
+	let playables = Playables::new(0, 0);
+	for (instance, _) in &mut instances {
+		let start_time = Instant::now();
+		while instance.get_sample(&playables) == Frame::from_mono(0.0) {
+			assert!(
+				start_time.elapsed().as_secs() < 5,
+				"the stream was never decoded"
+			);
+			std::thread::sleep(std::time::Duration::from_millis(1));
+		}
+	}
+	// the thread exits once it isn't decoding anything
+	drop(instances);
+	let start_time = Instant::now();
+	while decoding_thread.is_running() {
+		assert!(
+			start_time.elapsed().as_secs() < 5,
+			"the background thread never exited"
+		);
+		std::thread::sleep(std::time::Duration::from_millis(1));
+	}
+	std::fs::remove_file(&path).ok();
+}
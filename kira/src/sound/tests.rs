@@ -0,0 +1,372 @@
+use crate::Frame;
+
+use super::{InterpolationMode, SampleCursor, SampleFormat, Sound, SoundSettings};
+
+fn sound(frames: Vec<Frame>, sample_format: SampleFormat, keep_mono: bool) -> Sound {
+	Sound::from_frames(
+		100,
+		frames,
+		SoundSettings::new()
+			.sample_format(sample_format)
+			.keep_mono(keep_mono),
+	)
+}
+
+#[test]
+fn reads_the_same_frames_regardless_of_storage() {
+	// 16-bit values, so storing them as i16 is lossless
+	let sine = |i: usize, frequency: f32| {
+		Frame::from_i32(
+			((i as f32 * frequency).sin() * 16384.0) as i32,
+			((i as f32 * frequency * 0.5).cos() * 16384.0) as i32,
+			16,
+		)
+	};
+	let stereo_frames: Vec<Frame> = (0..1000).map(|i| sine(i, 0.05)).collect();
+	let mono_frames: Vec<Frame> = stereo_frames
+		.iter()
+		.map(|frame| Frame::from_mono(frame.left))
+		.collect();
+	let positions = (0..1100).map(|i| i as f64 * 0.00917 - 0.05);
+
+	for frames in [stereo_frames, mono_frames] {
+		let reference = sound(frames.clone(), SampleFormat::F32, false);
+		for (sample_format, keep_mono) in [
+			(SampleFormat::F32, true),
+			(SampleFormat::I16, false),
+			(SampleFormat::I16, true),
+		] {
+			let sound = sound(frames.clone(), sample_format, keep_mono);
+			for position in positions.clone() {
+				assert_eq!(
+					sound.get_frame_at_position(position),
+					reference.get_frame_at_position(position),
+					"{:?} (keep_mono: {}) at {}",
+					sample_format,
+					keep_mono,
+					position
+				);
+			}
+		}
+		// adpcm is lossy, so it only has to be close
+		for keep_mono in [false, true] {
+			let sound = sound(frames.clone(), SampleFormat::Adpcm, keep_mono);
+			// instances keep track of where they left off decoding,
+			// which shouldn't change the result, whether they're
+			// playing forward or backward
+			let mut forward_cursor = SampleCursor::default();
+			for position in positions.clone() {
+				assert_eq!(
+					sound.frame_at_position_with_cursor(
+						position,
+						sound.interpolation_mode(),
+						&mut forward_cursor
+					),
+					sound.get_frame_at_position(position)
+				);
+			}
+			let mut backward_cursor = SampleCursor::default();
+			for position in positions.clone().rev() {
+				assert_eq!(
+					sound.frame_at_position_with_cursor(
+						position,
+						sound.interpolation_mode(),
+						&mut backward_cursor
+					),
+					sound.get_frame_at_position(position)
+				);
+			}
+			for position in positions.clone() {
+				let frame = sound.get_frame_at_position(position);
+				let expected = reference.get_frame_at_position(position);
+				assert!(
+					(frame.left - expected.left).abs() < 0.02
+						&& (frame.right - expected.right).abs() < 0.02,
+					"adpcm (keep_mono: {}) at {}: expected {:?}, got {:?}",
+					keep_mono,
+					position,
+					expected,
+					frame
+				);
+			}
+		}
+	}
+}
+
+#[test]
+fn uses_less_memory_with_compact_storage() {
+	let mono_frames = vec![Frame::from_mono(0.5); 1024];
+	let stereo_frames = vec![Frame::new(0.5, -0.5); 1024];
+	let memory_size = |frames: &Vec<Frame>, sample_format, keep_mono| {
+		sound(frames.clone(), sample_format, keep_mono).memory_size()
+	};
+	assert_eq!(memory_size(&mono_frames, SampleFormat::F32, false), 8192);
+	assert_eq!(memory_size(&mono_frames, SampleFormat::F32, true), 4096);
+	assert_eq!(memory_size(&mono_frames, SampleFormat::I16, true), 2048);
+	assert!(memory_size(&mono_frames, SampleFormat::Adpcm, true) < 1024);
+	// stereo sounds can't be stored as mono
+	assert_eq!(memory_size(&stereo_frames, SampleFormat::F32, true), 8192);
+}
+
+#[test]
+fn resamples_without_aliasing() {
+	let sine = |sample_rate: u32, frequency: f64, num_frames: usize| -> Vec<Frame> {
+		(0..num_frames)
+			.map(|i| {
+				Frame::from_mono(
+					(i as f64 / sample_rate as f64 * frequency * std::f64::consts::TAU).sin()
+						as f32 * 0.5,
+				)
+			})
+			.collect()
+	};
+
+	// upsampling keeps the tone intact
+	let sound = Sound::from_frames(22050, sine(22050, 1000.0, 22050), SoundSettings::new())
+		.resampled(48000);
+	assert_eq!(sound.sample_rate(), 48000);
+	assert_eq!(sound.duration(), 1.0);
+	let expected = sine(48000, 1000.0, 48000);
+	// skip the edges, which fade in and out
+	for (i, expected) in expected.iter().enumerate().take(47000).skip(1000) {
+		let frame = sound.get_frame_at_position(i as f64 / 48000.0);
+		assert!(
+			(frame.left - expected.left).abs() < 0.001,
+			"frame {}: expected {:?}, got {:?}",
+			i,
+			expected,
+			frame
+		);
+	}
+
+	// downsampling removes tones the new sample rate can't represent
+	let sound = Sound::from_frames(48000, sine(48000, 15000.0, 48000), SoundSettings::new())
+		.resampled(22050);
+	assert_eq!(sound.sample_rate(), 22050);
+	for i in 1000..21000 {
+		let frame = sound.get_frame_at_position(i as f64 / 22050.0);
+		assert!(
+			frame.left.abs() < 0.005,
+			"frame {} should be silent, got {:?}",
+			i,
+			frame
+		);
+	}
+}
+
+#[test]
+fn interpolates_according_to_the_interpolation_mode() {
+	let sine = |position: f64| (position * 0.8).sin() as f32;
+	let frames: Vec<Frame> = (0..100).map(|i| Frame::from_mono(sine(i as f64))).collect();
+	let sound = |interpolation_mode| {
+		Sound::from_frames(
+			1,
+			frames.clone(),
+			SoundSettings::new().interpolation_mode(interpolation_mode),
+		)
+	};
+
+	assert_eq!(
+		sound(InterpolationMode::Nearest).get_frame_at_position(10.75),
+		frames[10]
+	);
+	let linear = sound(InterpolationMode::Linear).get_frame_at_position(10.75);
+	assert!((linear.left - (frames[10].left * 0.25 + frames[11].left * 0.75)).abs() < 0.0001);
+	// the sound's interpolation mode can be overridden
+	assert_eq!(
+		sound(InterpolationMode::Nearest)
+			.get_frame_at_position_with_interpolation(10.75, InterpolationMode::Linear),
+		linear
+	);
+
+	// each mode reconstructs the sine wave more accurately than the last
+	let max_error = |interpolation_mode| {
+		let sound = sound(interpolation_mode);
+		(200..800)
+			.map(|i| {
+				let position = i as f64 * 0.1 + 0.05;
+				(sound.get_frame_at_position(position).left - sine(position)).abs()
+			})
+			.fold(0.0, f32::max)
+	};
+	let linear_error = max_error(InterpolationMode::Linear);
+	let cubic_error = max_error(InterpolationMode::Cubic);
+	let sinc_error = max_error(InterpolationMode::Sinc);
+	assert!(cubic_error < linear_error);
+	assert!(sinc_error < cubic_error);
+	assert!(sinc_error < 0.001, "sinc error: {}", sinc_error);
+}
+
+#[cfg(feature = "wav")]
+#[test]
+fn reads_loop_points_and_markers_from_wav_files() {
+	use super::Marker;
+	use crate::{instance::InstanceSettings, manager::AudioManager};
+	use std::io::Cursor;
+
+	let mut cursor = Cursor::new(vec![]);
+	let mut writer = hound::WavWriter::new(
+		&mut cursor,
+		hound::WavSpec {
+			channels: 1,
+			sample_rate: 1000,
+			bits_per_sample: 16,
+			sample_format: hound::SampleFormat::Int,
+		},
+	)
+	.unwrap();
+	for _ in 0..1000 {
+		writer.write_sample(0i16).unwrap();
+	}
+	writer.finalize().unwrap();
+	let mut bytes = cursor.into_inner();
+
+	let words = |words: &[u32]| -> Vec<u8> {
+		words
+			.iter()
+			.flat_map(|word| word.to_le_bytes().to_vec())
+			.collect()
+	};
+	let chunk = |id: &[u8], data: &[u8]| -> Vec<u8> {
+		let mut chunk = id.to_vec();
+		chunk.extend(words(&[data.len() as u32]));
+		chunk.extend(data);
+		if data.len() % 2 == 1 {
+			chunk.push(0);
+		}
+		chunk
+	};
+	let data_chunk_id = u32::from_le_bytes(*b"data");
+	// one loop from frame 250 to frame 749
+	bytes.extend(chunk(
+		b"smpl",
+		&words(&[0, 0, 0, 60, 0, 0, 0, 1, 0, 0, 0, 250, 749, 0, 0]),
+	));
+	bytes.extend(chunk(
+		b"cue ",
+		&words(&[
+			3,
+			1,
+			0,
+			data_chunk_id,
+			0,
+			0,
+			500,
+			2,
+			0,
+			data_chunk_id,
+			0,
+			0,
+			100,
+			3,
+			0,
+			data_chunk_id,
+			0,
+			0,
+			900,
+		]),
+	));
+	let mut labels = b"adtl".to_vec();
+	labels.extend(chunk(
+		b"labl",
+		&[words(&[1]), b"chorus\0".to_vec()].concat(),
+	));
+	labels.extend(chunk(b"labl", &[words(&[2]), b"intro\0".to_vec()].concat()));
+	bytes.extend(chunk(b"LIST", &labels));
+	let riff_size = (bytes.len() - 8) as u32;
+	bytes[4..8].copy_from_slice(&riff_size.to_le_bytes());
+
+	let sound = Sound::from_wav_reader(Cursor::new(bytes.clone()), SoundSettings::new()).unwrap();
+	assert_eq!(sound.duration(), 1.0);
+	assert_eq!(sound.default_loop_start(), Some(0.25));
+	assert_eq!(sound.default_loop_end(), Some(0.75));
+	let markers = [
+		Marker {
+			name: Some("intro".into()),
+			position: 0.1,
+		},
+		Marker {
+			name: Some("chorus".into()),
+			position: 0.5,
+		},
+		Marker {
+			name: None,
+			position: 0.9,
+		},
+	];
+	assert_eq!(sound.markers(), &markers[..]);
+	assert_eq!(sound.marker("chorus"), Some(&markers[1]));
+	assert_eq!(sound.marker("outro"), None);
+
+	// the markers are also available from the sound's handle
+	let (mut manager, mut backend) = AudioManager::new_without_audio_thread(Default::default());
+	let mut handle = manager.add_sound(sound).unwrap();
+	assert_eq!(handle.markers(), &markers[..]);
+	assert_eq!(handle.marker("intro"), Some(&markers[0]));
+
+	// instances jump back to the loop start point at the end
+	// of the loop instead of the end of the sound
+	backend.process();
+	let instance_handle = handle.play(InstanceSettings::new()).unwrap();
+	for _ in 0..manager.sample_rate() * 9 / 10 {
+		backend.process();
+	}
+	assert!((instance_handle.position() - 0.4).abs() < 0.01);
+
+	// streamed sounds read the same metadata
+	let path = std::env::temp_dir().join(format!("kira-metadata-test-{}.wav", std::process::id()));
+	std::fs::write(&path, &bytes).unwrap();
+	let sound = Sound::stream_from_file(&path, SoundSettings::new()).unwrap();
+	std::fs::remove_file(&path).unwrap();
+	assert_eq!(sound.default_loop_start(), Some(0.25));
+	assert_eq!(sound.default_loop_end(), Some(0.75));
+	assert_eq!(sound.markers(), &markers[..]);
+
+	// the file doesn't have to start at the beginning of the reader
+	let mut reader = Cursor::new([b"junk".to_vec(), bytes.clone()].concat());
+	reader.set_position(4);
+	let sound = Sound::from_wav_reader(reader, SoundSettings::new()).unwrap();
+	assert_eq!(sound.duration(), 1.0);
+	assert_eq!(sound.default_loop_start(), Some(0.25));
+	assert_eq!(sound.markers(), &markers[..]);
+
+	// loop points set in the settings take priority, and the file's
+	// loop points aren't mixed with them
+	let sound = Sound::from_wav_reader(
+		Cursor::new(bytes),
+		SoundSettings::new().default_loop_start(0.5),
+	)
+	.unwrap();
+	assert_eq!(sound.default_loop_start(), Some(0.5));
+	assert_eq!(sound.default_loop_end(), None);
+}
+
+#[cfg(feature = "ogg")]
+#[test]
+fn reads_loop_points_from_ogg_comments() {
+	use super::metadata::Metadata;
+
+	let metadata = Metadata::from_ogg_comments(&[
+		("ARTIST".into(), "someone".into()),
+		("LoopStart".into(), "22050".into()),
+		("LOOPLENGTH".into(), "44100".into()),
+	]);
+	let settings = metadata.apply_to_settings(SoundSettings::new(), 44100);
+	assert_eq!(settings.default_loop_start, Some(0.5));
+	assert_eq!(settings.default_loop_end, Some(1.5));
+
+	// a loop end point set in the settings isn't mixed with the
+	// file's loop start point
+	let settings = metadata.apply_to_settings(SoundSettings::new().default_loop_end(1.0), 44100);
+	assert_eq!(settings.default_loop_start, None);
+	assert_eq!(settings.default_loop_end, Some(1.0));
+
+	// loops that end past the largest possible position are ignored
+	let metadata = Metadata::from_ogg_comments(&[
+		("LOOPSTART".into(), "22050".into()),
+		("LOOPLENGTH".into(), u64::MAX.to_string()),
+	]);
+	let settings = metadata.apply_to_settings(SoundSettings::new(), 44100);
+	assert_eq!(settings.default_loop_start, None);
+	assert_eq!(settings.default_loop_end, None);
+}
@@ -1,7 +1,61 @@
-use std::hash::Hash;
+use std::hash::{BuildHasherDefault, Hash, Hasher};
 
 use indexmap::IndexMap;
 
+/// A fast hasher for the small integer IDs used as keys
+/// on the audio thread.
+///
+/// IDs are handed out by the audio manager rather than by
+/// users, so there's no need for the protection against
+/// malicious keys that the standard library's hasher provides.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IdHasher {
+	hash: u64,
+}
+
+impl IdHasher {
+	// the same multiplier rustc's FxHasher uses
+	const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+	fn add(&mut self, value: u64) {
+		self.hash = (self.hash.rotate_left(5) ^ value).wrapping_mul(Self::SEED);
+	}
+}
+
+impl Hasher for IdHasher {
+	fn finish(&self) -> u64 {
+		self.hash
+	}
+
+	fn write(&mut self, bytes: &[u8]) {
+		for byte in bytes {
+			self.add(*byte as u64);
+		}
+	}
+
+	fn write_u8(&mut self, value: u8) {
+		self.add(value as u64);
+	}
+
+	fn write_u32(&mut self, value: u32) {
+		self.add(value as u64);
+	}
+
+	fn write_u64(&mut self, value: u64) {
+		self.add(value);
+	}
+
+	fn write_usize(&mut self, value: usize) {
+		self.add(value as u64);
+	}
+
+	fn write_isize(&mut self, value: isize) {
+		self.add(value as u64);
+	}
+}
+
+type IdBuildHasher = BuildHasherDefault<IdHasher>;
+
 /// A thin wrapper around `IndexMap`s that prevents any memory
 /// allocation from occurring after the `IndexMap` is created.
 ///
@@ -11,7 +65,7 @@ use indexmap::IndexMap;
 #[derive(Debug, Clone)]
 pub struct StaticIndexMap<K: Eq + Hash, V> {
 	capacity: usize,
-	index_map: IndexMap<K, V>,
+	index_map: IndexMap<K, V, IdBuildHasher>,
 }
 
 impl<K: Eq + Hash, V> StaticIndexMap<K, V> {
@@ -22,7 +76,7 @@ impl<K: Eq + Hash, V> StaticIndexMap<K, V> {
 			// capacity to make sure the map will never need to allocate
 			// memory to maintain the requested capacity.
 			// See here: https://github.com/rust-lang/hashbrown/pull/255
-			index_map: IndexMap::with_capacity(capacity * 2),
+			index_map: IndexMap::with_capacity_and_hasher(capacity * 2, Default::default()),
 		}
 	}
 
@@ -96,6 +150,22 @@ impl<K: Eq + Hash, V> StaticIndexMap<K, V> {
 	pub fn shift_remove_index(&mut self, index: usize) -> Option<(K, V)> {
 		self.index_map.shift_remove_index(index)
 	}
+
+	/// Moves every key value pair into another map, keeping
+	/// their order.
+	///
+	/// The other map should have enough capacity to hold all of
+	/// the items. This is used to move resources into a larger
+	/// map (created on another thread) without allocating memory.
+	pub fn move_into(&mut self, other: &mut Self) {
+		debug_assert!(
+			other.capacity() - other.len() >= self.len(),
+			"the other map doesn't have room for every item"
+		);
+		for (key, value) in self.index_map.drain(..) {
+			other.try_insert(key, value).ok();
+		}
+	}
 }
 
 impl<'a, K: Eq + Hash, V> IntoIterator for &'a StaticIndexMap<K, V> {
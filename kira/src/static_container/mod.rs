@@ -1,2 +1,6 @@
 pub mod index_map;
+pub mod slot_map;
 pub mod vec;
+
+#[cfg(test)]
+mod tests;
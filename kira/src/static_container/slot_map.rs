@@ -0,0 +1,185 @@
+/// The position of a resource in a [`StaticSlotMap`], along with
+/// the generation of the slot, which is incremented every time
+/// a resource is removed from the slot so that IDs of removed
+/// resources aren't mistaken for IDs of new ones.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+	feature = "serde_support",
+	derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct SlotIndex {
+	pub index: u32,
+	pub generation: u32,
+}
+
+/// An ID that refers to a slot in a [`StaticSlotMap`].
+pub trait SlotKey: Copy + Eq {
+	fn from_slot_index(index: SlotIndex) -> Self;
+
+	fn slot_index(&self) -> SlotIndex;
+}
+
+/// A fixed-capacity map whose keys are slot indices, so looking
+/// up a value is just indexing into a `Vec`.
+///
+/// Like the other static containers, this never allocates memory
+/// after it's created.
+#[derive(Debug, Clone)]
+pub struct StaticSlotMap<K: SlotKey, V> {
+	slots: Vec<Option<(K, V)>>,
+	len: usize,
+}
+
+impl<K: SlotKey, V> StaticSlotMap<K, V> {
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			slots: (0..capacity).map(|_| None).collect(),
+			len: 0,
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn capacity(&self) -> usize {
+		self.slots.len()
+	}
+
+	pub fn get(&self, key: &K) -> Option<&V> {
+		match self.slots.get(key.slot_index().index as usize) {
+			Some(Some((slot_key, value))) if slot_key == key => Some(value),
+			_ => None,
+		}
+	}
+
+	pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+		match self.slots.get_mut(key.slot_index().index as usize) {
+			Some(Some((slot_key, value))) if slot_key == key => Some(value),
+			_ => None,
+		}
+	}
+
+	/// Returns the key and value in the slot at `index`, if
+	/// the slot is occupied.
+	pub fn get_slot(&self, index: usize) -> Option<(&K, &V)> {
+		self.slots
+			.get(index)
+			.and_then(|slot| slot.as_ref().map(|(key, value)| (key, value)))
+	}
+
+	pub fn iter(&self) -> Iter<'_, K, V> {
+		Iter {
+			slots: self.slots.iter(),
+		}
+	}
+
+	pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+		IterMut {
+			slots: self.slots.iter_mut(),
+		}
+	}
+
+	pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+		self.iter_mut().map(|(_, value)| value)
+	}
+
+	/// Tries to put a value in the slot its key refers to.
+	///
+	/// - If the slot is outside of the map, returns the key and value
+	///   back through an `Err`
+	/// - Otherwise, returns `Ok` with whatever value was previously
+	///   in the slot, if any
+	///
+	/// Keys are handed out by the audio manager, which makes sure
+	/// an occupied slot is never handed out again, so a previous
+	/// value only comes back if the same key is inserted twice.
+	pub fn try_insert(&mut self, key: K, value: V) -> Result<Option<V>, (K, V)> {
+		let slot = match self.slots.get_mut(key.slot_index().index as usize) {
+			Some(slot) => slot,
+			None => return Err((key, value)),
+		};
+		let previous = slot.replace((key, value)).map(|(_, value)| value);
+		if previous.is_none() {
+			self.len += 1;
+		}
+		Ok(previous)
+	}
+
+	pub fn remove(&mut self, key: &K) -> Option<V> {
+		let slot = self.slots.get_mut(key.slot_index().index as usize)?;
+		match slot {
+			Some((slot_key, _)) if slot_key == key => {
+				self.len -= 1;
+				slot.take().map(|(_, value)| value)
+			}
+			_ => None,
+		}
+	}
+
+	/// Moves every value into the same slot of another map.
+	///
+	/// The other map should have at least as many slots as this
+	/// one. This is used to move resources into a larger map
+	/// (created on another thread) without allocating memory.
+	pub fn move_into(&mut self, other: &mut Self) {
+		for slot in &mut self.slots {
+			if let Some((key, value)) = slot.take() {
+				let result = other.try_insert(key, value);
+				debug_assert!(
+					result.is_ok(),
+					"the other map doesn't have a slot for every item"
+				);
+			}
+		}
+		self.len = 0;
+	}
+}
+
+/// Iterates over the occupied slots of a [`StaticSlotMap`].
+pub struct Iter<'a, K, V> {
+	slots: std::slice::Iter<'a, Option<(K, V)>>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+	type Item = (&'a K, &'a V);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.slots
+			.find_map(|slot| slot.as_ref().map(|(key, value)| (key, value)))
+	}
+}
+
+/// Mutably iterates over the occupied slots of a [`StaticSlotMap`].
+pub struct IterMut<'a, K, V> {
+	slots: std::slice::IterMut<'a, Option<(K, V)>>,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+	type Item = (&'a K, &'a mut V);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.slots
+			.find_map(|slot| slot.as_mut().map(|(key, value)| (&*key, value)))
+	}
+}
+
+impl<'a, K: SlotKey, V> IntoIterator for &'a StaticSlotMap<K, V> {
+	type Item = (&'a K, &'a V);
+
+	type IntoIter = Iter<'a, K, V>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
+impl<'a, K: SlotKey, V> IntoIterator for &'a mut StaticSlotMap<K, V> {
+	type Item = (&'a K, &'a mut V);
+
+	type IntoIter = IterMut<'a, K, V>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter_mut()
+	}
+}
@@ -0,0 +1,80 @@
+use super::{
+	index_map::StaticIndexMap,
+	slot_map::{SlotIndex, SlotKey, StaticSlotMap},
+	vec::StaticVec,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Key(SlotIndex);
+
+impl SlotKey for Key {
+	fn from_slot_index(index: SlotIndex) -> Self {
+		Self(index)
+	}
+
+	fn slot_index(&self) -> SlotIndex {
+		self.0
+	}
+}
+
+fn slot_key(index: u32) -> Key {
+	Key(SlotIndex {
+		index,
+		generation: 0,
+	})
+}
+
+#[test]
+fn static_vec_does_not_grow_past_its_capacity() {
+	let mut vec = StaticVec::new(2);
+	let capacity = vec.capacity();
+	for i in 0..capacity {
+		assert!(vec.try_push(i).is_ok());
+	}
+	// the vec is full, so pushing another item would allocate
+	assert!(vec.try_push(capacity).is_err());
+	assert_eq!(vec.len(), capacity);
+	assert_eq!(vec.capacity(), capacity);
+}
+
+#[test]
+fn index_map_moves_every_item_into_a_larger_map() {
+	let mut map = StaticIndexMap::new(2);
+	map.try_insert(1, "a").unwrap();
+	map.try_insert(2, "b").unwrap();
+	let mut other = StaticIndexMap::new(4);
+	map.move_into(&mut other);
+	assert_eq!(map.len(), 0);
+	assert_eq!(other.iter().collect::<Vec<_>>(), [(&1, &"a"), (&2, &"b")]);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic]
+fn index_map_panics_when_moving_into_a_smaller_map() {
+	let mut map = StaticIndexMap::new(2);
+	map.try_insert(1, "a").unwrap();
+	map.try_insert(2, "b").unwrap();
+	let mut other = StaticIndexMap::new(1);
+	map.move_into(&mut other);
+}
+
+#[test]
+fn slot_map_moves_every_item_into_a_larger_map() {
+	let mut map = StaticSlotMap::new(2);
+	map.try_insert(slot_key(1), "b").unwrap();
+	let mut other = StaticSlotMap::new(4);
+	map.move_into(&mut other);
+	assert_eq!(map.len(), 0);
+	assert_eq!(other.get(&slot_key(1)), Some(&"b"));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic]
+fn slot_map_panics_when_moving_into_a_smaller_map() {
+	let mut map = StaticSlotMap::new(2);
+	map.try_insert(slot_key(1), "b").unwrap();
+	let mut other = StaticSlotMap::new(1);
+	map.move_into(&mut other);
+}
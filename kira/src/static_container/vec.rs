@@ -66,13 +66,34 @@ impl<T> StaticVec<T> {
 	/// the user-facing out-of-capacity error to the audio thread in the
 	/// future, this will come in handy in that situation as well.
 	pub fn try_push(&mut self, value: T) -> Result<(), StaticVecFullError> {
-		if self.len() > self.capacity() {
+		if self.len() >= self.capacity() {
 			return Err(StaticVecFullError);
 		}
 		self.vec.push(value);
 		Ok(())
 	}
 
+	/// Tries to insert a value at `index`, shifting all of
+	/// the values after it to the right.
+	///
+	/// - If the `Vec` is full, returns `Err(StaticVecFullError)`
+	/// - Otherwise, returns `Ok(())`
+	pub fn try_insert(&mut self, index: usize, value: T) -> Result<(), StaticVecFullError> {
+		if self.len() >= self.capacity() {
+			return Err(StaticVecFullError);
+		}
+		self.vec.insert(index, value);
+		Ok(())
+	}
+
+	pub fn last(&self) -> Option<&T> {
+		self.vec.last()
+	}
+
+	pub fn pop(&mut self) -> Option<T> {
+		self.vec.pop()
+	}
+
 	pub fn drain(&mut self, range: impl RangeBounds<usize>) -> std::vec::Drain<T> {
 		self.vec.drain(range)
 	}
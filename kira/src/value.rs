@@ -1,8 +1,9 @@
 use std::ops::Range;
 
-use rand::{thread_rng, Rng};
-
-use crate::parameter::{handle::ParameterHandle, Mapping, ParameterId, Parameters};
+use crate::{
+	parameter::{handle::ParameterHandle, Mapping, ParameterId, Parameters},
+	random,
+};
 
 /// A value that something can be set to.
 #[derive(Debug, Copy, Clone)]
@@ -98,7 +99,15 @@ impl<T: From<f64> + Into<f64> + Copy> CachedValue<T> {
 	fn pick_random(lower: T, upper: T) -> T {
 		let lower: f64 = lower.into();
 		let upper: f64 = upper.into();
-		thread_rng().gen_range(lower..upper).into()
+		random::gen_range(lower..upper).into()
+	}
+
+	/// If the value is set to a random range, picks a new
+	/// raw value from that range.
+	pub(crate) fn pick_random_value(&mut self) {
+		if let Value::Random(lower, upper) = self.value {
+			self.last_value = Self::pick_random(lower, upper);
+		}
 	}
 
 	/// Sets the value.